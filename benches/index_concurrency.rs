@@ -0,0 +1,121 @@
+//! Throughput of the B-link `IndexTree` shared between threads, against the same tree behind
+//! one global lock. Run with `cargo bench --bench index_concurrency`.
+//!
+//! Two workloads, each at 1, 2, 4 and 8 threads:
+//! - insert: every thread inserts its own keys into an empty tree
+//! - mixed: 90% lookups and 10% inserts against a tree that already holds `PRELOAD` keys
+
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use oxidedb::{IndexKey, IndexTree, RowLocation};
+
+const OPS_PER_THREAD: usize = 200_000;
+const PRELOAD: usize = 200_000;
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+/// Spreads consecutive numbers over the whole key range, without repeats
+fn scramble(n: usize) -> i32 {
+    (n as u32).wrapping_mul(2_654_435_761) as i32
+}
+
+fn entry(key: i32) -> Option<Box<IndexKey<i32>>> {
+    Some(Box::new(IndexKey::new(key, Some(Box::new(RowLocation::new(key as i64, 0))))))
+}
+
+/// The operations a workload needs, so both trees run the same code
+trait SharedIndex: Sync {
+    fn insert(&self, key: i32);
+    fn contains(&self, key: i32) -> bool;
+}
+
+impl SharedIndex for IndexTree<i32> {
+    fn insert(&self, key: i32) {
+        IndexTree::insert(self, entry(key));
+    }
+
+    fn contains(&self, key: i32) -> bool {
+        self.search(&key).is_some()
+    }
+}
+
+/// The baseline: one lock around the whole tree
+struct GlobalLock(RwLock<IndexTree<i32>>);
+
+impl SharedIndex for GlobalLock {
+    fn insert(&self, key: i32) {
+        self.0.write().unwrap().insert(entry(key));
+    }
+
+    fn contains(&self, key: i32) -> bool {
+        self.0.read().unwrap().search(&key).is_some()
+    }
+}
+
+fn run_threads<I: SharedIndex>(index: &I, threads: usize, work: impl Fn(&I, usize) + Sync) -> Duration {
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for t in 0..threads {
+            let work = &work;
+            scope.spawn(move || work(index, t));
+        }
+    });
+    start.elapsed()
+}
+
+fn insert_workload<I: SharedIndex>(index: &I, threads: usize) -> Duration {
+    let elapsed = run_threads(index, threads, |index, t| {
+        for i in 0..OPS_PER_THREAD {
+            index.insert(scramble(t * OPS_PER_THREAD + i));
+        }
+    });
+    for n in 0..threads * OPS_PER_THREAD {
+        assert!(index.contains(scramble(n)), "key {} lost", scramble(n));
+    }
+    elapsed
+}
+
+fn mixed_workload<I: SharedIndex>(index: &I, threads: usize) -> Duration {
+    for n in 0..PRELOAD {
+        index.insert(scramble(n));
+    }
+    run_threads(index, threads, |index, t| {
+        let mut next_insert = PRELOAD + t * OPS_PER_THREAD;
+        for i in 0..OPS_PER_THREAD {
+            if i % 10 == 0 {
+                index.insert(scramble(next_insert));
+                next_insert += 1;
+            } else {
+                assert!(index.contains(scramble((t * 7919 + i * 104_729) % PRELOAD)));
+            }
+        }
+    })
+}
+
+fn ops_per_sec(threads: usize, elapsed: Duration) -> f64 {
+    (threads * OPS_PER_THREAD) as f64 / elapsed.as_secs_f64()
+}
+
+fn report(workload: &str, threads: usize, blink: Duration, global: Duration) {
+    let (blink, global) = (ops_per_sec(threads, blink), ops_per_sec(threads, global));
+    println!("{:<8} {:>8} {:>16.0} {:>16.0} {:>7.2}x", workload, threads, blink, global, blink / global);
+}
+
+fn global_lock() -> GlobalLock {
+    GlobalLock(RwLock::new(IndexTree::new()))
+}
+
+fn main() {
+    println!("{:<8} {:>8} {:>16} {:>16} {:>8}", "workload", "threads", "b-link ops/s", "global ops/s", "speedup");
+    for threads in THREADS {
+        let blink = insert_workload(&IndexTree::new(), threads);
+        let global = insert_workload(&global_lock(), threads);
+        report("insert", threads, blink, global);
+    }
+    for threads in THREADS {
+        let blink = mixed_workload(&IndexTree::new(), threads);
+        let global = mixed_workload(&global_lock(), threads);
+        report("mixed", threads, blink, global);
+    }
+}
@@ -1,7 +1,10 @@
-use std::fs::{File, OpenOptions};
-use std::io::{Write, Read, BufWriter, BufReader};
-use std::collections::HashMap;
-use crate::BPlusTree::{BPlusTree, Key, data};
+use std::fs::File;
+use std::io::{Read, BufReader};
+use std::path::{Path, PathBuf};
+use crate::UniversalBPlusTree::{BPlusTree, IntBPlusTree};
+use crate::UniversalKey::{Key, data};
+use crate::DiskIo;
+use crate::ErrorTypes::{Error, Result};
 
 /// Structure to serialize B+Tree node data
 #[derive(Debug)]
@@ -20,9 +23,9 @@ impl SerializedBTreeEntry {
         bytes
     }
 
-    fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
         if bytes.len() < 16 {
-            return Err("Insufficient bytes for SerializedBTreeEntry".to_string());
+            return Err(Error::corruption("Insufficient bytes for SerializedBTreeEntry"));
         }
         
         let key = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
@@ -44,145 +47,95 @@ impl SerializedBTreeEntry {
 pub struct BTreePersistence;
 
 impl BTreePersistence {
-    /// Save a B+Tree to file (using brute force key search)
-    pub fn save_btree(table_name: &str, btree: &BPlusTree) -> Result<(), String> {
-        let filename = format!("{}_btree.idx", table_name);
+    /// Save a B+Tree to `<table>_btree.idx` in `dir`, replacing the old file in one rename
+    pub fn save_btree(dir: &Path, table_name: &str, btree: &IntBPlusTree) -> Result<()> {
+        let filename = dir.join(format!("{}_btree.idx", table_name));
         
-        // Collect all entries from the B+Tree using brute force search
-        let entries = Self::collect_btree_entries_brute_force(btree);
+        let entries = Self::collect_btree_entries(btree);
         
-        // Write to file
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&filename)
-            .map_err(|e| format!("Failed to create B+Tree file: {}", e))?;
-        
-        let mut writer = BufWriter::new(file);
-        
-        // Write number of entries
+        // Number of entries, then each entry
         let num_entries = entries.len() as i32;
-        writer.write_all(&num_entries.to_le_bytes())
-            .map_err(|e| format!("Failed to write entry count: {}", e))?;
-        
-        // Write each entry
+        let mut bytes = num_entries.to_le_bytes().to_vec();
         for entry in entries {
-            let entry_bytes = entry.to_bytes();
-            writer.write_all(&entry_bytes)
-                .map_err(|e| format!("Failed to write entry: {}", e))?;
+            bytes.extend(entry.to_bytes());
         }
+        DiskIo::write_file_atomically(&filename, &bytes)?;
         
-        writer.flush()
-            .map_err(|e| format!("Failed to flush B+Tree file: {}", e))?;
-        
-        println!("Saved B+Tree for table '{}' with {} entries", table_name, num_entries);
         Ok(())
     }
     
-    /// Load a B+Tree from file
-    pub fn load_btree(table_name: &str) -> Result<BPlusTree, String> {
-        let filename = format!("{}_btree.idx", table_name);
+    /// The marker `<table>_btree.stale`, present while the table's pages may have changed
+    /// since its B+Tree was last saved
+    fn stale_marker(dir: &Path, table_name: &str) -> PathBuf {
+        dir.join(format!("{}_btree.stale", table_name))
+    }
+    
+    /// Records that the saved B+Tree of a table is about to fall behind its pages
+    pub fn mark_stale(dir: &Path, table_name: &str) -> Result<()> {
+        Ok(DiskIo::create_file(&Self::stale_marker(dir, table_name))?)
+    }
+    
+    /// Records that the saved B+Tree of a table matches its pages again
+    pub fn clear_stale(dir: &Path, table_name: &str) -> Result<()> {
+        Ok(DiskIo::remove_if_exists(&Self::stale_marker(dir, table_name))?)
+    }
+    
+    pub fn is_stale(dir: &Path, table_name: &str) -> bool {
+        Self::stale_marker(dir, table_name).exists()
+    }
+    
+    /// Load a B+Tree from `<table>_btree.idx` in `dir`
+    pub fn load_btree(dir: &Path, table_name: &str) -> Result<IntBPlusTree> {
+        let filename = dir.join(format!("{}_btree.idx", table_name));
         
-        if !std::path::Path::new(&filename).exists() {
+        if !filename.exists() {
             // File doesn't exist, return empty B+Tree
             return Ok(BPlusTree::new());
         }
         
-        let mut file = File::open(&filename)
-            .map_err(|e| format!("Failed to open B+Tree file: {}", e))?;
+        let file = File::open(&filename)?;
         
         let mut reader = BufReader::new(file);
         let mut buffer = Vec::new();
-        reader.read_to_end(&mut buffer)
-            .map_err(|e| format!("Failed to read B+Tree file: {}", e))?;
+        reader.read_to_end(&mut buffer)?;
         
-        if buffer.len() < 4 {
+        if buffer.is_empty() {
             return Ok(BPlusTree::new());
         }
-        
-        // Read number of entries
-        let num_entries = i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
-        
-        // Create new B+Tree
-        let mut btree = BPlusTree::new();
-        
-        // Read and insert each entry
-        let mut offset = 4;
-        for _ in 0..num_entries {
-            if offset + 16 > buffer.len() {
-                break;
-            }
-            
-            let entry = SerializedBTreeEntry::from_bytes(&buffer[offset..offset + 16])?;
-            
-            // Create and insert key
-            let data_ptr = Box::new(data::new(entry.page_id, entry.offset));
-            let key_entry = Box::new(Key::new(entry.key, Some(data_ptr)));
-            btree.insert(Some(key_entry));
-            
-            offset += 16;
+        if buffer.len() < 4 {
+            return Err(Error::corruption(format!("B+Tree file of '{}' is truncated", table_name)));
         }
         
-        println!("Loaded B+Tree for table '{}' with {} entries", table_name, num_entries);
-        Ok(btree)
-    }
-    
-    /// Collect all entries from a B+Tree using brute force search
-    /// This is a workaround since we don't have a proper traverse method
-    fn collect_btree_entries_brute_force(btree: &BPlusTree) -> Vec<SerializedBTreeEntry> {
-        let mut entries = Vec::new();
-        
-        // Test a reasonable range of keys (both positive and negative)
-        // This is not ideal but works for our current B+Tree implementation
-        
-        // Test positive keys
-        for key in 1..10000 {
-            if let Some(data_ref) = btree.search(key) {
-                entries.push(SerializedBTreeEntry {
-                    key,
-                    page_id: data_ref.page_id,
-                    offset: data_ref.offset,
-                });
-            }
+        // Read number of entries; the file must hold exactly that many
+        let num_entries = i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+        if num_entries < 0 || buffer.len() != 4 + num_entries as usize * 16 {
+            return Err(Error::corruption(format!(
+                "B+Tree file of '{}' holds {} bytes, expected {} entries",
+                table_name, buffer.len(), num_entries
+            )));
         }
         
-        // Test negative keys
-        for key in -1000..0 {
-            if let Some(data_ref) = btree.search(key) {
-                entries.push(SerializedBTreeEntry {
-                    key,
-                    page_id: data_ref.page_id,
-                    offset: data_ref.offset,
-                });
-            }
+        // Entries were saved in key order, so the tree is built bottom-up
+        let mut entries = Vec::with_capacity(num_entries as usize);
+        for chunk in buffer[4..].chunks_exact(16) {
+            let entry = SerializedBTreeEntry::from_bytes(chunk)?;
+            entries.push(Key::new(entry.key, Some(Box::new(data::new(entry.page_id, entry.offset)))));
         }
+        let btree = BPlusTree::bulk_load(entries)
+            .map_err(|e| Error::corruption(format!("B+Tree file of '{}' is out of order: {}", table_name, e)))?;
         
-        entries
-    }
-    
-    /// Save all B+Trees for all tables
-    pub fn save_all_btrees(table_btrees: &HashMap<String, BPlusTree>) -> Result<(), String> {
-        for (table_name, btree) in table_btrees {
-            Self::save_btree(table_name, btree)?;
-        }
-        Ok(())
+        Ok(btree)
     }
     
-    /// Load all B+Trees for existing tables
-    pub fn load_all_btrees(table_names: &[String]) -> Result<HashMap<String, BPlusTree>, String> {
-        let mut btrees = HashMap::new();
-        
-        for table_name in table_names {
-            let btree = Self::load_btree(table_name)?;
-            btrees.insert(table_name.clone(), btree);
-        }
-        
-        Ok(btrees)
+    /// Every key of a B+Tree with the row it points at, in key order
+    fn collect_btree_entries(btree: &IntBPlusTree) -> Vec<SerializedBTreeEntry> {
+        btree.entries()
+            .into_iter()
+            .filter_map(|entry| entry.data.map(|data_ref| SerializedBTreeEntry {
+                key: entry.key,
+                page_id: data_ref.page_id,
+                offset: data_ref.offset,
+            }))
+            .collect()
     }
 }
-
-/// Manual save function for convenience
-pub fn save_btree_manually(table_name: &str, btree: &BPlusTree) -> Result<(), String> {
-    BTreePersistence::save_btree(table_name, btree)
-}
\ No newline at end of file
@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+use crate::DatabaseHandler::Database;
+use crate::DiskIo;
+use crate::ErrorTypes::{Error, Result};
+use crate::FileWriter::checksum;
+use crate::TransactionManager::{TransactionManager, LOG_FILE};
+
+/// First bytes of a backup archive
+const MAGIC: &[u8; 8] = b"OXIDEBAK";
+const FORMAT_VERSION: u32 = 1;
+/// How often a backup starts over because the schema changed while it ran
+const ATTEMPTS: usize = 3;
+
+/// A file of the database directory, by name, with its contents
+pub(crate) type FileCopy = (String, Vec<u8>);
+
+/// Archive layout, all integers little-endian: `MAGIC`, the format version (u32) and the
+/// file count (u32); then per file its name length (u32), name, length (u64), checksum (u64)
+/// and bytes; then the checksum of everything before it.
+fn encode(files: &[FileCopy]) -> Vec<u8> {
+    let mut archive = MAGIC.to_vec();
+    archive.extend(FORMAT_VERSION.to_le_bytes());
+    archive.extend((files.len() as u32).to_le_bytes());
+    for (file_name, bytes) in files {
+        archive.extend((file_name.len() as u32).to_le_bytes());
+        archive.extend(file_name.as_bytes());
+        archive.extend((bytes.len() as u64).to_le_bytes());
+        archive.extend(checksum(bytes).to_le_bytes());
+        archive.extend(bytes);
+    }
+    archive.extend(checksum(&archive).to_le_bytes());
+    archive
+}
+
+/// Reads an archive back into its files, checking the archive's checksum and each file's.
+fn decode(archive: &[u8]) -> std::result::Result<Vec<FileCopy>, String> {
+    if archive.len() < MAGIC.len() + 16 || &archive[..MAGIC.len()] != MAGIC {
+        return Err("not an OxideDB backup".to_string());
+    }
+    let (body, trailer) = archive.split_at(archive.len() - 8);
+    if checksum(body).to_le_bytes() != trailer {
+        return Err("archive checksum does not match; it is damaged or cut short".to_string());
+    }
+
+    let mut reader = ArchiveReader { bytes: body, pos: MAGIC.len() };
+    let version = u32::from_le_bytes(reader.array()?);
+    if version != FORMAT_VERSION {
+        return Err(format!("unsupported backup format version {}", version));
+    }
+    let count = u32::from_le_bytes(reader.array()?);
+    let mut files: Vec<FileCopy> = Vec::new();
+    for _ in 0..count {
+        let name_len = u32::from_le_bytes(reader.array()?) as usize;
+        let file_name = String::from_utf8(reader.take(name_len)?.to_vec())
+            .map_err(|_| "file name is not UTF-8".to_string())?;
+        // Names come from one directory; anything else would write outside the target
+        if file_name.is_empty() || file_name == "." || file_name == ".." || file_name.contains(['/', '\\']) {
+            return Err(format!("invalid file name '{}'", file_name));
+        }
+        if files.iter().any(|(name, _)| *name == file_name) {
+            return Err(format!("file '{}' appears more than once", file_name));
+        }
+        let len = u64::from_le_bytes(reader.array()?) as usize;
+        let expected = u64::from_le_bytes(reader.array()?);
+        let bytes = reader.take(len)?;
+        if checksum(bytes) != expected {
+            return Err(format!("checksum of '{}' does not match", file_name));
+        }
+        files.push((file_name, bytes.to_vec()));
+    }
+    if reader.pos != body.len() {
+        return Err(format!("{} unexpected bytes after the last file", body.len() - reader.pos));
+    }
+    Ok(files)
+}
+
+struct ArchiveReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ArchiveReader<'a> {
+    fn take(&mut self, len: usize) -> std::result::Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "archive ends in the middle of a file".to_string())?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> std::result::Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+/// Copies the catalog and every table into one archive at `path`, as one transaction's
+/// snapshot sees them. Each table is only locked while it is read, and only against its
+/// writers. The transaction keeps vacuum from removing versions the snapshot needs and
+/// holds off drops, truncates, renames and rewrites; a schema change that slips in between
+/// reading the catalog and the tables starts the backup over.
+pub(crate) fn backup(db: &Database, path: &Path) -> Result<usize> {
+    let tables = db.query_handler();
+    for _ in 0..ATTEMPTS {
+        let txn = db.begin()?;
+        let (table_names, catalog) = tables.backup_catalog()?;
+        let mut files = catalog.clone();
+        for table_name in &table_names {
+            files.extend(tables.backup_table(table_name, txn.snapshot())
+                .map_err(|e| e.context(format_args!("Failed to back up table '{}'", table_name)))?);
+        }
+        files.push((LOG_FILE.to_string(), TransactionManager::log_for_copy(txn.snapshot())));
+        let (_, catalog_after) = tables.backup_catalog()?;
+        txn.rollback()?;
+        if catalog_after != catalog {
+            continue;
+        }
+
+        DiskIo::write_file_atomically(path, &encode(&files))
+            .map_err(|e| Error::from(e).context(format_args!("Failed to write backup '{}'", path.display())))?;
+        return Ok(files.len());
+    }
+    Err(Error::InvalidOperation(format!(
+        "The schema changed during each of {} backup attempts; try again when it is quiet", ATTEMPTS)))
+}
+
+/// A directory next to `dir`, named after it with `suffix`.
+fn sibling(dir: &Path, suffix: &str) -> PathBuf {
+    let mut name = dir.file_name().unwrap_or_default().to_owned();
+    name.push(suffix);
+    dir.with_file_name(name)
+}
+
+/// Replaces `dir` with the files of the archive at `path`. Every checksum is checked before
+/// anything is written; the files then go to a directory beside `dir`, which takes its place
+/// in two renames.
+pub(crate) fn restore(path: &Path, dir: &Path) -> Result<usize> {
+    let archive = std::fs::read(path)
+        .map_err(|e| Error::from(e).context(format_args!("Failed to read backup '{}'", path.display())))?;
+    let files = decode(&archive)
+        .map_err(|message| Error::corruption(format!("Backup '{}': {}", path.display(), message)))?;
+
+    let staging = sibling(dir, ".restoring");
+    let replaced = sibling(dir, ".replaced");
+    for leftover in [&staging, &replaced] {
+        if leftover.exists() {
+            std::fs::remove_dir_all(leftover)?;
+        }
+    }
+    std::fs::create_dir_all(&staging)
+        .map_err(|e| Error::from(e).context(format_args!("Failed to create '{}'", staging.display())))?;
+    for (file_name, bytes) in &files {
+        DiskIo::write_file_atomically(&staging.join(file_name), bytes)
+            .map_err(|e| Error::from(e).context(format_args!("Failed to write '{}'", file_name)))?;
+    }
+
+    if dir.exists() {
+        DiskIo::rename(dir, &replaced)?;
+        DiskIo::rename(&staging, dir)?;
+        std::fs::remove_dir_all(&replaced)?;
+    } else {
+        DiskIo::rename(&staging, dir)?;
+    }
+    Ok(files.len())
+}
@@ -0,0 +1,148 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use crate::ValueTypes::BlobRef;
+use crate::DiskIo;
+
+/// BLOBs up to this many bytes are kept inline in the row; larger ones go to overflow pages
+pub const BLOB_INLINE_LIMIT: usize = 512;
+
+// Overflow page layout: next page id (u64, NO_NEXT_PAGE at the end of a chain), bytes used (u32), payload
+const OVERFLOW_HEADER_SIZE: usize = 12;
+const OVERFLOW_PAYLOAD_SIZE: usize = crate::PAGE_SIZE - OVERFLOW_HEADER_SIZE;
+const NO_NEXT_PAGE: u64 = u64::MAX;
+
+/// Chains of overflow pages for a table's large BLOB values, kept in `<table>.ovf`.
+pub struct BlobStore {
+    file_name: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(file_name: PathBuf) -> BlobStore {
+        BlobStore { file_name }
+    }
+
+    pub fn write_bytes(&self, bytes: &[u8]) -> io::Result<BlobRef> {
+        self.write_from(&mut &bytes[..])
+    }
+
+    /// Streams `reader` into a new chain of pages appended to the overflow file.
+    pub fn write_from<R: Read>(&self, reader: &mut R) -> io::Result<BlobRef> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.file_name)?;
+
+        let first_page = file.metadata()?.len().div_ceil(crate::PAGE_SIZE as u64);
+        let mut page_id = first_page;
+        let mut len = 0u64;
+
+        let mut current = vec![0u8; OVERFLOW_PAYLOAD_SIZE];
+        let mut used = read_full(reader, &mut current)?;
+        loop {
+            // Read one chunk ahead so we know whether this page links to another
+            let mut next = vec![0u8; OVERFLOW_PAYLOAD_SIZE];
+            let next_used = if used == OVERFLOW_PAYLOAD_SIZE { read_full(reader, &mut next)? } else { 0 };
+            let next_page = if next_used > 0 { page_id + 1 } else { NO_NEXT_PAGE };
+
+            let mut page = Vec::with_capacity(crate::PAGE_SIZE);
+            page.extend_from_slice(&next_page.to_le_bytes());
+            page.extend_from_slice(&(used as u32).to_le_bytes());
+            page.extend_from_slice(&current[..used]);
+            page.resize(crate::PAGE_SIZE, 0);
+
+            file.seek(SeekFrom::Start(page_id * crate::PAGE_SIZE as u64))?;
+            DiskIo::write_all(&mut file, &page)?;
+            len += used as u64;
+
+            if next_page == NO_NEXT_PAGE {
+                break;
+            }
+            current = next;
+            used = next_used;
+            page_id += 1;
+        }
+        // The pages must be in place before a row refers to them
+        file.sync_data()?;
+
+        Ok(BlobRef { first_page, len })
+    }
+
+    pub fn open(&self, blob_ref: BlobRef) -> io::Result<BlobReader> {
+        let file = File::open(&self.file_name)?;
+        Ok(BlobReader::Overflow {
+            file,
+            next_page: blob_ref.first_page,
+            buffer: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    pub fn read_all(&self, blob_ref: BlobRef) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(blob_ref.len as usize);
+        self.open(blob_ref)?.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Streaming reader over a BLOB value, whether it is stored inline or in overflow pages.
+pub enum BlobReader {
+    Inline(Cursor<Vec<u8>>),
+    Overflow {
+        file: File,
+        next_page: u64,
+        buffer: Vec<u8>,
+        pos: usize,
+    },
+}
+
+impl BlobReader {
+    pub fn from_bytes(bytes: Vec<u8>) -> BlobReader {
+        BlobReader::Inline(Cursor::new(bytes))
+    }
+}
+
+impl Read for BlobReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        match self {
+            BlobReader::Inline(cursor) => cursor.read(out),
+            BlobReader::Overflow { file, next_page, buffer, pos } => {
+                while *pos == buffer.len() {
+                    if *next_page == NO_NEXT_PAGE {
+                        return Ok(0);
+                    }
+                    let mut page = vec![0u8; crate::PAGE_SIZE];
+                    file.seek(SeekFrom::Start(*next_page * crate::PAGE_SIZE as u64))?;
+                    file.read_exact(&mut page)?;
+
+                    *next_page = u64::from_le_bytes(page[0..8].try_into().unwrap());
+                    let used = u32::from_le_bytes(page[8..12].try_into().unwrap()) as usize;
+                    if used > OVERFLOW_PAYLOAD_SIZE {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "Corrupt overflow page"));
+                    }
+                    *buffer = page[OVERFLOW_HEADER_SIZE..OVERFLOW_HEADER_SIZE + used].to_vec();
+                    *pos = 0;
+                }
+                let n = out.len().min(buffer.len() - *pos);
+                out[..n].copy_from_slice(&buffer[*pos..*pos + n]);
+                *pos += n;
+                Ok(n)
+            }
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use crate::DatabaseHandler::Database;
+use crate::ErrorTypes::{Error, Result};
+use crate::JsonLines;
+use crate::MetaEnum::DataArray;
+use crate::TableCreationHandler::TableColumn;
+
+/// How a CSV file is laid out, for `Database::copy_from_csv` and `Database::copy_to_csv`.
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    /// Separates the fields of a record
+    pub delimiter: char,
+    /// Encloses a field holding the delimiter, the quote, or a line break; doubled inside it
+    pub quote: char,
+    /// Whether the first record holds column names. On import they say which column each
+    /// field goes to, unless `columns` does; on export they are written.
+    pub header: bool,
+    /// The unquoted field that stands for NULL. A quoted field is never NULL, so with the
+    /// default empty marker `""` is an empty string.
+    pub null: String,
+    /// The table columns the fields map to, in file order. By default the header's names,
+    /// or every column in table order without a header. Columns left out take their
+    /// default, or NULL.
+    pub columns: Option<Vec<String>>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            quote: '"',
+            header: true,
+            null: String::new(),
+            columns: None,
+        }
+    }
+}
+
+/// One field as read, remembering whether it was quoted so a quoted NULL marker stays text.
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+/// A record with the line it starts on.
+struct Record {
+    line: usize,
+    fields: Vec<Field>,
+}
+
+/// Splits CSV text into records. A quoted field may hold delimiters, doubled quotes and line
+/// breaks; lines end in `\n` or `\r\n`. Empty lines are skipped.
+fn read_records(text: &str, options: &CsvOptions) -> std::result::Result<Vec<Record>, (usize, String)> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = Field { text: String::new(), quoted: false };
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.strip_prefix('\u{feff}').unwrap_or(text).chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == options.quote {
+                if chars.peek() == Some(&options.quote) {
+                    chars.next();
+                    field.text.push(c);
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.text.push(c);
+            }
+            continue;
+        }
+        match c {
+            c if c == options.quote && field.text.is_empty() && !field.quoted => {
+                in_quotes = true;
+                field.quoted = true;
+            }
+            c if c == options.delimiter => {
+                fields.push(std::mem::replace(&mut field, Field { text: String::new(), quoted: false }));
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if !fields.is_empty() || !field.text.is_empty() || field.quoted {
+                    fields.push(std::mem::replace(&mut field, Field { text: String::new(), quoted: false }));
+                    records.push(Record { line: record_line, fields: std::mem::take(&mut fields) });
+                }
+                line += 1;
+                record_line = line;
+            }
+            c => field.text.push(c),
+        }
+    }
+    if in_quotes {
+        return Err((record_line, "quoted field is not closed before the end of the file".to_string()));
+    }
+    if !fields.is_empty() || !field.text.is_empty() || field.quoted {
+        fields.push(field);
+        records.push(Record { line: record_line, fields });
+    }
+    Ok(records)
+}
+
+/// Writes one record; `None` is NULL.
+fn write_record<W: Write>(writer: &mut W, fields: &[Option<String>], options: &CsvOptions) -> std::io::Result<()> {
+    let mut line = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            line.push(options.delimiter);
+        }
+        match field {
+            None => line.push_str(&options.null),
+            Some(text) => {
+                let needs_quotes = *text == options.null
+                    || text.contains([options.delimiter, options.quote, '\n', '\r']);
+                if needs_quotes {
+                    line.push(options.quote);
+                    for c in text.chars() {
+                        if c == options.quote {
+                            line.push(c);
+                        }
+                        line.push(c);
+                    }
+                    line.push(options.quote);
+                } else {
+                    line.push_str(text);
+                }
+            }
+        }
+    }
+    line.push('\n');
+    writer.write_all(line.as_bytes())
+}
+
+/// Positions in the table of the columns the fields go to, in file order.
+fn map_columns(table_name: &str, columns: &[TableColumn], names: &[String]) -> Result<Vec<usize>> {
+    let mut positions: Vec<usize> = Vec::with_capacity(names.len());
+    for name in names {
+        let position = columns.iter()
+            .position(|c| c.column_name == name.trim())
+            .ok_or_else(|| Error::SchemaMismatch(format!("Table '{}' has no column '{}'", table_name, name.trim())))?;
+        if positions.contains(&position) {
+            return Err(Error::SchemaMismatch(format!("Column '{}' given more than once", name.trim())));
+        }
+        positions.push(position);
+    }
+    Ok(positions)
+}
+
+/// Turns one record into a row, or says what is wrong with it.
+fn parse_record(record: &Record, columns: &[TableColumn], positions: &[usize], options: &CsvOptions) -> std::result::Result<Vec<DataArray>, String> {
+    if record.fields.len() != positions.len() {
+        return Err(format!("expected {} fields, found {}", positions.len(), record.fields.len()));
+    }
+    let mut values: Vec<Option<DataArray>> = vec![None; columns.len()];
+    for (field, position) in record.fields.iter().zip(positions) {
+        let column = &columns[*position];
+        let value = if !field.quoted && field.text == options.null {
+            DataArray::NULL
+        } else {
+            column.column_type.parse_value(&field.text)
+                .map_err(|e| format!("column '{}': {}", column.column_name, e))?
+        };
+        values[*position] = Some(value);
+    }
+    complete_row(columns, values)
+}
+
+/// Fills the columns a record left out with their default, or NULL, and checks NOT NULL.
+pub(crate) fn complete_row(columns: &[TableColumn], values: Vec<Option<DataArray>>) -> std::result::Result<Vec<DataArray>, String> {
+    let row: Vec<DataArray> = columns.iter()
+        .zip(values)
+        .map(|(column, value)| value
+            .or_else(|| column.default_value.clone())
+            .unwrap_or(DataArray::NULL))
+        .collect();
+    if let Some(column) = columns.iter().zip(&row).find(|(c, v)| c.not_null && v.is_null()).map(|(c, _)| c) {
+        return Err(format!("column '{}' is NOT NULL", column.column_name));
+    }
+    Ok(row)
+}
+
+/// Reads a CSV file into a table through the bulk insert path. Every record is parsed
+/// before anything is inserted; if any fails, `Error::Import` lists them all with their
+/// lines and the table is left as it was.
+pub(crate) fn import(db: &Database, table_name: &str, path: &Path, options: &CsvOptions) -> Result<usize> {
+    let source = path.display().to_string();
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| Error::from(e).context(format_args!("Failed to read '{}'", source)))?;
+    let mut records = read_records(&text, options)
+        .map_err(|error| Error::Import { file: source.clone(), errors: vec![error] })?;
+
+    let table = db.table(table_name)?;
+    let columns = table.columns();
+    let header = match options.header && !records.is_empty() {
+        true => Some(records.remove(0)),
+        false => None,
+    };
+    let names: Vec<String> = match (&options.columns, header) {
+        (Some(names), _) => names.clone(),
+        (None, Some(header)) => header.fields.into_iter().map(|field| field.text).collect(),
+        (None, None) => columns.iter().map(|c| c.column_name.clone()).collect(),
+    };
+    let positions = map_columns(table_name, columns, &names)?;
+
+    let mut rows = Vec::with_capacity(records.len());
+    let mut errors = Vec::new();
+    for record in &records {
+        match parse_record(record, columns, &positions, options) {
+            Ok(row) => rows.push(row),
+            Err(message) => errors.push((record.line, message)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Error::Import { file: source, errors });
+    }
+    let count = rows.len();
+    table.bulk_insert(rows)?;
+    Ok(count)
+}
+
+/// Writes every row of a table to a CSV file, as one snapshot. BLOBs are written in their
+/// `\x` hex form, which reads back in.
+pub(crate) fn export(db: &Database, table_name: &str, path: &Path, options: &CsvOptions) -> Result<usize> {
+    let columns = db.columns(table_name)?;
+    let names: Vec<String> = match &options.columns {
+        Some(names) => names.clone(),
+        None => columns.iter().map(|c| c.column_name.clone()).collect(),
+    };
+    let positions = map_columns(table_name, &columns, &names)?;
+
+    let txn = db.begin()?;
+    let rows = txn.scan(table_name)?;
+    let count = rows.len();
+    let source = path.display().to_string();
+    let failed = |e: std::io::Error| Error::from(e).context(format_args!("Failed to write '{}'", source));
+    let mut writer = BufWriter::new(File::create(path).map_err(failed)?);
+    if options.header {
+        let header: Vec<Option<String>> = names.iter().map(|name| Some(name.clone())).collect();
+        write_record(&mut writer, &header, options).map_err(failed)?;
+    }
+    for mut row in rows {
+        db.query_handler().inline_blobs(table_name, &mut row)?;
+        let fields: Vec<Option<String>> = positions.iter()
+            .map(|position| match &row[*position] {
+                DataArray::NULL => None,
+                value => Some(value.to_string()),
+            })
+            .collect();
+        write_record(&mut writer, &fields, options).map_err(failed)?;
+    }
+    writer.flush().map_err(failed)?;
+    txn.commit()?;
+    Ok(count)
+}
+
+/// A word, a quoted string or a punctuation mark of a COPY statement
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Symbol(char),
+}
+
+fn tokenize(statement: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = statement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            text.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => text.push(c),
+                        None => return Err(syntax_error("string is not closed")),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            '(' | ')' | ',' | ';' => tokens.push(Token::Symbol(c)),
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.peek().copied().filter(|c| c.is_alphanumeric() || *c == '_') {
+                    chars.next();
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => return Err(syntax_error(&format!("unexpected '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+fn syntax_error(what: &str) -> Error {
+    Error::InvalidOperation(format!("Invalid COPY statement: {}", what))
+}
+
+/// Reads tokens of a COPY statement front to back.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// `FORMAT json` was given
+    json: bool,
+    /// An option only CSV has was given
+    csv_only: bool,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn at_symbol(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<()> {
+        match self.next() {
+            Some(Token::Symbol(c)) if c == symbol => Ok(()),
+            other => Err(syntax_error(&format!("expected '{}', found {:?}", symbol, other))),
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            other => Err(syntax_error(&format!("expected {}, found {:?}", what, other))),
+        }
+    }
+
+    fn text(&mut self, what: &str) -> Result<String> {
+        match self.next() {
+            Some(Token::Text(text)) => Ok(text),
+            other => Err(syntax_error(&format!("expected {} in quotes, found {:?}", what, other))),
+        }
+    }
+
+    fn single_char(&mut self, what: &str) -> Result<char> {
+        let text = self.text(what)?;
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c != '\n' && c != '\r' => Ok(c),
+            _ => Err(syntax_error(&format!("{} must be one character", what))),
+        }
+    }
+
+    /// One option of the WITH list
+    fn option(&mut self, options: &mut CsvOptions) -> Result<()> {
+        let name = self.word("an option")?;
+        self.csv_only |= ["HEADER", "DELIMITER", "QUOTE", "NULL"].iter().any(|o| name.eq_ignore_ascii_case(o));
+        match name.to_ascii_uppercase().as_str() {
+            "HEADER" => {
+                let value = match self.peek() {
+                    Some(Token::Word(word)) if ["TRUE", "ON"].iter().any(|w| word.eq_ignore_ascii_case(w)) => Some(true),
+                    Some(Token::Word(word)) if ["FALSE", "OFF"].iter().any(|w| word.eq_ignore_ascii_case(w)) => Some(false),
+                    _ => None,
+                };
+                if value.is_some() {
+                    self.next();
+                }
+                options.header = value.unwrap_or(true);
+            }
+            "DELIMITER" => options.delimiter = self.single_char("DELIMITER")?,
+            "QUOTE" => options.quote = self.single_char("QUOTE")?,
+            "NULL" => options.null = self.text("NULL")?,
+            "FORMAT" => {
+                let format = self.word("a format")?;
+                self.json = match format.to_ascii_uppercase().as_str() {
+                    "CSV" => false,
+                    "JSON" => true,
+                    _ => return Err(syntax_error(&format!("format {} is not supported", format))),
+                };
+            }
+            _ => return Err(syntax_error(&format!("unknown option {}", name))),
+        }
+        Ok(())
+    }
+}
+
+/// Runs `COPY table [(column, ...)] FROM | TO 'file' [WITH (option, ...)]`. The options are
+/// `HEADER [true | false]`, `DELIMITER 'c'`, `QUOTE 'c'`, `NULL 'marker'` and
+/// `FORMAT csv | json`; the rest are as in `CsvOptions::default()`. `FORMAT json` copies
+/// JSON Lines and takes none of the others. Returns how many rows were copied.
+pub(crate) fn execute(db: &Database, statement: &str) -> Result<usize> {
+    let mut parser = Parser { tokens: tokenize(statement)?, position: 0, json: false, csv_only: false };
+    if !parser.at_keyword("COPY") {
+        return Err(syntax_error("expected COPY"));
+    }
+    parser.next();
+    let table_name = parser.word("a table name")?;
+    let mut options = CsvOptions::default();
+    if parser.at_symbol('(') {
+        parser.next();
+        let mut columns = vec![parser.word("a column name")?];
+        while parser.at_symbol(',') {
+            parser.next();
+            columns.push(parser.word("a column name")?);
+        }
+        parser.expect_symbol(')')?;
+        options.columns = Some(columns);
+    }
+    let from = match parser.word("FROM or TO")?.to_ascii_uppercase().as_str() {
+        "FROM" => true,
+        "TO" => false,
+        other => return Err(syntax_error(&format!("expected FROM or TO, found {}", other))),
+    };
+    let path = parser.text("a file name")?;
+    if parser.at_keyword("WITH") {
+        parser.next();
+    }
+    if parser.at_symbol('(') {
+        parser.next();
+        parser.option(&mut options)?;
+        while parser.at_symbol(',') {
+            parser.next();
+            parser.option(&mut options)?;
+        }
+        parser.expect_symbol(')')?;
+    } else {
+        while matches!(parser.peek(), Some(Token::Word(_))) {
+            parser.option(&mut options)?;
+        }
+    }
+    if parser.at_symbol(';') {
+        parser.next();
+    }
+    if let Some(token) = parser.peek() {
+        return Err(syntax_error(&format!("unexpected {:?} at the end", token)));
+    }
+
+    if parser.json && (parser.csv_only || options.columns.is_some()) {
+        return Err(syntax_error("FORMAT json takes no column list and no CSV options"));
+    }
+    match (parser.json, from) {
+        (false, true) => import(db, &table_name, Path::new(&path), &options),
+        (false, false) => export(db, &table_name, Path::new(&path), &options),
+        (true, true) => JsonLines::import(db, &table_name, Path::new(&path)),
+        (true, false) => JsonLines::export(db, &table_name, Path::new(&path)),
+    }
+}
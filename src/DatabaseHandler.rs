@@ -0,0 +1,384 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use crate::MetaEnum::DataArray;
+use crate::QueryPredicate::Predicate;
+use crate::BlobStore::BlobReader;
+use crate::CsvCopy::{self, CsvOptions};
+use crate::JsonLines;
+use crate::SqlDump;
+use crate::Backup;
+use crate::ErrorTypes::{Error, Result};
+use crate::TableHandle::{Table, Query};
+use crate::TableCreationHandler::{TableCreationHandler, TableColumn};
+use crate::TableMetaHandler::{TableMetaHandler, FIRST_USER_TABLE_ID};
+use crate::TableQueryHandler::{TableQueryHandler, StringOverflowPolicy};
+use crate::TransactionManager::{TransactionManager, Snapshot};
+use crate::TransactionHandle::Transaction;
+use crate::LockManager::{LockManager, LockMode};
+use crate::FileWriter::File_Handler;
+
+/// Settings for `Database::open`
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Create the directory when it does not exist yet
+    pub create_if_missing: bool,
+    /// Load the primary key indexes of existing tables when the database opens; otherwise
+    /// each is loaded when its table is first used
+    pub load_indexes: bool,
+    /// What to do with STRING/CHAR values longer than their column
+    pub string_overflow: StringOverflowPolicy,
+    /// How long a write waits for a row another transaction has locked; `None` waits
+    /// indefinitely. A transaction can change it with `Transaction::set_lock_timeout`.
+    pub lock_timeout: Option<Duration>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            create_if_missing: true,
+            load_indexes: true,
+            string_overflow: StringOverflowPolicy::Reject,
+            lock_timeout: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+/// An open database: a directory holding the system catalog, table data and index files.
+/// The handle owns the catalog, the B+Tree manager and the per-table file handlers, so one
+/// process can have several independent databases open at once.
+///
+/// A `Database` is `Send + Sync` and every method takes `&self`, so it can be shared between
+/// threads behind an `Arc`. Each table has a reader/writer lock: any number of threads read a
+/// table at once, while inserts and schema changes to it take turns. Different tables do not
+/// wait on each other except for the short catalog lookups.
+///
+/// Rows are versioned (MVCC): every read sees a consistent snapshot, and readers never wait
+/// for a transaction's uncommitted writes. Writers lock the rows they change until their
+/// transaction ends, so transactions writing different rows of a table do not wait on each
+/// other. Methods here each run as their own transaction; `begin` starts one that spans several.
+pub struct Database {
+    dir: PathBuf,
+    catalog: Arc<RwLock<TableMetaHandler>>,
+    transactions: Arc<TransactionManager>,
+    locks: LockManager,
+    lock_timeout: Option<Duration>,
+    tables: TableQueryHandler,
+    schema: TableCreationHandler,
+}
+
+impl Database {
+    /// Opens the database in `path`, creating the system tables on first use.
+    pub fn open<P: AsRef<Path>>(path: P, options: Options) -> Result<Database> {
+        let dir = path.as_ref().to_path_buf();
+        if !dir.is_dir() {
+            if !options.create_if_missing {
+                return Err(Error::not_found(format!("Database directory '{}' does not exist", dir.display())));
+            }
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| Error::from(e).context(format_args!("Failed to create database directory '{}'", dir.display())))?;
+        }
+
+        // Page writes a crash tore are redone before anything reads the pages
+        File_Handler::recover_double_writes(&dir)
+            .map_err(|e| Error::from(e).context("Failed to recover interrupted page writes"))?;
+        let mut catalog = TableMetaHandler::new(&dir);
+        catalog.load_catalog()
+            .map_err(|e| e.context("Failed to load catalog"))?;
+
+        let transactions = Arc::new(TransactionManager::open(&dir)
+            .map_err(|e| e.context("Failed to open transaction log"))?);
+        let catalog = Arc::new(RwLock::new(catalog));
+        let mut tables = TableQueryHandler::new(catalog.clone(), transactions.clone(), &dir);
+        tables.set_string_overflow_policy(options.string_overflow);
+        if options.load_indexes {
+            tables.load_existing_btrees()?;
+        }
+
+        Ok(Database {
+            schema: TableCreationHandler::new(catalog.clone()),
+            dir,
+            catalog,
+            transactions,
+            locks: LockManager::new(),
+            lock_timeout: options.lock_timeout,
+            tables,
+        })
+    }
+
+    /// The directory the database lives in.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn table_id(&self, table_name: &str) -> Option<i32> {
+        let config = self.catalog.read().ok()?;
+        config.get_table_id(table_name).map(|table_id| table_id as i32)
+    }
+
+    pub fn create_table(&self, table_name: &str, columns: Vec<TableColumn>) -> Result<i32> {
+        self.schema.create_table_with_validation(table_name.to_string(), columns)
+    }
+
+    /// Adds a column. Waits for the table's readers and writers to finish first, so an insert
+    /// never mixes the old and new schema.
+    pub fn add_column(&self, table_name: &str, column: TableColumn) -> Result<()> {
+        self.tables.with_table_locked(table_name, || self.schema.add_column(table_name, column))
+    }
+
+    pub fn drop_column(&self, table_name: &str, column_name: &str) -> Result<()> {
+        self.tables.with_table_locked(table_name, || self.schema.drop_column(table_name, column_name))
+    }
+
+    pub fn rename_column(&self, table_name: &str, old_name: &str, new_name: &str) -> Result<()> {
+        self.tables.with_table_locked(table_name, || self.schema.rename_column(table_name, old_name, new_name))
+    }
+
+    pub fn rename_table(&self, table_name: &str, new_name: &str) -> Result<()> {
+        self.tables.rename_table(table_name, new_name)
+    }
+
+    /// Drops a table along with its files.
+    pub fn drop_table(&self, table_name: &str) -> Result<()> {
+        self.tables.drop_table(table_name)
+    }
+
+    /// Removes every row of a table and empties its B+Tree, keeping the schema.
+    pub fn truncate_table(&self, table_name: &str) -> Result<()> {
+        self.tables.truncate_table(table_name)
+    }
+
+    /// Rewrites a table's pages in its current schema version.
+    pub fn rewrite_table(&self, table_name: &str) -> Result<()> {
+        self.tables.rewrite_table(table_name)
+    }
+
+    pub fn column_index(&self, table_name: &str, column_name: &str) -> Result<usize> {
+        self.tables.column_index(table_name, column_name)
+    }
+
+    /// A handle for inserting into, looking up and querying one table.
+    pub fn table(&self, table_name: &str) -> Result<Table<'_>> {
+        Table::new(self, table_name)
+    }
+
+    /// Starts a query over `table_name`; see `Query`.
+    pub fn query(&self, table_name: &str) -> Query<'_> {
+        Query::new(self, table_name)
+    }
+
+    /// Names of the user tables, in no particular order.
+    pub fn table_names(&self) -> Vec<String> {
+        let config = match self.catalog.read() {
+            Ok(config) => config,
+            Err(_) => return Vec::new(),
+        };
+        config.get_all_tables()
+            .into_iter()
+            .filter(|table| table.table_id >= FIRST_USER_TABLE_ID)
+            .map(|table| table.table_name)
+            .collect()
+    }
+
+    /// Column definitions of a table, in column order.
+    pub fn columns(&self, table_name: &str) -> Result<Vec<TableColumn>> {
+        self.tables.get_table_columns(table_name)
+    }
+
+    /// Starts a transaction. Its reads see the database as of this call plus its own writes,
+    /// and its writes stay invisible to others until it commits.
+    pub fn begin(&self) -> Result<Transaction<'_>> {
+        Transaction::begin(self)
+    }
+
+    /// Inserts one value per column, in column order, indexed under `primary_key`.
+    pub fn insert(&self, table_name: &str, primary_key: i32, values: Vec<DataArray>) -> Result<()> {
+        let row = self.tables.create_row(table_name, values)?;
+        self.autocommit(table_name, &[primary_key], |snapshot| {
+            self.tables.insert(table_name.to_string(), primary_key, row, snapshot).map(|_| ())
+        })
+    }
+
+    /// Inserts many rows, each a primary key with one value per column, as one transaction.
+    /// Meant for imports: the rows are packed into pages with one write per page, and an
+    /// empty table's index is built bottom-up. If any row is rejected, none is inserted.
+    pub fn bulk_insert(&self, table_name: &str, rows: Vec<(i32, Vec<DataArray>)>) -> Result<()> {
+        let keys: Vec<i32> = rows.iter().map(|(primary_key, _)| *primary_key).collect();
+        let rows = rows.into_iter()
+            .map(|(primary_key, values)| Ok((primary_key, self.tables.create_row(table_name, values)?)))
+            .collect::<Result<Vec<_>>>()?;
+        self.autocommit(table_name, &keys, |snapshot| {
+            self.tables.batch_insert(table_name.to_string(), rows, snapshot).map(|_| ())
+        })
+    }
+
+    /// Deletes the row with `primary_key`. Returns false if there was none.
+    pub fn delete(&self, table_name: &str, primary_key: i32) -> Result<bool> {
+        self.autocommit(table_name, &[primary_key], |snapshot| {
+            Ok(self.tables.delete(table_name, primary_key, snapshot)?.is_some())
+        })
+    }
+
+    /// Runs a write of the rows with `primary_keys` as its own transaction, holding their
+    /// locks while it runs, and commits it if it succeeds. A write of more than one row is
+    /// logged, and abandoned rather than rolled back if it fails, so rows it wrote before a
+    /// failure or a crash never count as committed.
+    fn autocommit<T, F>(&self, table_name: &str, primary_keys: &[i32], write: F) -> Result<T>
+    where
+        F: FnOnce(&Snapshot) -> Result<T>,
+    {
+        let batch = primary_keys.len() > 1;
+        let snapshot = if batch { self.transactions.begin_batch()? } else { self.transactions.begin(false)? };
+        let txn_id = snapshot.txn_id;
+        let result = primary_keys.iter()
+            .try_for_each(|primary_key| self.locks.lock(txn_id, table_name, *primary_key, LockMode::Exclusive, self.lock_timeout))
+            .and_then(|_| write(&snapshot));
+        let ended = match result {
+            Ok(_) => self.transactions.finish(txn_id, true)
+                .or_else(|e| self.transactions.abandon(txn_id).and(Err(e))),
+            Err(_) if batch => self.transactions.abandon(txn_id),
+            Err(_) => self.transactions.finish(txn_id, false),
+        };
+        let released = self.locks.release_all(txn_id);
+        let value = result?;
+        ended.and(released)?;
+        Ok(value)
+    }
+
+    /// Looks a row up by primary key.
+    pub fn get(&self, table_name: &str, primary_key: i32) -> Result<Option<Vec<DataArray>>> {
+        self.tables.get(table_name, primary_key, None)
+    }
+
+    /// Looks a row up by primary key and formats it as `TYPE: value` pairs.
+    pub fn select(&self, table_name: &str, primary_key: i32) -> Result<Option<String>> {
+        self.tables.select(table_name.to_string(), primary_key, None)
+    }
+
+    /// Looks a row up by primary key and renders it as a JSON object of column names to
+    /// values.
+    pub fn select_json(&self, table_name: &str, primary_key: i32) -> Result<Option<String>> {
+        let names: Vec<String> = self.columns(table_name)?.into_iter().map(|c| c.column_name).collect();
+        Ok(self.get(table_name, primary_key)?.map(|row| JsonLines::row_to_json(&names, &row)))
+    }
+
+    pub fn scan(&self, table_name: &str) -> Result<Vec<Vec<DataArray>>> {
+        self.tables.scan(table_name, None)
+    }
+
+    pub fn select_where(&self, table_name: &str, predicate: &Predicate) -> Result<Vec<Vec<DataArray>>> {
+        self.tables.select_where(table_name, predicate, None)
+    }
+
+    /// Frees the space of row versions no transaction can see any more. Returns how many
+    /// were removed.
+    pub fn vacuum(&self, table_name: &str) -> Result<usize> {
+        self.tables.vacuum(table_name)
+    }
+
+    pub fn write_blob<R: Read>(&self, table_name: &str, reader: &mut R) -> Result<DataArray> {
+        self.tables.write_blob(table_name, reader)
+    }
+
+    pub fn read_blob(&self, table_name: &str, primary_key: i32, column: usize) -> Result<Option<BlobReader>> {
+        self.tables.read_blob(table_name, primary_key, column, None)
+    }
+
+    /// Imports a CSV file into a table through `bulk_insert`. Every record is checked first;
+    /// if any cannot be a row, `Error::Import` lists them and nothing is inserted. Returns
+    /// how many rows were imported.
+    pub fn copy_from_csv(&self, table_name: &str, path: impl AsRef<Path>, options: &CsvOptions) -> Result<usize> {
+        CsvCopy::import(self, table_name, path.as_ref(), options)
+    }
+
+    /// Exports every row of a table, as of one snapshot, to a CSV file. Returns how many rows
+    /// were written.
+    pub fn copy_to_csv(&self, table_name: &str, path: impl AsRef<Path>, options: &CsvOptions) -> Result<usize> {
+        CsvCopy::export(self, table_name, path.as_ref(), options)
+    }
+
+    /// Imports a JSON Lines file, one object per line whose members name columns, through
+    /// `bulk_insert`. Columns an object leaves out take their default, or NULL. A value of
+    /// the wrong JSON kind for its column, or a member naming no column, is reported with
+    /// its line in `Error::Import`, and nothing is inserted. Returns how many rows were
+    /// imported.
+    pub fn copy_from_json_lines(&self, table_name: &str, path: impl AsRef<Path>) -> Result<usize> {
+        JsonLines::import(self, table_name, path.as_ref())
+    }
+
+    /// Exports every row of a table, as of one snapshot, as JSON Lines. INTEGER, BIGINT,
+    /// FLOAT, DOUBLE and DECIMAL values are JSON numbers (DECIMAL with all its digits),
+    /// BOOLEAN is true or false and NULL is null. STRING, CHAR, DATE, TIMESTAMP and BLOB
+    /// values are strings in the form `Value` displays, BLOBs as `\x` hex. Returns how many
+    /// rows were written.
+    pub fn copy_to_json_lines(&self, table_name: &str, path: impl AsRef<Path>) -> Result<usize> {
+        JsonLines::export(self, table_name, path.as_ref())
+    }
+
+    /// Runs a `COPY table [(column, ...)] FROM | TO 'file' [WITH (option, ...)]` statement;
+    /// see `CsvOptions` for what the options mean. `WITH (FORMAT json)` copies JSON Lines
+    /// instead. Returns how many rows were copied.
+    pub fn execute_copy(&self, statement: &str) -> Result<usize> {
+        CsvCopy::execute(self, statement)
+    }
+
+    /// Writes the database as a portable SQL script: CREATE TABLE statements from the catalog
+    /// and an INSERT for every row, read from one snapshot. Returns how many rows were
+    /// written. A key that a row's INTEGER primary key column does not hold is carried in
+    /// a `-- key: N` comment before its INSERT.
+    pub fn dump<W: Write>(&self, writer: W) -> Result<usize> {
+        SqlDump::dump(self, writer)
+    }
+
+    /// Replays a script from `dump` into this database, which must have no tables, such as
+    /// one just opened on an empty directory. Returns how many rows were inserted. A failed
+    /// restore leaves the tables and rows before the failing statement; start again from
+    /// an empty directory.
+    pub fn restore_dump(&self, path: impl AsRef<Path>) -> Result<usize> {
+        SqlDump::restore(self, path.as_ref())
+    }
+
+    /// Writes every table, index and catalog file to one archive at `path`, as they stood
+    /// when the backup began, while other threads keep reading and writing. A table's
+    /// writers wait only while that table is copied. Like any open transaction, the backup
+    /// makes drops, truncates, renames and rewrites fail until it is done. Returns the number
+    /// of files archived.
+    pub fn backup(&self, path: impl AsRef<Path>) -> Result<usize> {
+        Backup::backup(self, path.as_ref())
+    }
+
+    /// Replaces the database directory `dir` with the files of an archive from `backup`.
+    /// Every checksum is checked first, so a damaged archive is reported as corruption and
+    /// leaves `dir` as it was. No `Database` may have `dir` open. Returns the number of
+    /// files restored.
+    pub fn restore(archive: impl AsRef<Path>, dir: impl AsRef<Path>) -> Result<usize> {
+        Backup::restore(archive.as_ref(), dir.as_ref())
+    }
+
+    pub(crate) fn query_handler(&self) -> &TableQueryHandler {
+        &self.tables
+    }
+
+    pub(crate) fn transaction_manager(&self) -> &TransactionManager {
+        &self.transactions
+    }
+
+    pub(crate) fn lock_manager(&self) -> &LockManager {
+        &self.locks
+    }
+
+    pub(crate) fn lock_timeout(&self) -> Option<Duration> {
+        self.lock_timeout
+    }
+
+    /// Tables with a primary key index loaded or built in this session.
+    pub fn loaded_tables(&self) -> Vec<String> {
+        self.tables.get_available_tables().unwrap_or_default()
+    }
+
+    /// Writes the primary key indexes to disk.
+    pub fn save(&self) -> Result<()> {
+        self.tables.save_btrees()
+    }
+}
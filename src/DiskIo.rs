@@ -0,0 +1,169 @@
+#[cfg(any(test, feature = "fault-injection"))]
+use std::cell::Cell;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Every change the database makes to its files goes through the functions here, so a test
+/// can stop the world at any one of them. A write that "crashes" may leave a prefix of its
+/// bytes behind (a torn write); every write after it fails, as if the process had died.
+///
+/// Faults are armed per thread, so tests running side by side do not affect each other.
+/// They are only compiled in with the `fault-injection` feature, which the integration
+/// tests turn on.
+#[cfg(any(test, feature = "fault-injection"))]
+#[derive(Clone, Copy)]
+struct Faults {
+    /// Writes that still succeed; `None` only counts them
+    remaining: Option<u64>,
+    /// Bytes of the crashing write that reach the file
+    torn_bytes: usize,
+    writes: u64,
+    crashed: bool,
+}
+
+#[cfg(any(test, feature = "fault-injection"))]
+thread_local! {
+    static FAULTS: Cell<Option<Faults>> = const { Cell::new(None) };
+}
+
+/// Simulated crashes at the database's file write points, for testing recovery. Writes are
+/// file writes, renames, removals and resizes, made on the thread that armed the injector.
+#[cfg(any(test, feature = "fault-injection"))]
+pub struct FaultInjector;
+
+#[cfg(any(test, feature = "fault-injection"))]
+impl FaultInjector {
+    /// Lets `writes` more writes through, then crashes on the next one. If that write puts
+    /// bytes in a file, only the first `torn_bytes` of them get there.
+    pub fn crash_after(writes: u64, torn_bytes: usize) {
+        FAULTS.with(|faults| faults.set(Some(Faults { remaining: Some(writes), torn_bytes, writes: 0, crashed: false })));
+    }
+
+    /// Counts writes without failing any, to find out how many write points a workload has.
+    pub fn count_writes() {
+        FAULTS.with(|faults| faults.set(Some(Faults { remaining: None, torn_bytes: 0, writes: 0, crashed: false })));
+    }
+
+    /// Writes that succeeded since the injector was armed.
+    pub fn writes() -> u64 {
+        FAULTS.with(|faults| faults.get().map_or(0, |f| f.writes))
+    }
+
+    pub fn has_crashed() -> bool {
+        FAULTS.with(|faults| faults.get().is_some_and(|f| f.crashed))
+    }
+
+    /// Lets every write through again, as after a restart.
+    pub fn disarm() {
+        FAULTS.with(|faults| faults.set(None));
+    }
+}
+
+/// What happens to the write about to be made
+enum Outcome {
+    Proceed,
+    #[cfg_attr(not(any(test, feature = "fault-injection")), allow(dead_code))]
+    Tear(usize),
+}
+
+fn crashed() -> io::Error {
+    io::Error::other("Simulated crash")
+}
+
+#[cfg(not(any(test, feature = "fault-injection")))]
+fn next_write() -> io::Result<Outcome> {
+    Ok(Outcome::Proceed)
+}
+
+#[cfg(any(test, feature = "fault-injection"))]
+fn next_write() -> io::Result<Outcome> {
+    FAULTS.with(|faults| {
+        let mut state = match faults.get() {
+            Some(state) => state,
+            None => return Ok(Outcome::Proceed),
+        };
+        if state.crashed {
+            return Err(crashed());
+        }
+        let outcome = match state.remaining {
+            Some(0) => {
+                state.crashed = true;
+                Outcome::Tear(state.torn_bytes)
+            }
+            Some(remaining) => {
+                state.remaining = Some(remaining - 1);
+                state.writes += 1;
+                Outcome::Proceed
+            }
+            None => {
+                state.writes += 1;
+                Outcome::Proceed
+            }
+        };
+        faults.set(Some(state));
+        Ok(outcome)
+    })
+}
+
+/// Writes all of `bytes` at the file's current position.
+pub fn write_all(file: &mut File, bytes: &[u8]) -> io::Result<()> {
+    match next_write()? {
+        Outcome::Proceed => file.write_all(bytes),
+        Outcome::Tear(torn_bytes) => {
+            file.write_all(&bytes[..torn_bytes.min(bytes.len())])?;
+            Err(crashed())
+        }
+    }
+}
+
+pub fn set_len(file: &File, len: u64) -> io::Result<()> {
+    match next_write()? {
+        Outcome::Proceed => file.set_len(len),
+        Outcome::Tear(_) => Err(crashed()),
+    }
+}
+
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    match next_write()? {
+        Outcome::Proceed => std::fs::rename(from, to),
+        Outcome::Tear(_) => Err(crashed()),
+    }
+}
+
+pub fn remove_file(path: &Path) -> io::Result<()> {
+    match next_write()? {
+        Outcome::Proceed => std::fs::remove_file(path),
+        Outcome::Tear(_) => Err(crashed()),
+    }
+}
+
+/// Creates an empty file and syncs it, or leaves an existing one as it is.
+pub fn create_file(path: &Path) -> io::Result<()> {
+    match next_write()? {
+        Outcome::Proceed => OpenOptions::new().create(true).append(true).open(path)?.sync_all(),
+        Outcome::Tear(_) => Err(crashed()),
+    }
+}
+
+/// Removes a file that may already be gone.
+pub fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match remove_file(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+/// Replaces the whole of `path` with `bytes` through a synced temporary file and a rename,
+/// so after a crash the file holds either the old or the new contents.
+pub fn write_file_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_name = Path::new(&temp_name);
+    {
+        let mut file = File::create(temp_name)?;
+        write_all(&mut file, bytes)?;
+        file.sync_all()?;
+    }
+    rename(temp_name, path)
+}
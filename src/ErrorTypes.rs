@@ -0,0 +1,123 @@
+use std::fmt;
+use std::io;
+use std::sync::PoisonError;
+
+/// Error returned by every fallible operation. The variant says what kind of failure it was,
+/// so callers can react to, say, a duplicate key differently from a damaged file; the message
+/// is for people.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// A file's contents are not what the database wrote: a bad page, catalog row or index entry
+    Corruption(String),
+    /// Rows, columns or names that do not fit the table's schema
+    SchemaMismatch(String),
+    /// A row with this primary key already exists
+    DuplicateKey { table: String, key: i32 },
+    /// A table, column or database directory that does not exist
+    NotFound(String),
+    /// A table, column or file that is in the way of a create or rename
+    AlreadyExists(String),
+    /// A value whose type does not match its column
+    TypeMismatch(String),
+    /// A value breaking a NOT NULL, length or precision limit
+    ConstraintViolation(String),
+    /// An operation that is not allowed, such as writing to a system table
+    InvalidOperation(String),
+    /// Another transaction changed this row after our snapshot was taken, or has not committed
+    /// its change yet; the transaction should be rolled back and retried
+    WriteConflict { table: String, key: i32 },
+    /// Waiting for this row's lock would have closed a cycle of waiting transactions; this
+    /// transaction was chosen as the victim and rolled back
+    Deadlock { table: String, key: i32 },
+    /// This row's lock was not granted within the transaction's lock timeout
+    LockTimeout { table: String, key: i32 },
+    /// Records of an imported file that could not be turned into rows, each with the line it
+    /// starts on; nothing was imported
+    Import { file: String, errors: Vec<(usize, String)> },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn not_found(what: impl Into<String>) -> Error {
+        Error::NotFound(what.into())
+    }
+
+    pub(crate) fn table_not_found(table_name: &str) -> Error {
+        Error::NotFound(format!("Table '{}' not found", table_name))
+    }
+
+    pub(crate) fn corruption(message: impl Into<String>) -> Error {
+        Error::Corruption(message.into())
+    }
+
+    /// A lock whose holder panicked; what it guards may be half changed.
+    pub(crate) fn poisoned<T>(_: PoisonError<T>) -> Error {
+        Error::InvalidOperation("Database is unusable after a panic in another thread".to_string())
+    }
+
+    /// Prefixes the message with what was being done; the kind of error is kept.
+    pub(crate) fn context(self, what: impl fmt::Display) -> Error {
+        match self {
+            Error::Io(e) => Error::Io(io::Error::new(e.kind(), format!("{}: {}", what, e))),
+            Error::Corruption(m) => Error::Corruption(format!("{}: {}", what, m)),
+            Error::SchemaMismatch(m) => Error::SchemaMismatch(format!("{}: {}", what, m)),
+            Error::NotFound(m) => Error::NotFound(format!("{}: {}", what, m)),
+            Error::AlreadyExists(m) => Error::AlreadyExists(format!("{}: {}", what, m)),
+            Error::TypeMismatch(m) => Error::TypeMismatch(format!("{}: {}", what, m)),
+            Error::ConstraintViolation(m) => Error::ConstraintViolation(format!("{}: {}", what, m)),
+            Error::InvalidOperation(m) => Error::InvalidOperation(format!("{}: {}", what, m)),
+            other @ (Error::DuplicateKey { .. }
+            | Error::WriteConflict { .. }
+            | Error::Deadlock { .. }
+            | Error::LockTimeout { .. }
+            | Error::Import { .. }) => other,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Corruption(message) => write!(f, "Corruption: {}", message),
+            Error::DuplicateKey { table, key } => write!(f, "Duplicate primary key {} in table '{}'", key, table),
+            Error::WriteConflict { table, key } => write!(f, "Row {} of table '{}' was changed by a concurrent transaction", key, table),
+            Error::Deadlock { table, key } => write!(f, "Deadlock waiting for row {} of table '{}'; the transaction was rolled back", key, table),
+            Error::LockTimeout { table, key } => write!(f, "Timed out waiting for the lock on row {} of table '{}'", key, table),
+            Error::Import { file, errors } => {
+                write!(f, "{} of the records in '{}' could not be imported", errors.len(), file)?;
+                for (line, message) in errors.iter().take(10) {
+                    write!(f, "\n  line {}: {}", line, message)?;
+                }
+                if errors.len() > 10 {
+                    write!(f, "\n  and {} more", errors.len() - 10)?;
+                }
+                Ok(())
+            }
+            Error::SchemaMismatch(message)
+            | Error::NotFound(message)
+            | Error::AlreadyExists(message)
+            | Error::TypeMismatch(message)
+            | Error::ConstraintViolation(message)
+            | Error::InvalidOperation(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -1,60 +1,121 @@
+use crate::RowData::RawData;
+use crate::DiskIo;
+use std::io::{Seek, Read};
+use std::path::{Path, PathBuf};
+
+/// Page id and length in front of the page copy in a double-write file, checksum after it
+const DOUBLE_WRITE_HEADER: usize = 12;
+const DOUBLE_WRITE_CHECKSUM: usize = 8;
 
-use crate::{MetaEnum, RowData::RawData, TableMetaHandler};
-use std::io::{Seek, Write, Read};
 pub struct File_Handler{
-    schema_name: String,
+    file_name: PathBuf,
 }
 
 impl File_Handler {
-    pub fn new(schema_name: String, ) -> File_Handler {
-        File_Handler { schema_name }
+    pub fn new(file_name: PathBuf) -> File_Handler {
+        File_Handler { file_name }
     }
 
-    pub fn write_to_file(&self, raw_data: &RawData) {
-        let file_name = format!("{}.dat", self.schema_name);
+    /// Writes a page in place. The page is first copied, with a checksum, to the file's
+    /// double-write file `<name>.dwb`, so a write torn by a crash is redone on the next open
+    /// by `recover_double_writes`.
+    pub fn write_to_file(&self, raw_data: &RawData) -> std::io::Result<()> {
+        let mut copy = Vec::with_capacity(DOUBLE_WRITE_HEADER + raw_data.data.len() + DOUBLE_WRITE_CHECKSUM);
+        copy.extend_from_slice(&raw_data.page_id.to_le_bytes());
+        copy.extend_from_slice(&(raw_data.data.len() as u32).to_le_bytes());
+        copy.extend_from_slice(&raw_data.data);
+        copy.extend_from_slice(&checksum(&copy).to_le_bytes());
+        // Overwritten rather than truncated: a torn copy fails its checksum either way
+        let mut double_write = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(Self::double_write_file(&self.file_name))?;
+        DiskIo::write_all(&mut double_write, &copy)?;
+        double_write.sync_data()?;
+
         let mut file = std::fs::OpenOptions::new()
             .write(true)
             .create(true) 
-            .open(&file_name)
-            .expect("Unable to open or create file");
+            .truncate(false)
+            .open(&self.file_name)?;
 
         let required_file_size = (raw_data.page_id + 1) * raw_data.page_size as u64;
-        let current_file_size = file.metadata().expect("Unable to get file metadata").len();
+        let current_file_size = file.metadata()?.len();
 
         if required_file_size > current_file_size {
-            file.set_len(required_file_size)
-                .expect("Unable to extend file size");
+            DiskIo::set_len(&file, required_file_size)?;
         }
         let start_pos = raw_data.page_id * raw_data.page_size as u64;
-        file.seek(std::io::SeekFrom::Start(start_pos))
-            .expect("Unable to seek in file");
-        file.write_all(&raw_data.data)
-            .expect("Unable to write data to file");
+        file.seek(std::io::SeekFrom::Start(start_pos))?;
+        DiskIo::write_all(&mut file, &raw_data.data)?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// The double-write file that protects in-place writes of `file_name`.
+    pub fn double_write_file(file_name: &Path) -> PathBuf {
+        file_name.with_extension("dwb")
+    }
 
-        println!("Data for page {} written to file: {}", raw_data.page_id, file_name);
+    /// Redoes the last page write of every file in `dir` from its double-write file, in case
+    /// a crash tore it, then removes the double-write file. A copy that is itself torn fails
+    /// its checksum; the page write after it never started, so it is dropped.
+    pub fn recover_double_writes(dir: &Path) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "dwb") {
+                continue;
+            }
+            let file_name = path.with_extension("dat");
+            let copy = std::fs::read(&path)?;
+            if copy.len() > DOUBLE_WRITE_HEADER && file_name.exists() {
+                let page_id = u64::from_le_bytes(copy[..8].try_into().unwrap());
+                let page_size = u32::from_le_bytes(copy[8..12].try_into().unwrap()) as usize;
+                let body_len = DOUBLE_WRITE_HEADER + page_size;
+                let intact = copy.len() >= body_len + DOUBLE_WRITE_CHECKSUM
+                    && checksum(&copy[..body_len]).to_le_bytes() == copy[body_len..body_len + DOUBLE_WRITE_CHECKSUM];
+                if intact {
+                    let page = &copy[DOUBLE_WRITE_HEADER..body_len];
+                    let mut file = std::fs::OpenOptions::new().write(true).open(&file_name)?;
+                    let required_file_size = (page_id + 1) * page_size as u64;
+                    if required_file_size > file.metadata()?.len() {
+                        DiskIo::set_len(&file, required_file_size)?;
+                    }
+                    file.seek(std::io::SeekFrom::Start(page_id * page_size as u64))?;
+                    DiskIo::write_all(&mut file, page)?;
+                    file.sync_data()?;
+                }
+            }
+            DiskIo::remove_file(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Number of whole pages currently stored in a table's data file.
+    pub fn page_count(file_name: &Path, page_size: usize) -> u64 {
+        std::fs::metadata(file_name)
+            .map(|m| m.len() / page_size as u64)
+            .unwrap_or(0)
     }
 
-    pub fn read_from_file(schema_name: String, page_id: u64, page_size: usize) -> RawData {
-        let file_name = format!("{}.dat", schema_name);
+    pub fn read_page_bytes(file_name: &Path, page_id: u64, page_size: usize) -> std::io::Result<Box<[u8]>> {
         let mut file = std::fs::OpenOptions::new()
             .read(true)
-            .open(&file_name)
-            .expect("Unable to open file");
+            .open(file_name)?;
 
         let start_pos = page_id * page_size as u64;
-        file.seek(std::io::SeekFrom::Start(start_pos))
-            .expect("Unable to seek in file");
+        file.seek(std::io::SeekFrom::Start(start_pos))?;
 
         let mut data = vec![0; page_size];
-        file.read_exact(&mut data)
-            .expect("Unable to read data from file");
-        
-        let mut guard = TableMetaHandler::meta_config.lock().unwrap();
-
-        let config = guard.as_mut().unwrap();
-        let meta = config.get_table_meta_by_name(&schema_name)
-            .expect("Table metadata not found");
+        file.read_exact(&mut data)?;
 
-        RawData::new(schema_name.clone(), meta.clone(), page_size, 0, page_id, data.into_boxed_slice())
+        Ok(data.into_boxed_slice())
     }
-}
\ No newline at end of file
+}
+
+/// FNV-1a hash of `bytes`, to tell a complete copy from one cut short.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
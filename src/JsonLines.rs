@@ -0,0 +1,416 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use crate::CsvCopy::complete_row;
+use crate::DatabaseHandler::Database;
+use crate::ErrorTypes::{Error, Result};
+use crate::MetaEnum::{DataArray, MetaEnum};
+use crate::TableCreationHandler::TableColumn;
+
+/// A parsed JSON value. Numbers keep their text, so DECIMAL and BIGINT values are not
+/// rounded through a float.
+#[derive(Clone, Debug)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    /// No column takes an array, so only its syntax is checked
+    Array,
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// What kind of value this is, for error messages
+    fn kind(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "a boolean",
+            Json::Number(_) => "a number",
+            Json::String(_) => "a string",
+            Json::Array => "an array",
+            Json::Object(_) => "an object",
+        }
+    }
+
+    /// Parses one JSON document; anything but whitespace after it is an error.
+    pub(crate) fn parse(text: &str) -> std::result::Result<Json, String> {
+        let mut parser = JsonParser { text: text.as_bytes(), pos: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.text.len() {
+            return Err(format!("unexpected text after the value at column {}", parser.pos + 1));
+        }
+        Ok(value)
+    }
+}
+
+struct JsonParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while matches!(self.text.get(self.pos), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, what: &str) -> String {
+        match self.text.get(self.pos) {
+            Some(_) => format!("{} at column {}", what, self.pos + 1),
+            None => format!("{} at the end of the line", what),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> std::result::Result<Json, String> {
+        if self.text[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid value"))
+        }
+    }
+
+    fn value(&mut self) -> std::result::Result<Json, String> {
+        self.skip_whitespace();
+        match self.text.get(self.pos) {
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'[') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.text.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Ok(Json::Array);
+                }
+                loop {
+                    self.value()?;
+                    self.skip_whitespace();
+                    match self.text.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(Json::Array);
+                        }
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.text.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.text.get(self.pos) != Some(&b'"') {
+                        return Err(self.error("expected a member name"));
+                    }
+                    let name = self.string()?;
+                    self.skip_whitespace();
+                    if self.text.get(self.pos) != Some(&b':') {
+                        return Err(self.error("expected ':'"));
+                    }
+                    self.pos += 1;
+                    members.push((name, self.value()?));
+                    self.skip_whitespace();
+                    match self.text.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(Json::Object(members));
+                        }
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while matches!(self.text.get(self.pos), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    /// `-?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?`
+    fn number(&mut self) -> std::result::Result<Json, String> {
+        let start = self.pos;
+        if self.text.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+        }
+        let integer_start = self.pos;
+        if self.digits() == 0 || (self.text[integer_start] == b'0' && self.pos - integer_start > 1) {
+            return Err(self.error("invalid number"));
+        }
+        if self.text.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            if self.digits() == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        if matches!(self.text.get(self.pos), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.text.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        Ok(Json::Number(String::from_utf8_lossy(&self.text[start..self.pos]).into_owned()))
+    }
+
+    fn hex4(&mut self) -> std::result::Result<u32, String> {
+        let digits = self.text.get(self.pos..self.pos + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+
+    fn string(&mut self) -> std::result::Result<String, String> {
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = *self.text.get(self.pos).ok_or_else(|| self.error("string is not closed"))?;
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.text.get(self.pos).ok_or_else(|| self.error("string is not closed"))?;
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            if (0xd800..0xdc00).contains(&code) && self.text[self.pos..].starts_with(b"\\u") {
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err(self.error("invalid surrogate pair"));
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                0..=0x1f => return Err(self.error("control character in string")),
+                _ => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+}
+
+/// Appends `text` as a JSON string.
+fn push_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Appends a value as JSON. Numbers, booleans and NULL map onto their JSON kinds, DECIMAL
+/// with all its digits; text, dates, timestamps and BLOBs (as `\x` hex) are strings in the
+/// form `DataArray` displays. Infinite and NaN floats are the strings `inf`, `-inf` and
+/// `NaN`. A spilled BLOB not read in first is written as displayed, `<blob N bytes>`.
+fn push_value(out: &mut String, value: &DataArray) {
+    match value {
+        DataArray::NULL => out.push_str("null"),
+        DataArray::INTEGER(_) | DataArray::BIGINT(_) | DataArray::BOOLEAN(_) | DataArray::DECIMAL(_) => {
+            out.push_str(&value.to_string())
+        }
+        DataArray::FLOAT(f) if f.is_finite() => out.push_str(&value.to_string()),
+        DataArray::DOUBLE(d) if d.is_finite() => out.push_str(&value.to_string()),
+        value => push_string(out, &value.to_string()),
+    }
+}
+
+/// Renders a row as a JSON object of column names to values, in column order.
+pub(crate) fn row_to_json(columns: &[String], values: &[DataArray]) -> String {
+    let mut out = String::from("{");
+    for (i, (column, value)) in columns.iter().zip(values).enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_string(&mut out, column);
+        out.push(':');
+        push_value(&mut out, value);
+    }
+    out.push('}');
+    out
+}
+
+fn type_label(column_type: &MetaEnum) -> &'static str {
+    match column_type {
+        MetaEnum::INTEGER => "INTEGER",
+        MetaEnum::FLOAT => "FLOAT",
+        MetaEnum::DOUBLE => "DOUBLE",
+        MetaEnum::BIGINT => "BIGINT",
+        MetaEnum::STRING(_) => "STRING",
+        MetaEnum::CHAR(_) => "CHAR",
+        MetaEnum::BOOLEAN => "BOOLEAN",
+        MetaEnum::DATE => "DATE",
+        MetaEnum::TIMESTAMP => "TIMESTAMP",
+        MetaEnum::DECIMAL(_, _) => "DECIMAL",
+        MetaEnum::BLOB => "BLOB",
+    }
+}
+
+/// Writes a JSON number such as `1.25e2` without its exponent, `125.00`, for DECIMAL columns.
+/// Exponents too large to be a DECIMAL are left for the parser to reject.
+fn without_exponent(text: &str) -> String {
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent),
+        None => return text.to_string(),
+    };
+    let exponent: i64 = match exponent.parse() {
+        Ok(exponent) if (-100..=100).contains(&exponent) => exponent,
+        _ => return text.to_string(),
+    };
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", integer, fraction);
+    let point = integer.len() as i64 + exponent;
+    if point <= 0 {
+        format!("{}0.{}{}", sign, "0".repeat(-point as usize), digits)
+    } else if point as usize >= digits.len() {
+        format!("{}{}{}", sign, digits, "0".repeat(point as usize - digits.len()))
+    } else {
+        format!("{}{}.{}", sign, &digits[..point as usize], &digits[point as usize..])
+    }
+}
+
+/// Converts a JSON value into a value of the column, the reverse of `push_value`. DECIMAL
+/// columns also take strings, so producers that cannot write exact numbers need not round.
+fn value_from_json(column: &TableColumn, json: &Json) -> std::result::Result<DataArray, String> {
+    let column_type = &column.column_type;
+    let parsed = match (column_type, json) {
+        (_, Json::Null) => Ok(DataArray::NULL),
+        (MetaEnum::BOOLEAN, Json::Bool(b)) => Ok(DataArray::BOOLEAN(*b)),
+        (MetaEnum::DECIMAL(_, _), Json::Number(text)) => column_type.parse_value(&without_exponent(text)),
+        (MetaEnum::INTEGER | MetaEnum::BIGINT | MetaEnum::FLOAT | MetaEnum::DOUBLE, Json::Number(text)) => {
+            column_type.parse_value(text)
+        }
+        (MetaEnum::FLOAT | MetaEnum::DOUBLE, Json::String(text)) if ["inf", "-inf", "NaN"].contains(&text.as_str()) => {
+            column_type.parse_value(text)
+        }
+        (MetaEnum::STRING(_) | MetaEnum::CHAR(_) | MetaEnum::DATE | MetaEnum::TIMESTAMP | MetaEnum::DECIMAL(_, _) | MetaEnum::BLOB, Json::String(text)) => {
+            column_type.parse_value(text)
+        }
+        (_, other) => {
+            let expected = match column_type {
+                MetaEnum::INTEGER | MetaEnum::BIGINT | MetaEnum::FLOAT | MetaEnum::DOUBLE | MetaEnum::DECIMAL(_, _) => "a number",
+                MetaEnum::BOOLEAN => "a boolean",
+                _ => "a string",
+            };
+            Err(format!("expected {} for {}, found {}", expected, type_label(column_type), other.kind()))
+        }
+    };
+    parsed.map_err(|e| format!("column '{}': {}", column.column_name, e))
+}
+
+/// Turns one line's object into a row; members name columns and the rest take their default.
+fn parse_line(line: &str, columns: &[TableColumn]) -> std::result::Result<Vec<DataArray>, String> {
+    let members = match Json::parse(line)? {
+        Json::Object(members) => members,
+        other => return Err(format!("expected an object, found {}", other.kind())),
+    };
+    let mut values: Vec<Option<DataArray>> = vec![None; columns.len()];
+    for (name, json) in &members {
+        let position = columns.iter()
+            .position(|c| c.column_name == *name)
+            .ok_or_else(|| format!("no column '{}'", name))?;
+        if values[position].is_some() {
+            return Err(format!("column '{}' given more than once", name));
+        }
+        values[position] = Some(value_from_json(&columns[position], json)?);
+    }
+    complete_row(columns, values)
+}
+
+/// Reads a JSON Lines file, one object per line with members named after columns, into a
+/// table through the bulk insert path. Blank lines are skipped. As with CSV, every line is
+/// checked first and `Error::Import` lists those that cannot be rows.
+pub(crate) fn import(db: &Database, table_name: &str, path: &Path) -> Result<usize> {
+    let source = path.display().to_string();
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| Error::from(e).context(format_args!("Failed to read '{}'", source)))?;
+    let table = db.table(table_name)?;
+    let columns = table.columns();
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(line, columns) {
+            Ok(row) => rows.push(row),
+            Err(message) => errors.push((number + 1, message)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Error::Import { file: source, errors });
+    }
+    let count = rows.len();
+    table.bulk_insert(rows)?;
+    Ok(count)
+}
+
+/// Writes every row of a table, as of one snapshot, as JSON Lines.
+pub(crate) fn export(db: &Database, table_name: &str, path: &Path) -> Result<usize> {
+    let columns = db.columns(table_name)?;
+    let names: Vec<String> = columns.iter().map(|c| c.column_name.clone()).collect();
+    let txn = db.begin()?;
+    let rows = txn.scan(table_name)?;
+    let count = rows.len();
+    let source = path.display().to_string();
+    let failed = |e: std::io::Error| Error::from(e).context(format_args!("Failed to write '{}'", source));
+    let mut writer = BufWriter::new(File::create(path).map_err(failed)?);
+    for mut row in rows {
+        db.query_handler().inline_blobs(table_name, &mut row)?;
+        writeln!(writer, "{}", row_to_json(&names, &row)).map_err(failed)?;
+    }
+    writer.flush().map_err(failed)?;
+    txn.commit()?;
+    Ok(count)
+}
@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use crate::ValueTypes::{Date, Timestamp, Decimal};
 
 
 pub trait Comparable: Clone + std::fmt::Debug {
@@ -138,4 +139,135 @@ impl Comparable for f64 {
     fn is_less_equal(&self, other: &Self) -> bool {
         self < other || self.is_equal(other)
     }
-}
\ No newline at end of file
+}
+
+impl Comparable for bool {
+    fn get_key(&self) -> Self {
+        *self
+    }
+    
+    fn is_equal(&self, other: &Self) -> bool {
+        self == other
+    }
+    
+    fn is_greater(&self, other: &Self) -> bool {
+        self > other
+    }
+    
+    fn is_greater_equal(&self, other: &Self) -> bool {
+        self >= other
+    }
+    
+    fn is_less(&self, other: &Self) -> bool {
+        self < other
+    }
+    
+    fn is_less_equal(&self, other: &Self) -> bool {
+        self <= other
+    }
+}
+
+impl Comparable for Date {
+    fn get_key(&self) -> Self {
+        *self
+    }
+    
+    fn is_equal(&self, other: &Self) -> bool {
+        self == other
+    }
+    
+    fn is_greater(&self, other: &Self) -> bool {
+        self > other
+    }
+    
+    fn is_greater_equal(&self, other: &Self) -> bool {
+        self >= other
+    }
+    
+    fn is_less(&self, other: &Self) -> bool {
+        self < other
+    }
+    
+    fn is_less_equal(&self, other: &Self) -> bool {
+        self <= other
+    }
+}
+
+impl Comparable for Timestamp {
+    fn get_key(&self) -> Self {
+        *self
+    }
+    
+    fn is_equal(&self, other: &Self) -> bool {
+        self == other
+    }
+    
+    fn is_greater(&self, other: &Self) -> bool {
+        self > other
+    }
+    
+    fn is_greater_equal(&self, other: &Self) -> bool {
+        self >= other
+    }
+    
+    fn is_less(&self, other: &Self) -> bool {
+        self < other
+    }
+    
+    fn is_less_equal(&self, other: &Self) -> bool {
+        self <= other
+    }
+}
+
+impl Comparable for Decimal {
+    fn get_key(&self) -> Self {
+        *self
+    }
+    
+    fn is_equal(&self, other: &Self) -> bool {
+        self.compare(other) == Ordering::Equal
+    }
+    
+    fn is_greater(&self, other: &Self) -> bool {
+        self.compare(other) == Ordering::Greater
+    }
+    
+    fn is_greater_equal(&self, other: &Self) -> bool {
+        self.compare(other) != Ordering::Less
+    }
+    
+    fn is_less(&self, other: &Self) -> bool {
+        self.compare(other) == Ordering::Less
+    }
+    
+    fn is_less_equal(&self, other: &Self) -> bool {
+        self.compare(other) != Ordering::Greater
+    }
+}
+
+
+impl Comparable for Vec<u8> {
+    fn get_key(&self) -> Self {
+        self.clone()
+    }
+    
+    fn is_equal(&self, other: &Self) -> bool {
+        self == other
+    }
+    
+    fn is_greater(&self, other: &Self) -> bool {
+        self > other
+    }
+    
+    fn is_greater_equal(&self, other: &Self) -> bool {
+        self >= other
+    }
+    
+    fn is_less(&self, other: &Self) -> bool {
+        self < other
+    }
+    
+    fn is_less_equal(&self, other: &Self) -> bool {
+        self <= other
+    }
+}
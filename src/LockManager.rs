@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use crate::TransactionManager::TxnId;
+use crate::ErrorTypes::{Error, Result};
+
+/// How a transaction holds a row: any number of transactions may share a row, one may hold
+/// it exclusively.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+type RowKey = (String, i32);
+
+#[derive(Default)]
+struct LockTable {
+    /// Holders of each locked row
+    rows: HashMap<RowKey, HashMap<TxnId, LockMode>>,
+    /// Rows each transaction holds, so they are released together
+    held: HashMap<TxnId, Vec<RowKey>>,
+    /// The waits-for graph: an edge from every waiting transaction to those in its way
+    waiting: HashMap<TxnId, HashSet<TxnId>>,
+    /// Waiting transactions chosen to break a deadlock, not yet told
+    victims: HashSet<TxnId>,
+}
+
+/// Row locks on (table, primary key), held by transactions until they end. A transaction
+/// that would wait in a cycle of waiting transactions is a deadlock; the youngest one in the
+/// cycle is chosen as the victim and its lock request fails with `Error::Deadlock`.
+pub struct LockManager {
+    table: Mutex<LockTable>,
+    changed: Condvar,
+}
+
+impl LockManager {
+    pub fn new() -> LockManager {
+        LockManager { table: Mutex::new(LockTable::default()), changed: Condvar::new() }
+    }
+
+    fn lock_table(&self) -> Result<MutexGuard<'_, LockTable>> {
+        self.table.lock().map_err(Error::poisoned)
+    }
+
+    /// Locks a row for `txn_id`, waiting while other transactions hold it in a conflicting
+    /// mode. A shared lock held alone is upgraded in place. Waits at most `timeout`, or for
+    /// as long as it takes when `None`.
+    pub fn lock(&self, txn_id: TxnId, table_name: &str, key: i32, mode: LockMode, timeout: Option<Duration>) -> Result<()> {
+        let row = (table_name.to_string(), key);
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut table = self.lock_table()?;
+        loop {
+            if table.victims.remove(&txn_id) {
+                table.waiting.remove(&txn_id);
+                return Err(Error::Deadlock { table: table_name.to_string(), key });
+            }
+            let holders = conflicting_holders(&table, &row, txn_id, mode);
+            if holders.is_empty() {
+                table.waiting.remove(&txn_id);
+                table.grant(txn_id, row, mode);
+                return Ok(());
+            }
+
+            table.waiting.insert(txn_id, holders);
+            if let Some(cycle) = find_cycle(&table, txn_id) {
+                let victim = *cycle.iter().max().unwrap();
+                if victim == txn_id {
+                    table.waiting.remove(&txn_id);
+                    return Err(Error::Deadlock { table: table_name.to_string(), key });
+                }
+                table.victims.insert(victim);
+                self.changed.notify_all();
+            }
+
+            table = match deadline {
+                None => self.changed.wait(table).map_err(Error::poisoned)?,
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        table.waiting.remove(&txn_id);
+                        return Err(Error::LockTimeout { table: table_name.to_string(), key });
+                    }
+                    self.changed.wait_timeout(table, deadline - now).map_err(Error::poisoned)?.0
+                }
+            };
+        }
+    }
+
+    /// Releases every row lock of a transaction that has ended, and wakes those waiting.
+    pub fn release_all(&self, txn_id: TxnId) -> Result<()> {
+        let mut table = self.lock_table()?;
+        for row in table.held.remove(&txn_id).unwrap_or_default() {
+            if let Some(holders) = table.rows.get_mut(&row) {
+                holders.remove(&txn_id);
+                if holders.is_empty() {
+                    table.rows.remove(&row);
+                }
+            }
+        }
+        table.waiting.remove(&txn_id);
+        table.victims.remove(&txn_id);
+        self.changed.notify_all();
+        Ok(())
+    }
+}
+
+impl Default for LockManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LockTable {
+    fn grant(&mut self, txn_id: TxnId, row: RowKey, mode: LockMode) {
+        let holders = self.rows.entry(row.clone()).or_default();
+        match holders.get(&txn_id) {
+            Some(LockMode::Exclusive) => {}
+            Some(LockMode::Shared) => {
+                holders.insert(txn_id, mode);
+            }
+            None => {
+                holders.insert(txn_id, mode);
+                self.held.entry(txn_id).or_default().push(row);
+            }
+        }
+    }
+}
+
+/// Other transactions holding `row` in a mode that does not allow `mode` for `txn_id`.
+fn conflicting_holders(table: &LockTable, row: &RowKey, txn_id: TxnId, mode: LockMode) -> HashSet<TxnId> {
+    let holders = match table.rows.get(row) {
+        Some(holders) => holders,
+        None => return HashSet::new(),
+    };
+    holders.iter()
+        .filter(|(holder, held)| **holder != txn_id && (mode == LockMode::Exclusive || **held == LockMode::Exclusive))
+        .map(|(holder, _)| *holder)
+        .collect()
+}
+
+/// The transactions on a cycle of the waits-for graph through `start`, if there is one.
+fn find_cycle(table: &LockTable, start: TxnId) -> Option<Vec<TxnId>> {
+    let mut path = vec![start];
+    let mut visited = HashSet::new();
+    if walk(table, start, start, &mut path, &mut visited) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn walk(table: &LockTable, start: TxnId, current: TxnId, path: &mut Vec<TxnId>, visited: &mut HashSet<TxnId>) -> bool {
+    let holders = match table.waiting.get(&current) {
+        Some(holders) => holders,
+        None => return false,
+    };
+    for &next in holders {
+        // A victim already chosen is about to give up its wait
+        if table.victims.contains(&next) {
+            continue;
+        }
+        if next == start {
+            return true;
+        }
+        if visited.insert(next) {
+            path.push(next);
+            if walk(table, start, next, path, visited) {
+                return true;
+            }
+            path.pop();
+        }
+    }
+    false
+}
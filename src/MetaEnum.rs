@@ -1,10 +1,19 @@
- #[derive(Clone)]
+use std::fmt;
+use crate::ValueTypes::{Date, Timestamp, Decimal, BlobRef};
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum MetaEnum {
     INTEGER,
     FLOAT,
     DOUBLE,
     BIGINT,
     STRING(i64),
+    CHAR(i64),
+    BOOLEAN,
+    DATE,
+    TIMESTAMP,
+    DECIMAL(u8, u8), // precision, scale
+    BLOB,
 }
 
 impl MetaEnum {
@@ -15,21 +24,128 @@ impl MetaEnum {
             MetaEnum::DOUBLE => 8,
             MetaEnum::BIGINT => 8,
             MetaEnum::STRING(len) => *len as usize,
+            MetaEnum::CHAR(len) => *len as usize,
+            MetaEnum::BOOLEAN => 1,
+            MetaEnum::DATE => 4,
+            MetaEnum::TIMESTAMP => 8,
+            MetaEnum::DECIMAL(_, _) => 16,
+            MetaEnum::BLOB => 13,
         }
     }
 
-    pub fn get_total_size(metadata: &Vec<MetaEnum>) -> usize {
+    /// Whether `value` can be stored in a column of this type. NULL is handled by the caller.
+    pub fn accepts(&self, value: &DataArray) -> bool {
+        matches!(
+            (self, value),
+            (MetaEnum::INTEGER, DataArray::INTEGER(_))
+                | (MetaEnum::FLOAT, DataArray::FLOAT(_))
+                | (MetaEnum::DOUBLE, DataArray::DOUBLE(_))
+                | (MetaEnum::BIGINT, DataArray::BIGINT(_))
+                | (MetaEnum::STRING(_), DataArray::STRING(_, _))
+                | (MetaEnum::CHAR(_), DataArray::CHAR(_, _))
+                | (MetaEnum::BOOLEAN, DataArray::BOOLEAN(_))
+                | (MetaEnum::DATE, DataArray::DATE(_))
+                | (MetaEnum::TIMESTAMP, DataArray::TIMESTAMP(_))
+                | (MetaEnum::DECIMAL(_, _), DataArray::DECIMAL(_))
+                | (MetaEnum::BLOB, DataArray::BLOB(_) | DataArray::BLOB_REF(_))
+        )
+    }
+
+    /// Parses the textual form of a value of this type (see `DataArray`'s `Display`).
+    pub fn parse_value(&self, text: &str) -> Result<DataArray, String> {
+        let trimmed = text.trim();
+        match self {
+            MetaEnum::INTEGER => trimmed.parse().map(DataArray::INTEGER)
+                .map_err(|_| format!("Invalid INTEGER: '{}'", text)),
+            MetaEnum::FLOAT => trimmed.parse().map(DataArray::FLOAT)
+                .map_err(|_| format!("Invalid FLOAT: '{}'", text)),
+            MetaEnum::DOUBLE => trimmed.parse().map(DataArray::DOUBLE)
+                .map_err(|_| format!("Invalid DOUBLE: '{}'", text)),
+            MetaEnum::BIGINT => trimmed.parse().map(DataArray::BIGINT)
+                .map_err(|_| format!("Invalid BIGINT: '{}'", text)),
+            MetaEnum::STRING(len) => Ok(DataArray::STRING(text.to_string(), *len as i32)),
+            MetaEnum::CHAR(len) => Ok(DataArray::CHAR(text.to_string(), *len as i32)),
+            MetaEnum::BOOLEAN => match trimmed.to_ascii_lowercase().as_str() {
+                "true" | "t" | "1" => Ok(DataArray::BOOLEAN(true)),
+                "false" | "f" | "0" => Ok(DataArray::BOOLEAN(false)),
+                _ => Err(format!("Invalid BOOLEAN: '{}'", text)),
+            },
+            MetaEnum::DATE => Date::parse(trimmed).map(DataArray::DATE),
+            MetaEnum::TIMESTAMP => Timestamp::parse(trimmed).map(DataArray::TIMESTAMP),
+            MetaEnum::DECIMAL(_, scale) => Decimal::parse(trimmed, *scale).map(DataArray::DECIMAL),
+            MetaEnum::BLOB => parse_hex(trimmed).map(DataArray::BLOB),
+        }
+    }
+
+    pub fn get_total_size(metadata: &[MetaEnum]) -> usize {
         metadata.iter().map(|meta| meta.size()).sum()
     }
 }
 
-
+#[derive(Clone, Debug)]
 pub enum DataArray {
     INTEGER(i32),
     FLOAT(f32),
     DOUBLE(f64),
     BIGINT(i64),
     STRING(String, i32), 
+    CHAR(String, i32),
+    BOOLEAN(bool),
+    DATE(Date),
+    TIMESTAMP(Timestamp),
+    DECIMAL(Decimal),
+    BLOB(Vec<u8>),
+    BLOB_REF(BlobRef), // BLOB stored in overflow pages, read it through TableQueryHandler::read_blob
+    NULL,
+}
+
+impl fmt::Display for DataArray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataArray::INTEGER(i) => write!(f, "{}", i),
+            DataArray::FLOAT(v) => write!(f, "{}", v),
+            DataArray::DOUBLE(d) => write!(f, "{}", d),
+            DataArray::BIGINT(b) => write!(f, "{}", b),
+            DataArray::STRING(s, _) | DataArray::CHAR(s, _) => write!(f, "{}", s),
+            DataArray::BOOLEAN(b) => write!(f, "{}", b),
+            DataArray::DATE(d) => write!(f, "{}", d),
+            DataArray::TIMESTAMP(t) => write!(f, "{}", t),
+            DataArray::DECIMAL(d) => write!(f, "{}", d),
+            DataArray::BLOB(bytes) => {
+                write!(f, "\\x")?;
+                bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            DataArray::BLOB_REF(blob) => write!(f, "<blob {} bytes>", blob.len),
+            DataArray::NULL => write!(f, "NULL"),
+        }
+    }
+}
+
+impl DataArray {
+    pub fn is_null(&self) -> bool {
+        matches!(self, DataArray::NULL)
+    }
+}
+
+/// Parses the `\x0a1b...` hex form BLOBs are printed in (the `\x` prefix is optional).
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits = text.strip_prefix("\\x").unwrap_or(text);
+    if !digits.len().is_multiple_of(2) || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("Invalid BLOB hex string: '{}'", text));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Number of bytes needed for a null bitmap covering `column_count` columns.
+pub fn null_bitmap_len(column_count: usize) -> usize {
+    column_count.div_ceil(8)
+}
+
+pub fn is_null_bit_set(bitmap: &[u8], column: usize) -> bool {
+    bitmap[column / 8] & (1 << (column % 8)) != 0
 }
 
 pub struct row_array {
@@ -44,8 +160,8 @@ impl row_array {
     pub fn add_meta(&mut self, meta: MetaEnum) {
         self.meta_data.push(meta);
     }
-    pub fn add_meta_array(&mut self, meta: &Vec<MetaEnum>) {
-        self.meta_data.extend(meta.clone());
+    pub fn add_meta_array(&mut self, meta: &[MetaEnum]) {
+        self.meta_data.extend_from_slice(meta);
     }
 
     pub fn add_data(&mut self, data: DataArray) {
@@ -56,25 +172,22 @@ impl row_array {
         self.data.extend(data);
     }
 
-    pub fn get_data(&self) -> &Vec<DataArray> {
-        &self.data
-    }
-
-    pub fn get_data_as_string(&self) -> String {
-        self.data.iter().map(|d| match d {
-            DataArray::INTEGER(i) => i.to_string(),
-            DataArray::FLOAT(f) => f.to_string(),
-            DataArray::DOUBLE(d) => d.to_string(),
-            DataArray::BIGINT(b) => b.to_string(),
-            DataArray::STRING(s, _) => s.clone(),
-        }).collect::<Vec<String>>().join(", ")
-    }
     pub fn get_data_as_bytes(&self) -> Vec<u8> {
     let size = MetaEnum::get_total_size(&self.meta_data);
-    let mut bytes = Vec::with_capacity(size);
+    let bitmap_len = null_bitmap_len(self.data.len());
+    let mut bytes = Vec::with_capacity(bitmap_len + size);
+
+    // Null bitmap first: bit i set means column i is NULL and has no bytes below
+    bytes.resize(bitmap_len, 0);
+    for (i, data) in self.data.iter().enumerate() {
+        if data.is_null() {
+            bytes[i / 8] |= 1 << (i % 8);
+        }
+    }
 
-    for data in &self.data {
+    for (i, data) in self.data.iter().enumerate() {
         match data {
+            DataArray::NULL => {}
             DataArray::INTEGER(i) => bytes.extend_from_slice(&i.to_le_bytes()),
             DataArray::FLOAT(f) => bytes.extend_from_slice(&f.to_le_bytes()),
             DataArray::DOUBLE(d) => bytes.extend_from_slice(&d.to_le_bytes()),
@@ -84,6 +197,39 @@ impl row_array {
                 bytes.extend_from_slice(&len.to_le_bytes());
                 bytes.extend_from_slice(s.as_bytes());
             }
+            DataArray::CHAR(s, len) => {
+                // CHAR(n) is fixed width: pad with zero bytes up to the declared length
+                let width = match self.meta_data.get(i) {
+                    Some(MetaEnum::CHAR(n)) => *n as usize,
+                    _ => *len as usize,
+                };
+                let raw = s.as_bytes();
+                let used = raw.len().min(width);
+                bytes.extend_from_slice(&raw[..used]);
+                bytes.resize(bytes.len() + (width - used), 0);
+            }
+            DataArray::BOOLEAN(b) => bytes.push(*b as u8),
+            DataArray::DATE(d) => bytes.extend_from_slice(&d.0.to_le_bytes()),
+            DataArray::TIMESTAMP(t) => bytes.extend_from_slice(&t.0.to_le_bytes()),
+            DataArray::DECIMAL(d) => {
+                // Stored unscaled at the column's scale, which lives in the schema
+                let value = match self.meta_data.get(i) {
+                    Some(MetaEnum::DECIMAL(_, scale)) => d.rescale(*scale).map_or(d.value, |r| r.value),
+                    _ => d.value,
+                };
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            // BLOBs start with a tag: 0 = inline (u32 length + bytes), 1 = overflow (u64 length + first page)
+            DataArray::BLOB(b) => {
+                bytes.push(0);
+                bytes.extend_from_slice(&(b.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(b);
+            }
+            DataArray::BLOB_REF(blob) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&blob.len.to_le_bytes());
+                bytes.extend_from_slice(&blob.first_page.to_le_bytes());
+            }
         }
     }
     bytes
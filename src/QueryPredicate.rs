@@ -0,0 +1,122 @@
+use std::cmp::Ordering;
+use crate::MetaEnum::DataArray;
+use crate::ValueTypes::{Timestamp, Decimal, MICROS_PER_DAY};
+
+/// Row filter over column positions, evaluated with SQL three-valued logic:
+/// `Some(true)` is TRUE, `Some(false)` is FALSE and `None` is UNKNOWN.
+/// Any comparison involving NULL is UNKNOWN; only `IsNull`/`IsNotNull` look at NULLs directly.
+#[derive(Clone, Debug)]
+pub enum Predicate {
+    Eq(usize, DataArray),
+    Ne(usize, DataArray),
+    Lt(usize, DataArray),
+    Le(usize, DataArray),
+    Gt(usize, DataArray),
+    Ge(usize, DataArray),
+    IsNull(usize),
+    IsNotNull(usize),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    pub fn and(self, other: Predicate) -> Predicate {
+        Predicate::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Predicate) -> Predicate {
+        Predicate::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn evaluate(&self, row: &[DataArray]) -> Option<bool> {
+        match self {
+            Predicate::Eq(col, value) => Self::compare(row, *col, value).map(|o| o == Ordering::Equal),
+            Predicate::Ne(col, value) => Self::compare(row, *col, value).map(|o| o != Ordering::Equal),
+            Predicate::Lt(col, value) => Self::compare(row, *col, value).map(|o| o == Ordering::Less),
+            Predicate::Le(col, value) => Self::compare(row, *col, value).map(|o| o != Ordering::Greater),
+            Predicate::Gt(col, value) => Self::compare(row, *col, value).map(|o| o == Ordering::Greater),
+            Predicate::Ge(col, value) => Self::compare(row, *col, value).map(|o| o != Ordering::Less),
+            Predicate::IsNull(col) => Some(row.get(*col).is_none_or(|v| v.is_null())),
+            Predicate::IsNotNull(col) => Some(row.get(*col).is_some_and(|v| !v.is_null())),
+            // FALSE dominates AND, TRUE dominates OR, otherwise UNKNOWN propagates
+            Predicate::And(left, right) => match (left.evaluate(row), right.evaluate(row)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Predicate::Or(left, right) => match (left.evaluate(row), right.evaluate(row)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+            Predicate::Not(inner) => inner.evaluate(row).map(|b| !b),
+        }
+    }
+
+    /// A row passes a WHERE filter only when the predicate is TRUE (not UNKNOWN).
+    pub fn matches(&self, row: &[DataArray]) -> bool {
+        self.evaluate(row) == Some(true)
+    }
+
+    fn compare(row: &[DataArray], col: usize, value: &DataArray) -> Option<Ordering> {
+        compare_values(row.get(col)?, value)
+    }
+}
+
+/// `!predicate` is TRUE where the predicate is FALSE, and UNKNOWN where it is UNKNOWN.
+impl std::ops::Not for Predicate {
+    type Output = Predicate;
+
+    fn not(self) -> Predicate {
+        Predicate::Not(Box::new(self))
+    }
+}
+
+/// Orders two values of compatible types; `None` when either side is NULL or the types don't compare.
+pub fn compare_values(left: &DataArray, right: &DataArray) -> Option<Ordering> {
+    match (left, right) {
+        (DataArray::NULL, _) | (_, DataArray::NULL) => None,
+        (DataArray::STRING(a, _) | DataArray::CHAR(a, _), DataArray::STRING(b, _) | DataArray::CHAR(b, _)) => {
+            Some(a.cmp(b))
+        }
+        (DataArray::BOOLEAN(a), DataArray::BOOLEAN(b)) => Some(a.cmp(b)),
+        (DataArray::BLOB(a), DataArray::BLOB(b)) => Some(a.cmp(b)),
+        (DataArray::DATE(a), DataArray::DATE(b)) => Some(a.cmp(b)),
+        (DataArray::TIMESTAMP(a), DataArray::TIMESTAMP(b)) => Some(a.cmp(b)),
+        (DataArray::DATE(a), DataArray::TIMESTAMP(b)) => Some(Timestamp(a.0 as i64 * MICROS_PER_DAY).cmp(b)),
+        (DataArray::TIMESTAMP(a), DataArray::DATE(b)) => Some(a.cmp(&Timestamp(b.0 as i64 * MICROS_PER_DAY))),
+        (DataArray::DECIMAL(a), DataArray::DECIMAL(b)) => Some(a.compare(b)),
+        // Integers compare exactly; FLOAT and DOUBLE through f64, as they would with each other
+        (DataArray::DECIMAL(a), _) => match as_integer(right) {
+            Some(b) => Some(a.compare(&Decimal::new(b as i128, 0))),
+            None => a.to_f64().partial_cmp(&as_float(right)?),
+        },
+        (_, DataArray::DECIMAL(b)) => match as_integer(left) {
+            Some(a) => Some(Decimal::new(a as i128, 0).compare(b)),
+            None => as_float(left)?.partial_cmp(&b.to_f64()),
+        },
+        _ => match (as_integer(left), as_integer(right)) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => as_float(left)?.partial_cmp(&as_float(right)?),
+        },
+    }
+}
+
+fn as_integer(value: &DataArray) -> Option<i64> {
+    match value {
+        DataArray::INTEGER(i) => Some(*i as i64),
+        DataArray::BIGINT(b) => Some(*b),
+        _ => None,
+    }
+}
+
+fn as_float(value: &DataArray) -> Option<f64> {
+    match value {
+        DataArray::INTEGER(i) => Some(*i as f64),
+        DataArray::BIGINT(b) => Some(*b as f64),
+        DataArray::FLOAT(f) => Some(*f as f64),
+        DataArray::DOUBLE(d) => Some(*d),
+        _ => None,
+    }
+}
@@ -1,6 +1,9 @@
 use std::mem;
 
-use crate::MetaEnum::MetaEnum;
+use crate::MetaEnum::{MetaEnum, DataArray, null_bitmap_len, is_null_bit_set};
+use crate::ValueTypes::{Date, Timestamp, Decimal, BlobRef};
+use crate::ErrorTypes::{Error, Result};
+use crate::TransactionManager::{TxnId, FROZEN_TXN};
 
 
 
@@ -26,10 +29,10 @@ impl RawData {
         }
     }
 
-     pub fn new_without_array(schema_name: String, meta_data: &Vec<MetaEnum>, page_size: usize, header_size: usize, page_id: u64) -> RawData {
+     pub fn new_without_array(schema_name: String, meta_data: &[MetaEnum], page_size: usize, header_size: usize, page_id: u64) -> RawData {
         RawData {
             schema_name,
-            meta_data: meta_data.clone(),
+            meta_data: meta_data.to_vec(),
             page_size,
             header_size,
             page_id,
@@ -73,15 +76,36 @@ impl RawData {
     self.data[new_row_start..new_row_end].copy_from_slice(row_data);
 }
 
-pub fn data_as_str(&self, offset: usize) -> String {
+pub fn row_count(&self) -> i32 {
     const OFFSET_SIZE: usize = mem::size_of::<i32>();
     let row_count_bytes: [u8; OFFSET_SIZE] = self.data[self.header_size..self.header_size + OFFSET_SIZE]
         .try_into()
         .expect("Failed to read row count");
-    let row_count = i32::from_le_bytes(row_count_bytes);
+    i32::from_le_bytes(row_count_bytes)
+}
+
+/// Whether a row of `row_len` bytes still fits between the slot array and the row data.
+pub fn has_room_for(&self, row_len: usize) -> bool {
+    const OFFSET_SIZE: usize = mem::size_of::<i32>();
+    let row_count = self.row_count() as usize;
+    let slot_array_end = self.header_size + OFFSET_SIZE + (row_count + 1) * OFFSET_SIZE;
+
+    let last_row_offset = if row_count == 0 {
+        self.page_size
+    } else {
+        let last_slot_start = self.header_size + OFFSET_SIZE + (row_count - 1) * OFFSET_SIZE;
+        i32::from_le_bytes(self.data[last_slot_start..last_slot_start + OFFSET_SIZE].try_into().unwrap()) as usize
+    };
+
+    last_row_offset >= slot_array_end + row_len
+}
+
+pub fn row_bytes(&self, offset: usize) -> Option<&[u8]> {
+    const OFFSET_SIZE: usize = mem::size_of::<i32>();
+    let row_count = self.row_count();
     
     if offset >= row_count as usize {
-        return String::new();
+        return None;
     }
 
     let slot_array_start = self.header_size + OFFSET_SIZE;
@@ -98,50 +122,293 @@ pub fn data_as_str(&self, offset: usize) -> String {
         i32::from_le_bytes(self.data[prev_row_start_in_slot..prev_row_end_in_slot].try_into().unwrap())
     };
 
-    let row_data_slice = &self.data[row_data_start as usize..row_data_end as usize];
-    
+    Some(&self.data[row_data_start as usize..row_data_end as usize])
+}
+
+/// Decodes the row stored in slot `offset` into one value per column.
+pub fn decode_row(&self, offset: usize) -> Result<Vec<DataArray>> {
+    let mut bytes = self.row_bytes(offset)
+        .ok_or_else(|| Error::corruption(format!("Page {} of '{}' has no row {}", self.page_id, self.schema_name, offset)))?;
+    if self.is_versioned() {
+        if bytes.is_empty() {
+            return Err(Error::not_found(format!("Row {} on page {} of '{}' was removed by vacuum", offset, self.page_id, self.schema_name)));
+        }
+        bytes = &bytes[ROW_VERSION_SIZE..];
+    }
+    decode_values(&self.meta_data, bytes)
+        .map_err(|e| e.context(format_args!("Row {} on page {} of '{}'", offset, self.page_id, self.schema_name)))
+}
+
+/// Checks the row count and slot array against the page bounds, so a damaged page is
+/// reported instead of panicking when its rows are read.
+pub fn check(&self) -> Result<()> {
+    const OFFSET_SIZE: usize = mem::size_of::<i32>();
+    let corrupt = |what: String| Error::corruption(format!("Page {} of '{}': {}", self.page_id, self.schema_name, what));
+
+    if self.data.len() != self.page_size || self.page_size < self.header_size + OFFSET_SIZE {
+        return Err(corrupt(format!("expected {} bytes, found {}", self.page_size, self.data.len())));
+    }
+    let row_count = self.row_count();
+    let slot_array_start = self.header_size + OFFSET_SIZE;
+    let max_rows = (self.page_size - slot_array_start) / OFFSET_SIZE;
+    if row_count < 0 || row_count as usize > max_rows {
+        return Err(corrupt(format!("invalid row count {}", row_count)));
+    }
+
+    let slot_array_end = slot_array_start + row_count as usize * OFFSET_SIZE;
+    let mut previous = self.page_size;
+    for slot in 0..row_count as usize {
+        let start = slot_array_start + slot * OFFSET_SIZE;
+        let offset = i32::from_le_bytes(self.data[start..start + OFFSET_SIZE].try_into().unwrap());
+        if offset < slot_array_end as i32 || offset as usize > previous {
+            return Err(corrupt(format!("row {} has invalid offset {}", slot, offset)));
+        }
+        let row_len = previous - offset as usize;
+        if self.is_versioned() && row_len != 0 && row_len < ROW_VERSION_SIZE {
+            return Err(corrupt(format!("row {} is too short for its version header", slot)));
+        }
+        previous = offset as usize;
+    }
+    Ok(())
+}
+
+/// Whether every row on this page starts with a `RowVersion` header. Pages written before
+/// rows had versions do not have the flag.
+pub fn is_versioned(&self) -> bool {
+    self.data[PAGE_FLAGS_OFFSET] & PAGE_FLAG_VERSIONED != 0
+}
+
+pub fn set_versioned(&mut self) {
+    self.data[PAGE_FLAGS_OFFSET] |= PAGE_FLAG_VERSIONED;
+}
+
+/// The version header of the row in slot `offset`, or `None` when vacuum has removed it.
+/// Rows on an unversioned page count as frozen.
+pub fn row_version(&self, offset: usize) -> Result<Option<RowVersion>> {
+    let bytes = self.row_bytes(offset)
+        .ok_or_else(|| Error::corruption(format!("Page {} of '{}' has no row {}", self.page_id, self.schema_name, offset)))?;
+    if !self.is_versioned() {
+        return Ok(Some(RowVersion::frozen(0)));
+    }
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    RowVersion::from_bytes(bytes).map(Some)
+}
+
+/// Overwrites the version header of the row in slot `offset`.
+pub fn set_row_version(&mut self, offset: usize, version: &RowVersion) -> Result<()> {
+    if !self.is_versioned() {
+        return Err(Error::InvalidOperation(format!(
+            "Page {} of '{}' was written before row versions; rewrite the table first",
+            self.page_id, self.schema_name
+        )));
+    }
+    let len = self.row_bytes(offset).map_or(0, |bytes| bytes.len());
+    if len < ROW_VERSION_SIZE {
+        return Err(Error::corruption(format!("Page {} of '{}' has no row {}", self.page_id, self.schema_name, offset)));
+    }
+    let start = self.row_start(offset);
+    self.data[start..start + ROW_VERSION_SIZE].copy_from_slice(&version.to_bytes());
+    Ok(())
+}
+
+/// Rebuilds the page with only the rows `keep` accepts. Every row stays in its slot, so row
+/// locations held elsewhere remain valid; a removed row becomes an empty slot.
+pub fn compact<F>(&mut self, keep: F)
+where
+    F: Fn(usize) -> bool,
+{
+    let rows: Vec<Vec<u8>> = (0..self.row_count() as usize)
+        .map(|offset| match keep(offset) {
+            true => self.row_bytes(offset).unwrap_or(&[]).to_vec(),
+            false => Vec::new(),
+        })
+        .collect();
+    self.data[self.header_size..].fill(0);
+    for row in rows {
+        self.add_new_row(&row);
+    }
+}
+
+fn row_start(&self, offset: usize) -> usize {
+    const OFFSET_SIZE: usize = mem::size_of::<i32>();
+    let slot = self.header_size + OFFSET_SIZE + offset * OFFSET_SIZE;
+    i32::from_le_bytes(self.data[slot..slot + OFFSET_SIZE].try_into().unwrap()) as usize
+}
+
+/// Schema version the rows on this page were written with (see `page_schema_version`).
+pub fn schema_version(&self) -> u32 {
+    page_schema_version(&self.data)
+}
+
+pub fn set_schema_version(&mut self, version: u32) {
+    self.data[..4].copy_from_slice(&version.to_le_bytes());
+}
+}
+
+/// The first four bytes of a page header hold the schema version its rows were encoded with.
+/// Pages written before tables had versions have zeros there, which is version 0.
+pub fn page_schema_version(page: &[u8]) -> u32 {
+    u32::from_le_bytes(page[..4].try_into().unwrap())
+}
+
+/// Byte of the page header holding the page flags, after the schema version
+const PAGE_FLAGS_OFFSET: usize = 4;
+/// Page flag: every row starts with a `RowVersion` header
+const PAGE_FLAG_VERSIONED: u8 = 1;
+
+/// Bytes a `RowVersion` takes in front of a row
+pub const ROW_VERSION_SIZE: usize = 32;
+
+/// The transactions that created and deleted one version of a row, and where the older
+/// version of the same key is. Every row on a versioned page starts with one; `select` and
+/// scans use it to decide whether a snapshot sees the row.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RowVersion {
+    /// Primary key of the row, so the index can be rebuilt from the pages. Unversioned pages
+    /// do not record it; their rows read as key 0.
+    pub key: i32,
+    /// Transaction that wrote this version
+    pub xmin: TxnId,
+    /// Transaction that deleted it, or 0 while it is live
+    pub xmax: TxnId,
+    /// Page and slot of the version this one replaced
+    pub prev: Option<(u64, i32)>,
+}
+
+impl RowVersion {
+    pub fn new(key: i32, xmin: TxnId, prev: Option<(u64, i32)>) -> RowVersion {
+        RowVersion { key, xmin, xmax: 0, prev }
+    }
+
+    /// A version every transaction sees: rows from before row versions, and rewritten rows.
+    pub fn frozen(key: i32) -> RowVersion {
+        RowVersion::new(key, FROZEN_TXN, None)
+    }
+
+    pub fn to_bytes(self) -> [u8; ROW_VERSION_SIZE] {
+        let (prev_page, prev_slot) = match self.prev {
+            Some((page_id, offset)) => (page_id as i64, offset),
+            None => (-1, -1),
+        };
+        let mut bytes = [0; ROW_VERSION_SIZE];
+        bytes[0..8].copy_from_slice(&self.xmin.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.xmax.to_le_bytes());
+        bytes[16..24].copy_from_slice(&prev_page.to_le_bytes());
+        bytes[24..28].copy_from_slice(&prev_slot.to_le_bytes());
+        bytes[28..32].copy_from_slice(&self.key.to_le_bytes());
+        bytes
+    }
 
+    fn from_bytes(bytes: &[u8]) -> Result<RowVersion> {
+        let mut reader = RowReader { bytes, pos: 0 };
+        let xmin = u64::from_le_bytes(reader.array()?);
+        let xmax = u64::from_le_bytes(reader.array()?);
+        let prev_page = i64::from_le_bytes(reader.array()?);
+        let prev_slot = i32::from_le_bytes(reader.array()?);
+        let key = i32::from_le_bytes(reader.array()?);
+        let prev = match prev_page {
+            -1 => None,
+            page_id if page_id >= 0 && prev_slot >= 0 => Some((page_id as u64, prev_slot)),
+            _ => return Err(Error::corruption(format!("Invalid previous version {}:{}", prev_page, prev_slot))),
+        };
+        Ok(RowVersion { key, xmin, xmax, prev })
+    }
+}
+
+/// Renders a row as `TYPE: value, ` pairs, the format `select` returns.
+pub fn format_row(meta_data: &[MetaEnum], values: &[DataArray]) -> String {
     let mut result = String::new();
-    let mut current_pos = 0; 
-
-    for meta in self.meta_data.iter() {
-        match meta {
-            MetaEnum::INTEGER => {
-                let bytes: [u8; 4] = row_data_slice[current_pos..current_pos + 4].try_into().unwrap();
-                result.push_str(&format!("INTEGER: {}, ", i32::from_le_bytes(bytes)));
-                current_pos += 4;
-            }
-            MetaEnum::FLOAT => {
-                let bytes: [u8; 4] = row_data_slice[current_pos..current_pos + 4].try_into().unwrap();
-                result.push_str(&format!("FLOAT: {}, ", f32::from_le_bytes(bytes)));
-                current_pos += 4;
-            }
-            MetaEnum::DOUBLE => {
-                let bytes: [u8; 8] = row_data_slice[current_pos..current_pos + 8].try_into().unwrap();
-                result.push_str(&format!("DOUBLE: {}, ", f64::from_le_bytes(bytes)));
-                current_pos += 8;
+    for (meta, value) in meta_data.iter().zip(values.iter()) {
+        let label = match meta {
+            MetaEnum::INTEGER => "INTEGER",
+            MetaEnum::FLOAT => "FLOAT",
+            MetaEnum::DOUBLE => "DOUBLE",
+            MetaEnum::BIGINT => "BIGINT",
+            MetaEnum::STRING(_) => "STRING",
+            MetaEnum::CHAR(_) => "CHAR",
+            MetaEnum::BOOLEAN => "BOOLEAN",
+            MetaEnum::DATE => "DATE",
+            MetaEnum::TIMESTAMP => "TIMESTAMP",
+            MetaEnum::DECIMAL(_, _) => "DECIMAL",
+            MetaEnum::BLOB => "BLOB",
+        };
+        result.push_str(&format!("{}: {}, ", label, value));
+    }
+    
+    result
+}
+
+/// Decodes one encoded row: a null bitmap (one bit per column) followed by the non-null values.
+/// Null columns take no space after the bitmap. Bytes that run out early are reported as corruption.
+pub fn decode_values(meta_data: &[MetaEnum], row_data_slice: &[u8]) -> Result<Vec<DataArray>> {
+    let mut reader = RowReader { bytes: row_data_slice, pos: 0 };
+    let bitmap = reader.take(null_bitmap_len(meta_data.len()))?;
+    let mut values = Vec::with_capacity(meta_data.len());
+
+    for (i, meta) in meta_data.iter().enumerate() {
+        if is_null_bit_set(bitmap, i) {
+            values.push(DataArray::NULL);
+            continue;
+        }
+        let value = match meta {
+            MetaEnum::INTEGER => DataArray::INTEGER(i32::from_le_bytes(reader.array()?)),
+            MetaEnum::FLOAT => DataArray::FLOAT(f32::from_le_bytes(reader.array()?)),
+            MetaEnum::DOUBLE => DataArray::DOUBLE(f64::from_le_bytes(reader.array()?)),
+            MetaEnum::BIGINT => DataArray::BIGINT(i64::from_le_bytes(reader.array()?)),
+            MetaEnum::STRING(n) => {
+                let len = i32::from_le_bytes(reader.array()?);
+                let len = usize::try_from(len)
+                    .map_err(|_| Error::corruption(format!("Invalid string length {}", len)))?;
+                let str_value = String::from_utf8_lossy(reader.take(len)?);
+                DataArray::STRING(str_value.to_string(), *n as i32)
             }
-            MetaEnum::BIGINT => {
-                let bytes: [u8; 8] = row_data_slice[current_pos..current_pos + 8].try_into().unwrap();
-                result.push_str(&format!("BIGINT: {}, ", i64::from_le_bytes(bytes)));
-                current_pos += 8;
+            MetaEnum::CHAR(n) => {
+                let raw = reader.take(*n as usize)?;
+                let used = raw.iter().rposition(|b| *b != 0).map_or(0, |p| p + 1);
+                let str_value = String::from_utf8_lossy(&raw[..used]);
+                DataArray::CHAR(str_value.to_string(), *n as i32)
             }
-            MetaEnum::STRING(s) => {
-                let len_bytes: [u8; 4] = row_data_slice[current_pos..current_pos + 4].try_into().unwrap();
-                let len = i32::from_le_bytes(len_bytes) as usize;
-                current_pos += 4; 
-
-                let str_value = String::from_utf8_lossy(&row_data_slice[current_pos..current_pos + len]);
-                result.push_str(&format!("STRING: {}, ", str_value));
-                current_pos += len;
+            MetaEnum::BOOLEAN => DataArray::BOOLEAN(reader.take(1)?[0] != 0),
+            MetaEnum::DATE => DataArray::DATE(Date(i32::from_le_bytes(reader.array()?))),
+            MetaEnum::TIMESTAMP => DataArray::TIMESTAMP(Timestamp(i64::from_le_bytes(reader.array()?))),
+            MetaEnum::DECIMAL(_, scale) => DataArray::DECIMAL(Decimal::new(i128::from_le_bytes(reader.array()?), *scale)),
+            MetaEnum::BLOB => {
+                let tag = reader.take(1)?[0];
+                if tag == 0 {
+                    let len = u32::from_le_bytes(reader.array()?) as usize;
+                    DataArray::BLOB(reader.take(len)?.to_vec())
+                } else {
+                    let len = u64::from_le_bytes(reader.array()?);
+                    let first_page = u64::from_le_bytes(reader.array()?);
+                    DataArray::BLOB_REF(BlobRef { first_page, len })
+                }
             }
-        }
+        };
+        values.push(value);
     }
-    
-    result
+
+    Ok(values)
 }
 
-    fn get_row_size(&self) -> usize {
-        self.meta_data.iter().map(|meta| meta.size()).sum()
+/// Bounds-checked cursor over the bytes of one row.
+struct RowReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RowReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| Error::corruption(format!("Row ends after {} bytes, expected at least {}", self.bytes.len(), self.pos.saturating_add(len))))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
     }
-}
\ No newline at end of file
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
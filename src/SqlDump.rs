@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use crate::CsvCopy::complete_row;
+use crate::DatabaseHandler::Database;
+use crate::ErrorTypes::{Error, Result};
+use crate::MetaEnum::{DataArray, MetaEnum};
+use crate::TableCreationHandler::TableColumn;
+
+/// Marks the primary key of the INSERT that follows, for rows it cannot be read from
+const KEY_COMMENT: &str = "-- key:";
+
+/// Writes `name` as a quoted identifier.
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn text_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// The SQL type of a column type: standard names where there is one, which `Parser::column_type`
+/// reads back along with the names `MetaEnum` uses.
+fn type_name(column_type: &MetaEnum) -> String {
+    match column_type {
+        MetaEnum::INTEGER => "INTEGER".to_string(),
+        MetaEnum::FLOAT => "REAL".to_string(),
+        MetaEnum::DOUBLE => "DOUBLE PRECISION".to_string(),
+        MetaEnum::BIGINT => "BIGINT".to_string(),
+        MetaEnum::STRING(len) => format!("VARCHAR({})", len),
+        MetaEnum::CHAR(len) => format!("CHAR({})", len),
+        MetaEnum::BOOLEAN => "BOOLEAN".to_string(),
+        MetaEnum::DATE => "DATE".to_string(),
+        MetaEnum::TIMESTAMP => "TIMESTAMP".to_string(),
+        MetaEnum::DECIMAL(precision, scale) => format!("DECIMAL({}, {})", precision, scale),
+        MetaEnum::BLOB => "BLOB".to_string(),
+    }
+}
+
+/// A value as an SQL literal. Infinite and NaN floats are the strings `'inf'`, `'-inf'`
+/// and `'NaN'`; BLOBs are `X'..'` hex.
+fn literal(value: &DataArray) -> String {
+    match value {
+        DataArray::NULL => "NULL".to_string(),
+        DataArray::BOOLEAN(true) => "TRUE".to_string(),
+        DataArray::BOOLEAN(false) => "FALSE".to_string(),
+        DataArray::FLOAT(f) if !f.is_finite() => text_literal(&value.to_string()),
+        DataArray::DOUBLE(d) if !d.is_finite() => text_literal(&value.to_string()),
+        DataArray::INTEGER(_) | DataArray::BIGINT(_) | DataArray::FLOAT(_) | DataArray::DOUBLE(_) | DataArray::DECIMAL(_) => {
+            value.to_string()
+        }
+        DataArray::STRING(text, _) | DataArray::CHAR(text, _) => text_literal(text),
+        DataArray::DATE(date) => format!("DATE {}", text_literal(&date.to_string())),
+        DataArray::TIMESTAMP(timestamp) => format!("TIMESTAMP {}", text_literal(&timestamp.to_string())),
+        DataArray::BLOB(bytes) => format!("X'{}'", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+        // Read in before a row is written; kept readable should one get through
+        DataArray::BLOB_REF(_) => text_literal(&value.to_string()),
+    }
+}
+
+fn create_table_statement(table_name: &str, columns: &[TableColumn]) -> String {
+    let mut statement = format!("CREATE TABLE {} (", identifier(table_name));
+    for (i, column) in columns.iter().enumerate() {
+        statement.push_str(if i == 0 { "\n  " } else { ",\n  " });
+        statement.push_str(&format!("{} {}", identifier(&column.column_name), type_name(&column.column_type)));
+        if column.is_primary {
+            statement.push_str(" PRIMARY KEY");
+        } else if column.not_null {
+            statement.push_str(" NOT NULL");
+        }
+        if let Some(default) = &column.default_value {
+            statement.push_str(&format!(" DEFAULT {}", literal(default)));
+        }
+    }
+    statement.push_str("\n);");
+    statement
+}
+
+/// Writes the whole database as an SQL script: a CREATE TABLE per table, in the order they
+/// were created, each followed by an INSERT per row in key order. Rows are read from one
+/// snapshot. A row whose key is not the value of an INTEGER primary key column is preceded
+/// by a `-- key: N` comment, which `restore` reads and other databases ignore.
+pub(crate) fn dump<W: Write>(db: &Database, mut writer: W) -> Result<usize> {
+    let mut table_names = db.table_names();
+    table_names.sort_by_key(|name| db.table_id(name));
+
+    let txn = db.begin()?;
+    let mut count = 0;
+    writeln!(writer, "-- OxideDB SQL dump")?;
+    writeln!(writer, "BEGIN TRANSACTION;")?;
+    for table_name in &table_names {
+        let columns = db.columns(table_name)?;
+        let primary = columns.iter().position(|c| c.is_primary && c.column_type == MetaEnum::INTEGER);
+        let names: Vec<String> = columns.iter().map(|c| identifier(&c.column_name)).collect();
+        let insert = format!("INSERT INTO {} ({}) VALUES (", identifier(table_name), names.join(", "));
+        writeln!(writer)?;
+        writeln!(writer, "{}", create_table_statement(table_name, &columns))?;
+        for (key, mut row) in db.query_handler().scan_keyed(table_name, Some(txn.snapshot()))? {
+            db.query_handler().inline_blobs(table_name, &mut row)?;
+            if !matches!(primary.map(|p| &row[p]), Some(DataArray::INTEGER(value)) if *value == key) {
+                writeln!(writer, "{} {}", KEY_COMMENT, key)?;
+            }
+            let values: Vec<String> = row.iter().map(literal).collect();
+            writeln!(writer, "{}{});", insert, values.join(", "))?;
+            count += 1;
+        }
+    }
+    writeln!(writer)?;
+    writeln!(writer, "COMMIT;")?;
+    writer.flush()?;
+    txn.commit()?;
+    Ok(count)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A keyword or bare identifier
+    Word(String),
+    /// A `"quoted"` identifier
+    Quoted(String),
+    /// A `'string'` literal
+    Text(String),
+    /// An `X'..'` literal
+    Hex(Vec<u8>),
+    Number(String),
+    Symbol(char),
+    /// A `-- key: N` comment
+    Key(i32),
+}
+
+/// A token as it reads in an error message.
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        None => "the end of the script".to_string(),
+        Some(Token::Word(word)) => word.clone(),
+        Some(Token::Quoted(name)) => identifier(name),
+        Some(Token::Text(text)) => text_literal(text),
+        Some(Token::Hex(_)) => "a BLOB literal".to_string(),
+        Some(Token::Number(number)) => number.clone(),
+        Some(Token::Symbol(c)) => format!("'{}'", c),
+        Some(Token::Key(_)) => "a key comment".to_string(),
+    }
+}
+
+/// Reads up to the closing `end`, where a doubled one stands for itself, counting lines.
+fn quoted(chars: &mut std::iter::Peekable<std::str::Chars>, end: char, line: &mut usize) -> std::result::Result<String, (usize, String)> {
+    let start = *line;
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some(c) if c == end && chars.peek() == Some(&end) => {
+                chars.next();
+                text.push(c);
+            }
+            Some(c) if c == end => return Ok(text),
+            Some(c) => {
+                if c == '\n' {
+                    *line += 1;
+                }
+                text.push(c);
+            }
+            None => return Err((start, format!("{} is not closed", if end == '"' { "quoted name" } else { "string" }))),
+        }
+    }
+}
+
+/// Splits a script into tokens, each with its line. Comments other than key comments are
+/// dropped.
+fn tokenize(script: &str) -> std::result::Result<Vec<(usize, Token)>, (usize, String)> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = script.strip_prefix('\u{feff}').unwrap_or(script).chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '-' if chars.peek() == Some(&'-') => {
+                let mut comment = String::from("-");
+                while let Some(c) = chars.peek().copied().filter(|c| *c != '\n') {
+                    chars.next();
+                    comment.push(c);
+                }
+                if let Some(key) = comment.strip_prefix(KEY_COMMENT) {
+                    let key = key.trim().parse().map_err(|_| (line, format!("invalid key comment '{}'", comment)))?;
+                    tokens.push((line, Token::Key(key)));
+                }
+            }
+            '\'' => {
+                let start = line;
+                tokens.push((start, Token::Text(quoted(&mut chars, '\'', &mut line)?)));
+            }
+            '"' => {
+                let start = line;
+                tokens.push((start, Token::Quoted(quoted(&mut chars, '"', &mut line)?)));
+            }
+            'x' | 'X' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                let start = line;
+                let digits = quoted(&mut chars, '\'', &mut line)?;
+                let bytes = MetaEnum::BLOB.parse_value(&digits)
+                    .map_err(|e| (start, e))?;
+                match bytes {
+                    DataArray::BLOB(bytes) => tokens.push((start, Token::Hex(bytes))),
+                    _ => unreachable!("BLOB text parses to a BLOB"),
+                }
+            }
+            c if c.is_ascii_digit() || (c == '.' && chars.peek().is_some_and(|c| c.is_ascii_digit())) => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.peek().copied() {
+                    let exponent_sign = (c == '+' || c == '-') && number.ends_with(['e', 'E']);
+                    if !(c.is_ascii_alphanumeric() || c == '.' || exponent_sign) {
+                        break;
+                    }
+                    chars.next();
+                    number.push(c);
+                }
+                tokens.push((line, Token::Number(number)));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.peek().copied().filter(|c| c.is_alphanumeric() || *c == '_') {
+                    chars.next();
+                    word.push(c);
+                }
+                tokens.push((line, Token::Word(word)));
+            }
+            '(' | ')' | ',' | ';' | '-' | '+' => tokens.push((line, Token::Symbol(c))),
+            c => return Err((line, format!("unexpected '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A literal before it is given its column's type
+enum Literal {
+    Null,
+    Bool(bool),
+    Number(String),
+    Text(String),
+    Blob(Vec<u8>),
+    /// `DATE '..'` or `TIMESTAMP '..'`
+    Typed(String, String),
+}
+
+impl Literal {
+    /// Converts the literal to a value of the column.
+    fn value(self, column: &TableColumn) -> std::result::Result<DataArray, String> {
+        let column_type = &column.column_type;
+        let parsed = match (column_type, self) {
+            (_, Literal::Null) => Ok(DataArray::NULL),
+            (MetaEnum::BOOLEAN, Literal::Bool(b)) => Ok(DataArray::BOOLEAN(b)),
+            (MetaEnum::INTEGER | MetaEnum::BIGINT | MetaEnum::FLOAT | MetaEnum::DOUBLE | MetaEnum::DECIMAL(_, _), Literal::Number(text)) => {
+                column_type.parse_value(&text)
+            }
+            (MetaEnum::BLOB, Literal::Blob(bytes)) => Ok(DataArray::BLOB(bytes)),
+            (MetaEnum::DATE, Literal::Typed(kind, text)) if kind == "DATE" => column_type.parse_value(&text),
+            (MetaEnum::TIMESTAMP, Literal::Typed(kind, text)) if kind == "TIMESTAMP" || kind == "DATE" => column_type.parse_value(&text),
+            // Strings hold any value in its text form, as other databases write some of them
+            (_, Literal::Text(text)) => column_type.parse_value(&text),
+            (_, _) => Err(format!("value does not fit a {} column", type_name(column_type))),
+        };
+        parsed.map_err(|e| format!("column '{}': {}", column.column_name, e))
+    }
+}
+
+/// Reads the statements of a script one token at a time.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    /// Line of the next token, or of the last one at the end
+    fn line(&self) -> usize {
+        self.tokens.get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(line, _)| *line)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn error<T>(&self, message: String) -> std::result::Result<T, (usize, String)> {
+        Err((self.line(), message))
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> std::result::Result<(), (usize, String)> {
+        if !self.at_keyword(keyword) {
+            return self.error(format!("expected {}, found {}", keyword, describe(self.peek())));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn at_symbol(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    fn symbol(&mut self, symbol: char) -> std::result::Result<(), (usize, String)> {
+        if !self.at_symbol(symbol) {
+            return self.error(format!("expected '{}', found {}", symbol, describe(self.peek())));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn name(&mut self) -> std::result::Result<String, (usize, String)> {
+        match self.peek().cloned() {
+            Some(Token::Word(name) | Token::Quoted(name)) => {
+                self.position += 1;
+                Ok(name)
+            }
+            other => self.error(format!("expected a name, found {}", describe(other.as_ref()))),
+        }
+    }
+
+    /// `(name, ...)`
+    fn names(&mut self) -> std::result::Result<Vec<String>, (usize, String)> {
+        self.symbol('(')?;
+        let mut names = vec![self.name()?];
+        while self.at_symbol(',') {
+            self.position += 1;
+            names.push(self.name()?);
+        }
+        self.symbol(')')?;
+        Ok(names)
+    }
+
+    fn size(&mut self) -> std::result::Result<u64, (usize, String)> {
+        match self.next() {
+            Some(Token::Number(number)) => number.parse().or_else(|_| self.error(format!("invalid size {}", number))),
+            other => self.error(format!("expected a size, found {}", describe(other.as_ref()))),
+        }
+    }
+
+    fn column_type(&mut self) -> std::result::Result<MetaEnum, (usize, String)> {
+        let name = self.name()?.to_ascii_uppercase();
+        let column_type = match name.as_str() {
+            "INTEGER" | "INT" => MetaEnum::INTEGER,
+            "REAL" | "FLOAT" => MetaEnum::FLOAT,
+            "DOUBLE" => {
+                if self.at_keyword("PRECISION") {
+                    self.position += 1;
+                }
+                MetaEnum::DOUBLE
+            }
+            "BIGINT" => MetaEnum::BIGINT,
+            "VARCHAR" | "STRING" | "CHAR" => {
+                self.symbol('(')?;
+                let len = self.size()? as i64;
+                self.symbol(')')?;
+                if name == "CHAR" { MetaEnum::CHAR(len) } else { MetaEnum::STRING(len) }
+            }
+            "BOOLEAN" => MetaEnum::BOOLEAN,
+            "DATE" => MetaEnum::DATE,
+            "TIMESTAMP" => MetaEnum::TIMESTAMP,
+            "DECIMAL" | "NUMERIC" => {
+                self.symbol('(')?;
+                let precision = self.size()?;
+                let scale = match self.at_symbol(',') {
+                    true => {
+                        self.position += 1;
+                        self.size()?
+                    }
+                    false => 0,
+                };
+                self.symbol(')')?;
+                match (u8::try_from(precision), u8::try_from(scale)) {
+                    (Ok(precision), Ok(scale)) => MetaEnum::DECIMAL(precision, scale),
+                    _ => return self.error(format!("DECIMAL({}, {}) is too large", precision, scale)),
+                }
+            }
+            "BLOB" => MetaEnum::BLOB,
+            _ => return self.error(format!("unknown type {}", name)),
+        };
+        Ok(column_type)
+    }
+
+    fn literal(&mut self) -> std::result::Result<Literal, (usize, String)> {
+        let negative = match self.peek() {
+            Some(Token::Symbol('-')) => {
+                self.position += 1;
+                true
+            }
+            Some(Token::Symbol('+')) => {
+                self.position += 1;
+                false
+            }
+            _ => false,
+        };
+        match self.next() {
+            Some(Token::Number(number)) if negative => Ok(Literal::Number(format!("-{}", number))),
+            Some(Token::Number(number)) => Ok(Literal::Number(number)),
+            _ if negative => self.error("expected a number after '-'".to_string()),
+            Some(Token::Text(text)) => Ok(Literal::Text(text)),
+            Some(Token::Hex(bytes)) => Ok(Literal::Blob(bytes)),
+            Some(Token::Word(word)) => match word.to_ascii_uppercase().as_str() {
+                "NULL" => Ok(Literal::Null),
+                "TRUE" => Ok(Literal::Bool(true)),
+                "FALSE" => Ok(Literal::Bool(false)),
+                kind @ ("DATE" | "TIMESTAMP") => match self.next() {
+                    Some(Token::Text(text)) => Ok(Literal::Typed(kind.to_string(), text)),
+                    other => self.error(format!("expected a string after {}, found {}", kind, describe(other.as_ref()))),
+                },
+                _ => self.error(format!("expected a value, found {}", word)),
+            },
+            other => self.error(format!("expected a value, found {}", describe(other.as_ref()))),
+        }
+    }
+
+    /// `name type [PRIMARY KEY] [NOT NULL] [DEFAULT literal]`, constraints in any order
+    fn column(&mut self) -> std::result::Result<TableColumn, (usize, String)> {
+        let name = self.name()?;
+        let column_type = self.column_type()?;
+        let mut column = TableColumn::new(name, column_type, false);
+        loop {
+            if self.at_keyword("PRIMARY") {
+                self.position += 1;
+                self.keyword("KEY")?;
+                column.is_primary = true;
+                column.not_null = true;
+            } else if self.at_keyword("NOT") {
+                self.position += 1;
+                self.keyword("NULL")?;
+                column.not_null = true;
+            } else if self.at_keyword("NULL") {
+                self.position += 1;
+            } else if self.at_keyword("DEFAULT") {
+                self.position += 1;
+                let default = self.literal()?.value(&column).or_else(|e| self.error(e))?;
+                column.default_value = Some(default);
+            } else {
+                return Ok(column);
+            }
+        }
+    }
+}
+
+/// INSERTs waiting to be bulk inserted, all into one table
+struct Batch {
+    table_name: String,
+    line: usize,
+    rows: Vec<(i32, Vec<DataArray>)>,
+}
+
+fn flush(db: &Database, batch: &mut Option<Batch>) -> Result<()> {
+    if let Some(batch) = batch.take() {
+        db.bulk_insert(&batch.table_name, batch.rows)
+            .map_err(|e| e.context(format_args!("INSERT at line {}", batch.line)))?;
+    }
+    Ok(())
+}
+
+/// Replays a script `dump` wrote, or a similar one, into a database without tables. CREATE
+/// TABLE and INSERT statements are run; BEGIN and COMMIT are accepted and ignored. A run of
+/// INSERTs into one table goes in with one `bulk_insert`. Returns how many rows were
+/// inserted.
+pub(crate) fn restore(db: &Database, path: &Path) -> Result<usize> {
+    let source = path.display().to_string();
+    if let Some(table_name) = db.table_names().first() {
+        return Err(Error::InvalidOperation(format!(
+            "Restore needs an empty database, but '{}' has table '{}'", db.path().display(), table_name)));
+    }
+    let mut script = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut script))
+        .map_err(|e| Error::from(e).context(format_args!("Failed to read '{}'", source)))?;
+    let syntax_error = |(line, message): (usize, String)| Error::Import { file: source.clone(), errors: vec![(line, message)] };
+    let mut parser = Parser { tokens: tokenize(&script).map_err(syntax_error)?, position: 0 };
+
+    let mut columns: HashMap<String, Vec<TableColumn>> = HashMap::new();
+    let mut batch: Option<Batch> = None;
+    let mut count = 0;
+    let mut key = None;
+    while let Some(token) = parser.next() {
+        let line = parser.tokens[parser.position - 1].0;
+        let statement = match token {
+            Token::Symbol(';') => continue,
+            Token::Key(next_key) => {
+                key = Some(next_key);
+                continue;
+            }
+            Token::Word(word) => word.to_ascii_uppercase(),
+            other => return Err(syntax_error((line, format!("expected a statement, found {}", describe(Some(&other)))))),
+        };
+        match statement.as_str() {
+            "BEGIN" => {
+                if parser.at_keyword("TRANSACTION") || parser.at_keyword("WORK") {
+                    parser.position += 1;
+                }
+            }
+            "COMMIT" | "END" => {}
+            "CREATE" => {
+                parser.keyword("TABLE").map_err(syntax_error)?;
+                let table_name = parser.name().map_err(syntax_error)?;
+                parser.symbol('(').map_err(syntax_error)?;
+                let mut table_columns = vec![parser.column().map_err(syntax_error)?];
+                while parser.at_symbol(',') {
+                    parser.position += 1;
+                    table_columns.push(parser.column().map_err(syntax_error)?);
+                }
+                parser.symbol(')').map_err(syntax_error)?;
+                flush(db, &mut batch)?;
+                db.create_table(&table_name, table_columns.clone())
+                    .map_err(|e| e.context(format_args!("CREATE TABLE at line {}", line)))?;
+                columns.insert(table_name, table_columns);
+            }
+            "INSERT" => {
+                parser.keyword("INTO").map_err(syntax_error)?;
+                let table_name = parser.name().map_err(syntax_error)?;
+                let table_columns = match columns.get(&table_name) {
+                    Some(table_columns) => table_columns,
+                    None => return Err(Error::table_not_found(&table_name).context(format_args!("INSERT at line {}", line))),
+                };
+                let names = match parser.at_symbol('(') {
+                    true => parser.names().map_err(syntax_error)?,
+                    false => table_columns.iter().map(|c| c.column_name.clone()).collect(),
+                };
+                let positions = names.iter()
+                    .map(|name| table_columns.iter().position(|c| c.column_name == *name)
+                        .ok_or_else(|| syntax_error((line, format!("table '{}' has no column '{}'", table_name, name)))))
+                    .collect::<Result<Vec<usize>>>()?;
+                parser.keyword("VALUES").map_err(syntax_error)?;
+                if batch.as_ref().is_some_and(|batch| batch.table_name != table_name) {
+                    flush(db, &mut batch)?;
+                }
+                let batch = batch.get_or_insert_with(|| Batch { table_name: table_name.clone(), line, rows: Vec::new() });
+                loop {
+                    let tuple_line = parser.line();
+                    parser.symbol('(').map_err(syntax_error)?;
+                    let mut values: Vec<Option<DataArray>> = vec![None; table_columns.len()];
+                    for (i, position) in positions.iter().enumerate() {
+                        if i > 0 {
+                            parser.symbol(',').map_err(syntax_error)?;
+                        }
+                        let value = parser.literal().map_err(syntax_error)?
+                            .value(&table_columns[*position])
+                            .map_err(|e| syntax_error((tuple_line, e)))?;
+                        values[*position] = Some(value);
+                    }
+                    parser.symbol(')').map_err(syntax_error)?;
+                    let row = complete_row(table_columns, values).map_err(|e| syntax_error((tuple_line, e)))?;
+                    let row_key = match key.take() {
+                        Some(key) => key,
+                        None => match table_columns.iter().position(|c| c.is_primary).map(|p| &row[p]) {
+                            Some(DataArray::INTEGER(value)) => *value,
+                            _ => return Err(syntax_error((tuple_line, format!(
+                                "row of table '{}' has no INTEGER primary key and no '{}' comment", table_name, KEY_COMMENT)))),
+                        },
+                    };
+                    batch.rows.push((row_key, row));
+                    count += 1;
+                    if !parser.at_symbol(',') {
+                        break;
+                    }
+                    parser.position += 1;
+                }
+            }
+            other => return Err(syntax_error((line, format!("unsupported statement {}", other)))),
+        }
+        if parser.peek().is_some() {
+            parser.symbol(';').map_err(syntax_error)?;
+        }
+    }
+    flush(db, &mut batch)?;
+    Ok(count)
+}
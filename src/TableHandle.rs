@@ -0,0 +1,352 @@
+use std::fmt;
+use std::io::Write;
+use std::ops::Index;
+use std::sync::Arc;
+use crate::DatabaseHandler::Database;
+use crate::ErrorTypes::{Error, Result};
+use crate::JsonLines;
+use crate::MetaEnum::DataArray;
+use crate::QueryPredicate::Predicate;
+use crate::TableCreationHandler::TableColumn;
+
+/// One row returned by a lookup or query: values in column order, addressable by name.
+#[derive(Clone, Debug)]
+pub struct Row {
+    columns: Arc<[String]>,
+    values: Vec<DataArray>,
+}
+
+impl Row {
+    fn new(columns: Arc<[String]>, values: Vec<DataArray>) -> Row {
+        Row { columns, values }
+    }
+
+    /// Value of the column called `column`, if the row has one.
+    pub fn get(&self, column: &str) -> Option<&DataArray> {
+        let index = self.columns.iter().position(|c| c == column)?;
+        self.values.get(index)
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[DataArray] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<DataArray> {
+        self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The row as a JSON object of column names to values; see `Database::copy_to_json_lines`
+    /// for how each type is written.
+    pub fn to_json(&self) -> String {
+        JsonLines::row_to_json(&self.columns, &self.values)
+    }
+}
+
+impl Index<usize> for Row {
+    type Output = DataArray;
+
+    fn index(&self, index: usize) -> &DataArray {
+        &self.values[index]
+    }
+}
+
+impl Index<&str> for Row {
+    type Output = DataArray;
+
+    fn index(&self, column: &str) -> &DataArray {
+        self.get(column).unwrap_or_else(|| panic!("Row has no column '{}'", column))
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (column, value)) in self.columns.iter().zip(&self.values).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", column, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A handle on one table of an open database, from `Database::table`.
+pub struct Table<'db> {
+    db: &'db Database,
+    name: String,
+    columns: Vec<TableColumn>,
+    column_names: Arc<[String]>,
+}
+
+impl<'db> Table<'db> {
+    pub(crate) fn new(db: &'db Database, name: &str) -> Result<Table<'db>> {
+        let columns = db.columns(name)?;
+        let column_names = columns.iter().map(|c| c.column_name.clone()).collect();
+        Ok(Table { db, name: name.to_string(), columns, column_names })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.columns
+    }
+
+    /// Inserts a row given one value per column, in column order. The primary key is taken
+    /// from the row, so the table needs an INTEGER primary key column.
+    pub fn insert(&self, values: Vec<DataArray>) -> Result<()> {
+        let primary_key = self.primary_key_of(&values)?;
+        self.db.insert(&self.name, primary_key, values)
+    }
+
+    /// Inserts a row given (column name, value) pairs; columns left out take their default, or NULL.
+    pub fn insert_named(&self, values: Vec<(&str, DataArray)>) -> Result<()> {
+        let mut row: Vec<Option<DataArray>> = vec![None; self.columns.len()];
+        for (name, value) in values {
+            let index = self.column_index(name)?;
+            if row[index].is_some() {
+                return Err(Error::SchemaMismatch(format!("Column '{}' given more than once", name)));
+            }
+            row[index] = Some(value);
+        }
+
+        let values = self.columns.iter()
+            .zip(row)
+            .map(|(column, value)| value
+                .or_else(|| column.default_value.clone())
+                .unwrap_or(DataArray::NULL))
+            .collect();
+        self.insert(values)
+    }
+
+    /// Inserts many rows, each one value per column in column order, as one transaction; see
+    /// `Database::bulk_insert`. The primary keys are taken from the rows.
+    pub fn bulk_insert(&self, rows: Vec<Vec<DataArray>>) -> Result<()> {
+        let rows = rows.into_iter()
+            .map(|values| Ok((self.primary_key_of(&values)?, values)))
+            .collect::<Result<Vec<_>>>()?;
+        self.db.bulk_insert(&self.name, rows)
+    }
+
+    /// Looks a row up by primary key.
+    pub fn get(&self, primary_key: i32) -> Result<Option<Row>> {
+        Ok(self.db.get(&self.name, primary_key)?
+            .map(|values| Row::new(self.column_names.clone(), values)))
+    }
+
+    /// Every row of the table, in storage order.
+    pub fn scan(&self) -> Result<Vec<Row>> {
+        self.query().run()
+    }
+
+    /// Starts a query over this table.
+    pub fn query(&self) -> Query<'_> {
+        self.db.query(&self.name)
+    }
+
+    fn column_index(&self, column: &str) -> Result<usize> {
+        self.column_names.iter()
+            .position(|c| c == column)
+            .ok_or_else(|| Error::not_found(format!("Table '{}' has no column '{}'", self.name, column)))
+    }
+
+    fn primary_key_of(&self, values: &[DataArray]) -> Result<i32> {
+        let index = self.columns.iter()
+            .position(|c| c.is_primary)
+            .ok_or_else(|| Error::InvalidOperation(format!("Table '{}' has no primary key; use Database::insert", self.name)))?;
+        match values.get(index) {
+            Some(DataArray::INTEGER(key)) => Ok(*key),
+            _ => Err(Error::TypeMismatch(format!("Primary key of table '{}' must be an INTEGER value", self.name))),
+        }
+    }
+}
+
+/// Builds a filtered, projected scan of one table. Conditions are ANDed together and use
+/// SQL three-valued logic (see `Predicate`); unknown column names are reported by `run`.
+///
+/// ```no_run
+/// # use oxidedb::{Database, Options, Value};
+/// # let db = Database::open("data", Options::default())?;
+/// let rich = db.query("users")
+///     .gt("salary", Value::DOUBLE(80000.0))
+///     .is_not_null("email")
+///     .select(&["id", "name"])
+///     .limit(10)
+///     .run()?;
+/// # Ok::<(), oxidedb::Error>(())
+/// ```
+pub struct Query<'db> {
+    db: &'db Database,
+    table_name: String,
+    column_names: Arc<[String]>,
+    filter: Option<Predicate>,
+    projection: Option<Vec<String>>,
+    limit: Option<usize>,
+    offset: usize,
+    error: Option<Error>,
+}
+
+impl<'db> Query<'db> {
+    pub(crate) fn new(db: &'db Database, table_name: &str) -> Query<'db> {
+        let (column_names, error) = match db.columns(table_name) {
+            Ok(columns) => (columns.into_iter().map(|c| c.column_name).collect(), None),
+            Err(e) => (Arc::from(Vec::new()), Some(e)),
+        };
+        Query {
+            db,
+            table_name: table_name.to_string(),
+            column_names,
+            filter: None,
+            projection: None,
+            limit: None,
+            offset: 0,
+            error,
+        }
+    }
+
+    /// Adds a condition over column positions.
+    pub fn filter(mut self, predicate: Predicate) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(filter) => filter.and(predicate),
+            None => predicate,
+        });
+        self
+    }
+
+    pub fn eq(self, column: &str, value: DataArray) -> Self {
+        self.on_column(column, |c| Predicate::Eq(c, value))
+    }
+
+    pub fn ne(self, column: &str, value: DataArray) -> Self {
+        self.on_column(column, |c| Predicate::Ne(c, value))
+    }
+
+    pub fn lt(self, column: &str, value: DataArray) -> Self {
+        self.on_column(column, |c| Predicate::Lt(c, value))
+    }
+
+    pub fn le(self, column: &str, value: DataArray) -> Self {
+        self.on_column(column, |c| Predicate::Le(c, value))
+    }
+
+    pub fn gt(self, column: &str, value: DataArray) -> Self {
+        self.on_column(column, |c| Predicate::Gt(c, value))
+    }
+
+    pub fn ge(self, column: &str, value: DataArray) -> Self {
+        self.on_column(column, |c| Predicate::Ge(c, value))
+    }
+
+    pub fn is_null(self, column: &str) -> Self {
+        self.on_column(column, Predicate::IsNull)
+    }
+
+    pub fn is_not_null(self, column: &str) -> Self {
+        self.on_column(column, Predicate::IsNotNull)
+    }
+
+    /// Returns only these columns, in this order.
+    pub fn select(mut self, columns: &[&str]) -> Self {
+        self.projection = Some(columns.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn run(self) -> Result<Vec<Row>> {
+        self.rows(false)
+    }
+
+    /// Runs the query and writes each row as a JSON object on its own line, with spilled
+    /// BLOBs read in. Returns how many rows were written.
+    pub fn write_json_lines<W: Write>(self, mut writer: W) -> Result<usize> {
+        let rows = self.rows(true)?;
+        for row in &rows {
+            writeln!(writer, "{}", row.to_json())?;
+        }
+        writer.flush()?;
+        Ok(rows.len())
+    }
+
+    fn rows(mut self, inline_blobs: bool) -> Result<Vec<Row>> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        let rows = match &self.filter {
+            Some(filter) => self.db.select_where(&self.table_name, filter)?,
+            None => self.db.scan(&self.table_name)?,
+        };
+        let mut rows: Vec<Vec<DataArray>> = rows.into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        if inline_blobs {
+            for row in &mut rows {
+                self.db.query_handler().inline_blobs(&self.table_name, row)?;
+            }
+        }
+
+        let projection = match &self.projection {
+            Some(names) => names.iter()
+                .map(|name| self.position(name))
+                .collect::<Result<Vec<usize>>>()?,
+            None => (0..self.column_names.len()).collect(),
+        };
+        let column_names: Arc<[String]> = projection.iter()
+            .map(|&i| self.column_names[i].clone())
+            .collect();
+
+        Ok(rows.into_iter()
+            .map(|values| {
+                let values = projection.iter().map(|&i| values[i].clone()).collect();
+                Row::new(column_names.clone(), values)
+            })
+            .collect())
+    }
+
+    /// The first matching row, if any.
+    pub fn first(self) -> Result<Option<Row>> {
+        Ok(self.limit(1).run()?.into_iter().next())
+    }
+
+    fn on_column<F>(mut self, column: &str, condition: F) -> Self
+    where
+        F: FnOnce(usize) -> Predicate,
+    {
+        match self.position(column) {
+            Ok(index) => self.filter(condition(index)),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self
+            }
+        }
+    }
+
+    fn position(&self, column: &str) -> Result<usize> {
+        self.column_names.iter()
+            .position(|c| c == column)
+            .ok_or_else(|| Error::not_found(format!("Table '{}' has no column '{}'", self.table_name, column)))
+    }
+}
@@ -1,17 +1,58 @@
 use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
-use std::io::{BufReader, BufWriter, Read, Write, Seek, SeekFrom};
-use std::sync::Mutex;
-use crate::MetaEnum::MetaEnum;
-use crate::RowData::RawData;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::MetaEnum::{MetaEnum, DataArray, row_array};
+use crate::RowData::{RawData, decode_values, page_schema_version};
+use crate::TableCreationHandler::TableColumn;
+use crate::FileWriter::File_Handler;
+use crate::DiskIo;
+use crate::ErrorTypes::{Error, Result};
 
+/// In-memory view of the system catalog. The catalog itself lives in the system tables
+/// `sys_tables`, `sys_columns` and `sys_indexes`, stored as ordinary pages in `<name>.dat`.
+/// Every file the catalog touches is resolved against the database directory `dir`.
 pub struct TableMetaHandler {
-    file_name: String,
+    dir: PathBuf,
     table_id: HashMap<String, i64>,
+    tables: HashMap<i64, TableMetadata>,
     table_id_meta: HashMap<i64, Vec<MetaEnum>>,
+    table_id_columns: HashMap<i64, Vec<TableColumn>>,
+    // Highest ids seen in any system table row, so ids left behind by an interrupted create are never reused
+    max_table_id: i32,
+    max_index_id: i32,
 }
 
-pub static meta_config: Mutex<Option<TableMetaHandler>> = Mutex::new(None);
+pub const SYS_TABLES: &str = "sys_tables";
+pub const SYS_COLUMNS: &str = "sys_columns";
+pub const SYS_INDEXES: &str = "sys_indexes";
+
+// Table ids below FIRST_USER_TABLE_ID are reserved for the system tables
+const SYS_TABLES_ID: i32 = 0;
+const SYS_COLUMNS_ID: i32 = 1;
+const SYS_INDEXES_ID: i32 = 2;
+pub const FIRST_USER_TABLE_ID: i32 = 3;
+
+/// Longest table, column or index name the catalog can hold, in bytes
+pub const MAX_NAME_LEN: usize = 128;
+
+/// Intent record for a DROP/TRUNCATE in progress; replayed on the next open if present
+const JOURNAL_FILE: &str = "catalog.journal";
+
+/// Catalog file used before the system tables existed; imported once on first open, then renamed to `.bak`
+const LEGACY_CATALOG_FILE: &str = "meta_config.db";
+
+/// High bit of a column's type byte in the legacy (unversioned) meta file marks the column NOT NULL
+const NOT_NULL_FLAG: u8 = 0x80;
+
+/// Versioned meta files start with this magic followed by a u32 format version.
+/// Files without it are the legacy format that only stored column types.
+const CATALOG_MAGIC: &[u8; 4] = b"OXCT";
+const CATALOG_VERSION: u32 = 2;
+
+// Per-column flags in the versioned meta file
+const COLUMN_NOT_NULL: u8 = 0x01;
+const COLUMN_PRIMARY: u8 = 0x02;
 
 #[derive(Clone)]
 enum DataTypeVsId {
@@ -20,6 +61,12 @@ enum DataTypeVsId {
     DOUBLE = 3,
     BIGINT = 4,
     STRING = 5,
+    CHAR = 6,
+    BOOLEAN = 7,
+    DATE = 8,
+    TIMESTAMP = 9,
+    DECIMAL = 10,
+    BLOB = 11,
 }
 
 impl DataTypeVsId {
@@ -30,10 +77,18 @@ impl DataTypeVsId {
             3 => Some(DataTypeVsId::DOUBLE),
             4 => Some(DataTypeVsId::BIGINT),
             5 => Some(DataTypeVsId::STRING),
+            6 => Some(DataTypeVsId::CHAR),
+            7 => Some(DataTypeVsId::BOOLEAN),
+            8 => Some(DataTypeVsId::DATE),
+            9 => Some(DataTypeVsId::TIMESTAMP),
+            10 => Some(DataTypeVsId::DECIMAL),
+            11 => Some(DataTypeVsId::BLOB),
             _ => None,
         }
     }
 
+    /// `string_length` is the 4-byte type parameter stored after the type byte:
+    /// the length for STRING/CHAR, and `precision << 8 | scale` for DECIMAL.
     fn to_meta_enum(&self, string_length: Option<i32>) -> MetaEnum {
         match self {
             DataTypeVsId::INTEGER => MetaEnum::INTEGER,
@@ -41,6 +96,15 @@ impl DataTypeVsId {
             DataTypeVsId::DOUBLE => MetaEnum::DOUBLE,
             DataTypeVsId::BIGINT => MetaEnum::BIGINT,
             DataTypeVsId::STRING => MetaEnum::STRING(string_length.unwrap_or(0) as i64),
+            DataTypeVsId::CHAR => MetaEnum::CHAR(string_length.unwrap_or(0) as i64),
+            DataTypeVsId::BOOLEAN => MetaEnum::BOOLEAN,
+            DataTypeVsId::DATE => MetaEnum::DATE,
+            DataTypeVsId::TIMESTAMP => MetaEnum::TIMESTAMP,
+            DataTypeVsId::DECIMAL => {
+                let param = string_length.unwrap_or(0);
+                MetaEnum::DECIMAL((param >> 8) as u8, param as u8)
+            }
+            DataTypeVsId::BLOB => MetaEnum::BLOB,
         }
     }
 
@@ -51,327 +115,818 @@ impl DataTypeVsId {
             MetaEnum::DOUBLE => (DataTypeVsId::DOUBLE, None),
             MetaEnum::BIGINT => (DataTypeVsId::BIGINT, None),
             MetaEnum::STRING(len) => (DataTypeVsId::STRING, Some(*len as i32)),
+            MetaEnum::CHAR(len) => (DataTypeVsId::CHAR, Some(*len as i32)),
+            MetaEnum::BOOLEAN => (DataTypeVsId::BOOLEAN, None),
+            MetaEnum::DATE => (DataTypeVsId::DATE, None),
+            MetaEnum::TIMESTAMP => (DataTypeVsId::TIMESTAMP, None),
+            MetaEnum::DECIMAL(precision, scale) => (DataTypeVsId::DECIMAL, Some((*precision as i32) << 8 | *scale as i32)),
+            MetaEnum::BLOB => (DataTypeVsId::BLOB, None),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CatalogOp {
+    Drop = 1,
+    Truncate = 2,
+    Rename = 3,
+}
+
+/// A column as the catalog keeps it: with the schema versions it exists in.
+/// `ordinal` is its position among every column the table ever had and never changes.
+#[derive(Clone, Debug)]
+pub struct StoredColumn {
+    pub(crate) ordinal: i32,
+    pub(crate) column: TableColumn,
+    pub(crate) added_in: u32,
+    pub(crate) dropped_in: Option<u32>,
+}
+
+impl StoredColumn {
+    fn exists_in(&self, version: u32) -> bool {
+        self.added_in <= version && self.dropped_in.is_none_or(|dropped| dropped > version)
+    }
+}
+
+/// A table's schema history. Each ALTER that changes the row layout bumps `schema_version`;
+/// pages record the version their rows were written with, so old rows keep decoding.
+#[derive(Clone)]
 pub struct TableMetadata {
     pub(crate) table_id: i32,
-    table_name: String,
-    columns: Vec<MetaEnum>,
+    pub(crate) table_name: String,
+    pub(crate) schema_version: u32,
+    pub(crate) columns: Vec<StoredColumn>,
+}
+
+impl TableMetadata {
+    fn new(table_id: i32, table_name: String, columns: Vec<TableColumn>) -> Self {
+        let mut table = TableMetadata { table_id, table_name, schema_version: 0, columns: Vec::new() };
+        for column in columns {
+            table.push_column(column, 0);
+        }
+        table
+    }
+
+    fn push_column(&mut self, column: TableColumn, added_in: u32) {
+        let ordinal = self.columns.iter().map(|c| c.ordinal + 1).max().unwrap_or(0);
+        self.columns.push(StoredColumn { ordinal, column, added_in, dropped_in: None });
+        self.schema_version = self.schema_version.max(added_in);
+    }
+
+    /// Columns making up the row layout of `version`, in row order.
+    pub fn columns_at(&self, version: u32) -> Vec<&StoredColumn> {
+        let mut columns: Vec<&StoredColumn> = self.columns.iter().filter(|c| c.exists_in(version)).collect();
+        columns.sort_by_key(|c| c.ordinal);
+        columns
+    }
+
+    pub fn current_columns(&self) -> Vec<TableColumn> {
+        self.columns_at(self.schema_version).into_iter().map(|c| c.column.clone()).collect()
+    }
+
+    fn layout(&self, version: u32) -> Vec<MetaEnum> {
+        self.columns_at(version).into_iter().map(|c| c.column.column_type.clone()).collect()
+    }
+
+    /// Converts a row decoded under `from_version` to the current layout: dropped columns are
+    /// left out and columns added since take their default, or NULL.
+    pub fn upgrade_row(&self, from_version: u32, values: Vec<DataArray>) -> Vec<DataArray> {
+        if from_version == self.schema_version {
+            return values;
+        }
+        let mut old_values: HashMap<i32, DataArray> = self.columns_at(from_version).into_iter()
+            .map(|c| c.ordinal)
+            .zip(values)
+            .collect();
+        self.columns_at(self.schema_version).into_iter()
+            .map(|c| old_values.remove(&c.ordinal)
+                .or_else(|| c.column.default_value.clone())
+                .unwrap_or(DataArray::NULL))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexMetadata {
+    pub index_id: i32,
+    pub table_id: i32,
+    pub index_name: String,
+    pub column_ordinal: usize,
+    pub is_unique: bool,
+}
+
+/// Schemas of the system tables. They describe themselves in `sys_tables`/`sys_columns` too,
+/// but their layout is fixed here so the catalog can be read before anything else is known.
+/// Columns added to them later carry the version they appeared in, like any user table.
+fn system_tables() -> Vec<TableMetadata> {
+    let name_type = MetaEnum::STRING(MAX_NAME_LEN as i64);
+    let required = |name: &str, column_type: MetaEnum| {
+        TableColumn::new(name.to_string(), column_type, false).with_not_null(true)
+    };
+
+    let mut sys_tables = TableMetadata::new(SYS_TABLES_ID, SYS_TABLES.to_string(), vec![
+        TableColumn::new("table_id".to_string(), MetaEnum::INTEGER, true),
+        required("table_name", name_type.clone()),
+    ]);
+    sys_tables.push_column(required("schema_version", MetaEnum::INTEGER).with_default(DataArray::INTEGER(0)), 1);
+
+    let mut sys_columns = TableMetadata::new(SYS_COLUMNS_ID, SYS_COLUMNS.to_string(), vec![
+        required("table_id", MetaEnum::INTEGER),
+        required("ordinal", MetaEnum::INTEGER),
+        required("column_name", name_type.clone()),
+        required("type_id", MetaEnum::INTEGER),
+        TableColumn::new("type_param".to_string(), MetaEnum::INTEGER, false),
+        required("not_null", MetaEnum::BOOLEAN),
+        required("is_primary", MetaEnum::BOOLEAN),
+        // Encoded like a one-column row so any column type round-trips
+        TableColumn::new("default_value".to_string(), MetaEnum::BLOB, false),
+    ]);
+    sys_columns.push_column(required("added_in", MetaEnum::INTEGER).with_default(DataArray::INTEGER(0)), 1);
+    sys_columns.push_column(TableColumn::new("dropped_in".to_string(), MetaEnum::INTEGER, false), 1);
+
+    let sys_indexes = TableMetadata::new(SYS_INDEXES_ID, SYS_INDEXES.to_string(), vec![
+        TableColumn::new("index_id".to_string(), MetaEnum::INTEGER, true),
+        required("table_id", MetaEnum::INTEGER),
+        required("index_name", name_type),
+        required("column_ordinal", MetaEnum::INTEGER),
+        required("is_unique", MetaEnum::BOOLEAN),
+    ]);
+
+    vec![sys_tables, sys_columns, sys_indexes]
+}
+
+pub fn is_system_table(table_name: &str) -> bool {
+    matches!(table_name, SYS_TABLES | SYS_COLUMNS | SYS_INDEXES)
+}
+
+/// Table names become file names in the database directory, so they are limited to ASCII
+/// letters, digits and underscores, not starting with a digit: no separator, `..` or NUL
+/// can make one name a file elsewhere.
+pub fn check_table_name(table_name: &str) -> Result<()> {
+    if table_name.is_empty() {
+        return Err(Error::ConstraintViolation("Table name cannot be empty".to_string()));
+    }
+    if table_name.len() > MAX_NAME_LEN {
+        return Err(Error::ConstraintViolation(format!("Table name is longer than {} bytes", MAX_NAME_LEN)));
+    }
+    let starts_well = table_name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+    if !starts_well || !table_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(Error::ConstraintViolation(format!(
+            "Invalid table name {:?}: use ASCII letters, digits and underscores, not starting with a digit", table_name
+        )));
+    }
+    Ok(())
 }
 
 impl TableMetaHandler {
-    pub fn new(file_name: String) -> Self {
+    pub fn new(dir: &Path) -> Self {
         TableMetaHandler {
-            file_name,
+            dir: dir.to_path_buf(),
             table_id: HashMap::new(),
+            tables: HashMap::new(),
             table_id_meta: HashMap::new(),
+            table_id_columns: HashMap::new(),
+            max_table_id: FIRST_USER_TABLE_ID - 1,
+            max_index_id: 0,
         }
     }
-    
-    pub fn load_table_schema_meta(&mut self) -> Result<(), std::io::Error> {
-        let file_path = "schema/table_meta";
 
-        if !std::path::Path::new(file_path).exists() {
-            fs::create_dir_all("schema")?;
-
-            let mut file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .open(file_path)?;
-            
-            let mut writer = BufWriter::new(&mut file);
-
-            let tables_to_create = vec![
-                (1, "TableIdVsRange"),
-                (2, "tableVsColumn"),
-            ];
+    /// Full path of a file in the database directory.
+    pub fn path(&self, file_name: &str) -> PathBuf {
+        self.dir.join(file_name)
+    }
 
-            let num_entries = tables_to_create.len() as i32;
-            writer.write_all(&num_entries.to_le_bytes())?;
+    /// The data file holding a table's pages.
+    pub fn data_file(&self, table_name: &str) -> PathBuf {
+        self.path(&format!("{}.dat", table_name))
+    }
 
-            for (id, name) in &tables_to_create {
-                let name_bytes = name.as_bytes();
-                let name_len = name_bytes.len() as i32;
-                let entry_size = 4 + name_len + 4;
+    /// Loads the catalog by scanning the system tables, bootstrapping them on first open.
+    pub fn load_catalog(&mut self) -> Result<()> {
+        if !self.data_file(SYS_TABLES).exists() {
+            self.bootstrap()?;
+        }
+        for table in system_tables() {
+            self.cache_table(&table);
+        }
+        self.replay_journal()?;
 
-                writer.write_all(&entry_size.to_le_bytes())?;
-                writer.write_all(&name_len.to_le_bytes())?;
-                writer.write_all(name_bytes)?;
-                writer.write_all(&(*id as i32).to_le_bytes())?;
+        let mut columns: HashMap<i32, Vec<StoredColumn>> = HashMap::new();
+        for values in self.scan_table(SYS_COLUMNS)? {
+            let (table_id, column) = column_from_values(&values)?;
+            self.max_table_id = self.max_table_id.max(table_id);
+            columns.entry(table_id).or_default().push(column);
+        }
 
-                self.table_id.insert(name.to_string(), *id as i64);
-            }
-            writer.flush()?;
-        } else {
-            let mut file = OpenOptions::new().read(true).open(file_path)?;
-            let mut reader = BufReader::new(file);
+        for values in self.scan_table(SYS_INDEXES)? {
+            let index = index_from_values(&values)?;
+            self.max_table_id = self.max_table_id.max(index.table_id);
+            self.max_index_id = self.max_index_id.max(index.index_id);
+        }
 
-            let mut num_entries_bytes = [0u8; 4];
-            if reader.read_exact(&mut num_entries_bytes).is_err() {
-                // File is empty or corrupt, can decide to handle this case.
-                return Ok(()); 
+        for values in self.scan_table(SYS_TABLES)? {
+            let table_id = int_value(&values, 0)?;
+            let table_name = string_value(&values, 1)?;
+            let schema_version = int_value(&values, 2)? as u32;
+            self.max_table_id = self.max_table_id.max(table_id);
+            // System tables keep the layout defined in code
+            if table_id < FIRST_USER_TABLE_ID {
+                continue;
             }
-            let num_entries = i32::from_le_bytes(num_entries_bytes);
 
-            for _ in 0..num_entries {
-                let mut entry_size_bytes = [0u8; 4];
-                reader.read_exact(&mut entry_size_bytes)?;
-                // let _entry_size = i32::from_le_bytes(entry_size_bytes);
-
-                let mut name_len_bytes = [0u8; 4];
-                reader.read_exact(&mut name_len_bytes)?;
-                let name_len = i32::from_le_bytes(name_len_bytes);
+            let mut table = TableMetadata {
+                table_id,
+                table_name,
+                schema_version,
+                columns: columns.remove(&table_id).unwrap_or_default(),
+            };
+            self.discard_uncommitted_alter(&mut table)?;
+            self.cache_table(&table);
+        }
 
-                let mut name_bytes = vec![0u8; name_len as usize];
-                reader.read_exact(&mut name_bytes)?;
-                let table_name = String::from_utf8(name_bytes)
-                    .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid UTF-8"))?;
+        Ok(())
+    }
 
-                let mut table_id_bytes = [0u8; 4];
-                reader.read_exact(&mut table_id_bytes)?;
-                let table_id = i32::from_le_bytes(table_id_bytes);
+    /// An ALTER writes `sys_columns` first and bumps the version in `sys_tables` last. Column
+    /// changes tagged with a version the table never reached were interrupted, so undo them.
+    fn discard_uncommitted_alter(&self, table: &mut TableMetadata) -> Result<()> {
+        let version = table.schema_version;
+        let uncommitted = table.columns.iter()
+            .any(|c| c.added_in > version || c.dropped_in.is_some_and(|dropped| dropped > version));
+        if !uncommitted {
+            return Ok(());
+        }
 
-                self.table_id.insert(table_name, table_id as i64);
+        table.columns.retain(|c| c.added_in <= version);
+        for column in table.columns.iter_mut() {
+            if column.dropped_in.is_some_and(|dropped| dropped > version) {
+                column.dropped_in = None;
             }
         }
-        Ok(())
+        self.save_columns(table)
     }
 
-    pub fn load_meta_file(&mut self) -> Result<Vec<TableMetadata>, std::io::Error> {
+    /// Creates the system tables describing themselves, importing any tables from the legacy
+    /// catalog file. `sys_tables.dat` is written last: its presence marks a finished bootstrap.
+    fn bootstrap(&mut self) -> Result<()> {
+        let mut tables = system_tables();
+        let legacy_file = self.path(LEGACY_CATALOG_FILE);
+        let has_legacy = legacy_file.exists();
+        if has_legacy {
+            tables.extend(Self::read_legacy_catalog(&legacy_file)?);
+        }
 
-        if !std::path::Path::new(&self.file_name).exists() {
+        let mut table_rows = Vec::new();
+        let mut column_rows = Vec::new();
+        let mut index_rows = Vec::new();
+        for table in &tables {
+            table_rows.push(table_row(table));
+            column_rows.extend(table.columns.iter().map(|column| column_row(table.table_id, column)));
+            if table.table_id >= FIRST_USER_TABLE_ID
+                && let Some(index) = self.primary_index_for(table)
+            {
+                index_rows.push(index_row(&index));
+            }
+        }
 
-        OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&self.file_name)?;
-        
-        return Ok(Vec::new());
+        self.write_table_file(SYS_COLUMNS, column_rows)?;
+        self.write_table_file(SYS_INDEXES, index_rows)?;
+        self.write_table_file(SYS_TABLES, table_rows)?;
+
+        if has_legacy {
+            DiskIo::rename(&legacy_file, &self.path(&format!("{}.bak", LEGACY_CATALOG_FILE)))?;
+        }
+        Ok(())
     }
 
-        let mut file = OpenOptions::new()
+    fn read_legacy_catalog(file_name: &Path) -> Result<Vec<TableMetadata>> {
+        let mut buffer = Vec::new();
+        OpenOptions::new()
             .read(true)
-            .open(&self.file_name)?;
+            .open(file_name)?
+            .read_to_end(&mut buffer)?;
+        
+        let is_versioned = buffer.starts_with(CATALOG_MAGIC);
+        let mut reader: &[u8] = &buffer;
+        if is_versioned {
+            reader = &reader[CATALOG_MAGIC.len()..];
+            let version = read_i32(&mut reader)? as u32;
+            if version != CATALOG_VERSION {
+                return Err(Error::corruption(format!("Unsupported catalog version {}", version)));
+            }
+        }
         
-        let mut reader = BufReader::new(file);
         let mut tables = Vec::new();
+        while !reader.is_empty() {
+            let table = if is_versioned {
+                Self::read_table_record(&mut reader)?
+            } else {
+                Self::read_legacy_table_record(&mut reader)?
+            };
+            tables.push(table);
+        }
+        Ok(tables)
+    }
+
+    fn cache_table(&mut self, table: &TableMetadata) {
+        let table_id = table.table_id as i64;
+        let columns = table.current_columns();
+        self.table_id.insert(table.table_name.clone(), table_id);
+        self.table_id_meta.insert(table_id, columns.iter().map(|c| c.column_type.clone()).collect());
+        self.table_id_columns.insert(table_id, columns);
+        self.tables.insert(table_id, table.clone());
+    }
+
+    fn read_table_record(reader: &mut &[u8]) -> Result<TableMetadata> {
+        let _data_length = read_i32(reader)?;
+        let table_id = read_i32(reader)?;
+        let table_name = read_string(reader)?;
+        let num_columns = read_i32(reader)?;
         
-        loop {
-            let mut length_bytes = [0u8; 4];
-            match reader.read_exact(&mut length_bytes) {
-                Ok(_) => {},
-                Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                    break;
-                },
-                Err(e) => return Err(e),
-            }
-            
-            let data_length = i32::from_le_bytes(length_bytes);
-            
-            let mut table_id_bytes = [0u8; 4];
-            reader.read_exact(&mut table_id_bytes)?;
-            let table_id = i32::from_le_bytes(table_id_bytes);
-            
-            let mut table_name_length_bytes = [0u8; 4];
-            reader.read_exact(&mut table_name_length_bytes)?;
-            let table_name_length = i32::from_le_bytes(table_name_length_bytes);
-            
-            let mut table_name_bytes = vec![0u8; table_name_length as usize];
-            reader.read_exact(&mut table_name_bytes)?;
-            let table_name = String::from_utf8_lossy(&table_name_bytes).to_string();
-            
-            let mut num_columns_bytes = [0u8; 4];
-            reader.read_exact(&mut num_columns_bytes)?;
-            let num_columns = i32::from_le_bytes(num_columns_bytes);
-            
-            let mut columns = Vec::new();
-            
-            // Read each column metadata
-            for _ in 0..num_columns {
-                // Read data type (1 byte)
-                let mut data_type_byte = [0u8; 1];
-                reader.read_exact(&mut data_type_byte)?;
-                
-                let data_type = DataTypeVsId::from_byte(data_type_byte[0])
-                    .ok_or_else(|| std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        format!("Invalid data type ID: {}", data_type_byte[0])
-                    ))?;
-                
-                let string_length = match data_type {
-                    DataTypeVsId::STRING => {
-                        let mut string_length_bytes = [0u8; 4];
-                        reader.read_exact(&mut string_length_bytes)?;
-                        Some(i32::from_le_bytes(string_length_bytes))
-                    },
-                    _ => None,
-                };
-                
-                // Convert to MetaEnum and add to columns
-                columns.push(data_type.to_meta_enum(string_length));
-            }
-            
-            let table_metadata = TableMetadata {
-                table_id,
-                table_name: table_name.clone(),
-                columns: columns.clone(),
+        let mut columns = Vec::new();
+        for _ in 0..num_columns {
+            let column_name = read_string(reader)?;
+            let column_type = read_column_type(reader)?;
+            let flags = read_u8(reader)?;
+            let default_len = read_i32(reader)?;
+            let default_value = if default_len < 0 {
+                None
+            } else {
+                let bytes = read_bytes(reader, default_len as usize)?;
+                decode_values(std::slice::from_ref(&column_type), &bytes)?.pop()
             };
             
-            // Store in HashMaps
-            self.table_id.insert(table_name.clone(), table_id as i64);
-            self.table_id_meta.insert(table_id as i64, columns);
-            
-            tables.push(table_metadata);
+            let mut column = TableColumn::new(column_name, column_type, flags & COLUMN_PRIMARY != 0)
+                .with_not_null(flags & COLUMN_NOT_NULL != 0);
+            column.default_value = default_value;
+            columns.push(column);
         }
         
-        Ok(tables)
+        Ok(TableMetadata::new(table_id, table_name, columns))
     }
 
-    pub fn write_meta_file(&self, tables: &[TableMetadata]) -> Result<(), std::io::Error> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&self.file_name)?;
+    /// The legacy format only kept types (with the NOT NULL bit), so names are synthesized.
+    fn read_legacy_table_record(reader: &mut &[u8]) -> Result<TableMetadata> {
+        let _data_length = read_i32(reader)?;
+        let table_id = read_i32(reader)?;
+        let table_name = read_string(reader)?;
+        let num_columns = read_i32(reader)?;
         
-        let mut writer = BufWriter::new(file);
+        let mut columns = Vec::new();
+        for i in 0..num_columns {
+            let type_byte = read_u8(reader)?;
+            let column_type = read_type_payload(reader, type_byte & !NOT_NULL_FLAG)?;
+            columns.push(
+                TableColumn::new(format!("column_{}", i), column_type, false)
+                    .with_not_null(type_byte & NOT_NULL_FLAG != 0),
+            );
+        }
         
-        for table in tables {
-            // Calculate the total length for this table entry
-            let mut data_length = 4 + 4 + table.table_name.len() as i32 + 4; // table_id + name_length + name + num_columns
-            
-            for column in &table.columns {
-                data_length += 1; // data type byte
-                if let MetaEnum::STRING(_) = column {
-                    data_length += 4; // string length
-                }
+        Ok(TableMetadata::new(table_id, table_name, columns))
+    }
+
+    /// Wraps a page read from disk, using the column layout of the schema version in its header.
+    /// A page claiming a version the table never reached, or with a damaged slot array, is corrupt.
+    pub fn page_from_bytes(&self, table_name: &str, page_id: u64, data: Box<[u8]>) -> Result<RawData> {
+        let table = self.get_table_by_name(table_name)
+            .ok_or_else(|| Error::table_not_found(table_name))?;
+        let version = page_schema_version(&data);
+        if version > table.schema_version {
+            return Err(Error::corruption(format!(
+                "Page {} of '{}' has schema version {}, but the table is at version {}",
+                page_id, table_name, version, table.schema_version
+            )));
+        }
+        let page = RawData::new(table_name.to_string(), table.layout(version), data.len(), crate::HEADER_SIZE, page_id, data);
+        page.check()?;
+        Ok(page)
+    }
+
+    /// Reads every row of a table through the page format, without going through the shared
+    /// catalog handle (the caller often already holds its lock). Rows come back in the current layout.
+    fn scan_table(&self, table_name: &str) -> Result<Vec<Vec<DataArray>>> {
+        let table = self.get_table_by_name(table_name)
+            .ok_or_else(|| Error::table_not_found(table_name))?;
+
+        let mut rows = Vec::new();
+        let file_name = self.data_file(table_name);
+        for page_id in 0..File_Handler::page_count(&file_name, crate::PAGE_SIZE) {
+            let data = File_Handler::read_page_bytes(&file_name, page_id, crate::PAGE_SIZE)?;
+            let raw_data = self.page_from_bytes(table_name, page_id, data)?;
+            for offset in 0..raw_data.row_count() as usize {
+                let row = raw_data.decode_row(offset)?;
+                rows.push(table.upgrade_row(raw_data.schema_version(), row));
             }
-            
-            // Write length (4 bytes)
-            writer.write_all(&data_length.to_le_bytes())?;
-            
-            // Write table ID (4 bytes)
-            writer.write_all(&table.table_id.to_le_bytes())?;
-            
-            // Write table name length (4 bytes)
-            let table_name_length = table.table_name.len() as i32;
-            writer.write_all(&table_name_length.to_le_bytes())?;
-            
-            // Write table name
-            writer.write_all(table.table_name.as_bytes())?;
-            
-            // Write number of columns (4 bytes)
-            let num_columns = table.columns.len() as i32;
-            writer.write_all(&num_columns.to_le_bytes())?;
-            
-            // Write each column metadata
-            for column in &table.columns {
-                let (data_type, string_length) = DataTypeVsId::from_meta_enum(column);
-                
-                // Write data type (1 byte)
-                writer.write_all(&[data_type as u8])?;
-                
-                // Write string length if it's a STRING type
-                if let Some(length) = string_length {
-                    writer.write_all(&length.to_le_bytes())?;
+        }
+        Ok(rows)
+    }
+
+    /// Adds rows to `page` and to as many following pages as needed; returns every page touched.
+    /// Rows are encoded with `meta`, and new pages are stamped with `schema_version`.
+    pub fn pack_rows(table_name: &str, meta: &[MetaEnum], schema_version: u32, mut page: RawData, rows: Vec<row_array>) -> Result<Vec<RawData>> {
+        let mut pages = Vec::new();
+        for row in rows {
+            let bytes = row.get_data_as_bytes();
+            if !page.has_room_for(bytes.len()) {
+                if page.row_count() == 0 {
+                    return Err(Error::ConstraintViolation(format!("Row of {} bytes does not fit in a page of {}", bytes.len(), table_name)));
                 }
+                let next_page_id = page.page_id + 1;
+                pages.push(page);
+                page = RawData::new_without_array(table_name.to_string(), meta, crate::PAGE_SIZE, crate::HEADER_SIZE, next_page_id);
+                page.set_schema_version(schema_version);
             }
+            page.add_new_row(&bytes);
         }
-        
-        writer.flush()?;
-        Ok(())
+        pages.push(page);
+        Ok(pages)
     }
 
-    pub fn create_raw_data_for_table(&self, table_name: &str, page_size: usize, header_size: usize, page_id: u64) -> Option<RawData> {
-        if let Some(meta_data) = self.get_table_meta_by_name(table_name) {
-            Some(RawData::new_without_array(
-                table_name.to_string(),
-                meta_data,
-                page_size,
-                header_size,
-                page_id,
-            ))
+    fn append_rows(&self, table_name: &str, rows: Vec<row_array>) -> Result<()> {
+        let table = self.get_table_by_name(table_name)
+            .ok_or_else(|| Error::table_not_found(table_name))?;
+        let meta = table.layout(table.schema_version);
+
+        // Rows only go onto pages of the current version; an older last page is left as it is
+        let file_name = self.data_file(table_name);
+        let page_count = File_Handler::page_count(&file_name, crate::PAGE_SIZE);
+        let last_page = if page_count > 0 {
+            let data = File_Handler::read_page_bytes(&file_name, page_count - 1, crate::PAGE_SIZE)?;
+            Some(self.page_from_bytes(table_name, page_count - 1, data)?)
+                .filter(|page| page.schema_version() == table.schema_version)
         } else {
             None
+        };
+        let last_page = last_page.unwrap_or_else(|| {
+            let mut page = RawData::new_without_array(table_name.to_string(), &meta, crate::PAGE_SIZE, crate::HEADER_SIZE, page_count);
+            page.set_schema_version(table.schema_version);
+            page
+        });
+
+        let file_handler = File_Handler::new(file_name);
+        for page in Self::pack_rows(table_name, &meta, table.schema_version, last_page, rows)? {
+            file_handler.write_to_file(&page)?;
         }
+        Ok(())
     }
 
-    pub fn add_table(&mut self, table_id: i32, table_name: String, columns: Vec<MetaEnum>) -> Result<(), std::io::Error> {
-        self.table_id.insert(table_name.clone(), table_id as i64);
-        self.table_id_meta.insert(table_id as i64, columns.clone());
-        self.append_table_to_file(table_id, &table_name, &columns)?;
-        
-        Ok(())
+    /// Writes a whole table file at once through a temporary file, so it is either complete or absent.
+    fn write_table_file(&self, table_name: &str, rows: Vec<row_array>) -> Result<()> {
+        let table = system_tables().into_iter()
+            .find(|table| table.table_name == table_name)
+            .ok_or_else(|| Error::InvalidOperation(format!("'{}' is not a system table", table_name)))?;
+        let meta = table.layout(table.schema_version);
+
+        let mut first_page = RawData::new_without_array(table_name.to_string(), &meta, crate::PAGE_SIZE, crate::HEADER_SIZE, 0);
+        first_page.set_schema_version(table.schema_version);
+        let pages = Self::pack_rows(table_name, &meta, table.schema_version, first_page, rows)?;
+        Ok(write_pages_atomically(&self.data_file(table_name), &pages)?)
+    }
+
+    /// Removes a table: its rows in the system tables, then its data, overflow and index files.
+    pub fn drop_table(&mut self, table_name: &str) -> Result<()> {
+        let table_id = self.user_table_id(table_name)?;
+        self.run_journaled(CatalogOp::Drop, table_id, table_name, "")
+    }
+
+    /// Deletes a table's data, overflow and index files while keeping its schema.
+    pub fn truncate_table(&mut self, table_name: &str) -> Result<()> {
+        let table_id = self.user_table_id(table_name)?;
+        self.run_journaled(CatalogOp::Truncate, table_id, table_name, "")
+    }
+
+    /// Renames a table and its data, overflow and primary key index files. The table keeps
+    /// its id, so its catalog rows are only renamed.
+    pub fn rename_table(&mut self, table_name: &str, new_name: &str) -> Result<()> {
+        let table_id = self.user_table_id(table_name)?;
+        check_table_name(new_name)?;
+        if is_system_table(new_name) || self.get_table_id(new_name).is_some() {
+            return Err(Error::AlreadyExists(format!("Table '{}' already exists", new_name)));
+        }
+        if let Some(file) = table_files(new_name).into_iter().find(|file| self.path(file).exists()) {
+            return Err(Error::AlreadyExists(format!("File '{}' already exists", file)));
+        }
+        self.run_journaled(CatalogOp::Rename, table_id, table_name, new_name)
     }
 
-    fn append_table_to_file(&self, table_id: i32, table_name: &str, columns: &[MetaEnum]) -> Result<(), std::io::Error> {
+    fn user_table_id(&self, table_name: &str) -> Result<i32> {
+        if is_system_table(table_name) {
+            return Err(Error::InvalidOperation(format!("System table '{}' cannot be changed", table_name)));
+        }
+        self.get_table_id(table_name)
+            .map(|table_id| table_id as i32)
+            .ok_or_else(|| Error::table_not_found(table_name))
+    }
+
+    /// Records the operation before doing it. Every step is idempotent, so after a crash
+    /// the next open simply runs the whole operation again from the journal.
+    /// `new_name` is only used (and only journaled) for a rename.
+    fn run_journaled(&mut self, op: CatalogOp, table_id: i32, table_name: &str, new_name: &str) -> Result<()> {
+        let mut record = vec![op as u8];
+        record.extend_from_slice(&table_id.to_le_bytes());
+        record.extend_from_slice(&(table_name.len() as i32).to_le_bytes());
+        record.extend_from_slice(table_name.as_bytes());
+        if op == CatalogOp::Rename {
+            record.extend_from_slice(&(new_name.len() as i32).to_le_bytes());
+            record.extend_from_slice(new_name.as_bytes());
+        }
+
+        let journal_file = self.path(JOURNAL_FILE);
+        let temp_name = self.path(&format!("{}.tmp", JOURNAL_FILE));
         let mut file = OpenOptions::new()
             .write(true)
-            .append(true)
             .create(true)
-            .open(&self.file_name)?;
-        
-        let mut writer = BufWriter::new(file);
-        
-        let mut data_length = 4 + 4 + table_name.len() as i32 + 4; 
-        
-        for column in columns {
-            data_length += 1; // data type byte
-            if let MetaEnum::STRING(_) = column {
-                data_length += 4; // string length
+            .truncate(true)
+            .open(&temp_name)?;
+        DiskIo::write_all(&mut file, &record)?;
+        file.sync_all()?;
+        DiskIo::rename(&temp_name, &journal_file)?;
+
+        self.apply(op, table_id, table_name, new_name)?;
+        Ok(DiskIo::remove_file(&journal_file)?)
+    }
+
+    fn replay_journal(&mut self) -> Result<()> {
+        let journal_file = self.path(JOURNAL_FILE);
+        let buffer = match fs::read(&journal_file) {
+            Ok(buffer) => buffer,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut reader: &[u8] = &buffer;
+        let op = match read_u8(&mut reader)? {
+            1 => CatalogOp::Drop,
+            2 => CatalogOp::Truncate,
+            3 => CatalogOp::Rename,
+            other => return Err(Error::corruption(format!("Invalid catalog journal operation {}", other))),
+        };
+        let table_id = read_i32(&mut reader)?;
+        let table_name = read_string(&mut reader)?;
+        let new_name = if op == CatalogOp::Rename {
+            read_string(&mut reader)?
+        } else {
+            String::new()
+        };
+
+        self.apply(op, table_id, &table_name, &new_name)?;
+        Ok(DiskIo::remove_file(&journal_file)?)
+    }
+
+    fn apply(&mut self, op: CatalogOp, table_id: i32, table_name: &str, new_name: &str) -> Result<()> {
+        if op == CatalogOp::Rename {
+            return self.apply_rename(table_id, table_name, new_name);
+        }
+        let index_names: Vec<String> = self.scan_table(SYS_INDEXES)?
+            .iter()
+            .map(|values| index_from_values(values))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|index| index.table_id == table_id)
+            .map(|index| index.index_name)
+            .collect();
+
+        // The sys_tables row goes first: once it is gone the table no longer exists
+        if op == CatalogOp::Drop {
+            self.remove_catalog_rows(SYS_TABLES, 0, table_id)?;
+            self.remove_catalog_rows(SYS_COLUMNS, 0, table_id)?;
+            self.remove_catalog_rows(SYS_INDEXES, 1, table_id)?;
+        }
+
+        let mut files = table_files(table_name);
+        files.extend(index_names.iter().map(|index_name| format!("{}.idx", index_name)));
+        for file in files {
+            DiskIo::remove_if_exists(&self.path(&file))?;
+        }
+
+        if op == CatalogOp::Drop {
+            self.table_id.remove(table_name);
+            self.tables.remove(&(table_id as i64));
+            self.table_id_meta.remove(&(table_id as i64));
+            self.table_id_columns.remove(&(table_id as i64));
+        }
+        Ok(())
+    }
+
+    /// Works from the system tables rather than the cache: on replay user tables are not loaded yet.
+    fn apply_rename(&mut self, table_id: i32, table_name: &str, new_name: &str) -> Result<()> {
+        let mut table_rows = Vec::new();
+        for mut values in self.scan_table(SYS_TABLES)? {
+            if int_value(&values, 0)? == table_id {
+                values[1] = name_value(new_name);
+                table_rows.push(system_row(SYS_TABLES, values));
             }
         }
-        
-        writer.write_all(&data_length.to_le_bytes())?;
-        
-        writer.write_all(&table_id.to_le_bytes())?;
-        
-        let table_name_length = table_name.len() as i32;
-        writer.write_all(&table_name_length.to_le_bytes())?;
-        
-        writer.write_all(table_name.as_bytes())?;
-        
-        let num_columns = columns.len() as i32;
-        writer.write_all(&num_columns.to_le_bytes())?;
-        
-        for column in columns {
-            let (data_type, string_length) = DataTypeVsId::from_meta_enum(column);
-            
-            writer.write_all(&[data_type as u8])?;
-            
-            if let Some(length) = string_length {
-                writer.write_all(&length.to_le_bytes())?;
+        self.replace_catalog_rows(SYS_TABLES, 0, table_id, table_rows)?;
+
+        let old_pk_name = format!("{}_pk", table_name);
+        let mut indexes = Vec::new();
+        for values in self.scan_table(SYS_INDEXES)? {
+            let mut index = index_from_values(&values)?;
+            if index.table_id == table_id {
+                if index.index_name == old_pk_name {
+                    index.index_name = format!("{}_pk", new_name);
+                }
+                indexes.push(index);
             }
         }
-        
-        writer.flush()?;
+        let index_rows = indexes.iter().map(index_row).collect();
+        self.replace_catalog_rows(SYS_INDEXES, 1, table_id, index_rows)?;
+
+        // Files already moved by an earlier, interrupted attempt are skipped
+        for (old_file, new_file) in table_files(table_name).into_iter().zip(table_files(new_name)) {
+            match DiskIo::rename(&self.path(&old_file), &self.path(&new_file)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        if let Some(mut table) = self.tables.get(&(table_id as i64)).cloned() {
+            table.table_name = new_name.to_string();
+            self.table_id.remove(table_name);
+            self.cache_table(&table);
+        }
         Ok(())
     }
 
-    pub fn get_all_tables(&self) -> Vec<TableMetadata> {
-        let mut tables = Vec::new();
-        
-        for (table_name, &table_id) in &self.table_id {
-            if let Some(columns) = self.table_id_meta.get(&table_id) {
-                tables.push(TableMetadata {
-                    table_id: table_id as i32,
-                    table_name: table_name.clone(),
-                    columns: columns.clone(),
-                });
+    /// Rewrites a system table without the rows whose `table_id_column` equals `table_id`,
+    /// then appends `new_rows`.
+    fn replace_catalog_rows(&self, table_name: &str, table_id_column: usize, table_id: i32, new_rows: Vec<row_array>) -> Result<()> {
+        let mut rows = Vec::new();
+        for values in self.scan_table(table_name)? {
+            if int_value(&values, table_id_column)? != table_id {
+                rows.push(system_row(table_name, values));
             }
         }
+        rows.extend(new_rows);
+        self.write_table_file(table_name, rows)
+    }
+
+    fn remove_catalog_rows(&self, table_name: &str, table_id_column: usize, table_id: i32) -> Result<()> {
+        self.replace_catalog_rows(table_name, table_id_column, table_id, Vec::new())
+    }
+
+    /// Replaces all of a table's `sys_columns` rows with its in-memory column history.
+    fn save_columns(&self, table: &TableMetadata) -> Result<()> {
+        let rows = table.columns.iter().map(|column| column_row(table.table_id, column)).collect();
+        self.replace_catalog_rows(SYS_COLUMNS, 0, table.table_id, rows)
+    }
+
+    /// Column changes go to `sys_columns` first; the new version in `sys_tables` commits them.
+    fn commit_alter(&mut self, table: TableMetadata) -> Result<()> {
+        self.save_columns(&table)?;
+        let current_version = self.tables.get(&(table.table_id as i64)).map(|t| t.schema_version);
+        if current_version != Some(table.schema_version) {
+            self.replace_catalog_rows(SYS_TABLES, 0, table.table_id, vec![table_row(&table)])?;
+        }
+        self.cache_table(&table);
+        Ok(())
+    }
+
+    fn user_table(&self, table_name: &str) -> Result<TableMetadata> {
+        let table_id = self.user_table_id(table_name)?;
+        self.tables.get(&(table_id as i64))
+            .cloned()
+            .ok_or_else(|| Error::table_not_found(table_name))
+    }
+
+    /// ALTER TABLE ... ADD COLUMN. Existing rows are not touched; they read the column's default.
+    pub fn add_column(&mut self, table_name: &str, column: TableColumn) -> Result<()> {
+        let mut table = self.user_table(table_name)?;
+        if table.current_columns().iter().any(|c| c.column_name == column.column_name) {
+            return Err(Error::AlreadyExists(format!("Table '{}' already has a column '{}'", table_name, column.column_name)));
+        }
+        if column.is_primary {
+            return Err(Error::InvalidOperation("Cannot add a primary key column to an existing table".to_string()));
+        }
+        if column.not_null && column.default_value.as_ref().is_none_or(|value| value.is_null()) {
+            return Err(Error::ConstraintViolation(format!("NOT NULL column '{}' needs a default for existing rows", column.column_name)));
+        }
+
+        let version = table.schema_version + 1;
+        table.push_column(column, version);
+        self.commit_alter(table)
+    }
+
+    /// ALTER TABLE ... DROP COLUMN. The values stay in old pages until the table is rewritten.
+    pub fn drop_column(&mut self, table_name: &str, column_name: &str) -> Result<()> {
+        let mut table = self.user_table(table_name)?;
+        let version = table.schema_version;
+        let live_columns = table.columns.iter().filter(|c| c.exists_in(version)).count();
+
+        let column = table.columns.iter_mut()
+            .find(|c| c.exists_in(version) && c.column.column_name == column_name)
+            .ok_or_else(|| Error::not_found(format!("Table '{}' has no column '{}'", table_name, column_name)))?;
+        if column.column.is_primary {
+            return Err(Error::InvalidOperation(format!("Cannot drop primary key column '{}'", column_name)));
+        }
+        if live_columns == 1 {
+            return Err(Error::InvalidOperation(format!("Cannot drop the only column of table '{}'", table_name)));
+        }
+
+        column.dropped_in = Some(version + 1);
+        table.schema_version = version + 1;
+        self.commit_alter(table)
+    }
+
+    /// ALTER TABLE ... RENAME COLUMN. Names are not part of the row layout, so no new version.
+    pub fn rename_column(&mut self, table_name: &str, old_name: &str, new_name: &str) -> Result<()> {
+        let mut table = self.user_table(table_name)?;
+        let version = table.schema_version;
+        if table.columns_at(version).iter().any(|c| c.column.column_name == new_name) {
+            return Err(Error::AlreadyExists(format!("Table '{}' already has a column '{}'", table_name, new_name)));
+        }
+
+        let column = table.columns.iter_mut()
+            .find(|c| c.exists_in(version) && c.column.column_name == old_name)
+            .ok_or_else(|| Error::not_found(format!("Table '{}' has no column '{}'", table_name, old_name)))?;
+        column.column.column_name = new_name.to_string();
+        self.commit_alter(table)
+    }
+
+    pub fn create_raw_data_for_table(&self, table_name: &str, page_size: usize, header_size: usize, page_id: u64) -> Option<RawData> {
+        let table = self.get_table_by_name(table_name)?;
+        let mut raw_data = RawData::new_without_array(
+            table_name.to_string(),
+            &table.layout(table.schema_version),
+            page_size,
+            header_size,
+            page_id,
+        );
+        raw_data.set_schema_version(table.schema_version);
+        Some(raw_data)
+    }
+
+    /// Records a new table in the system tables. Its `sys_tables` row is written last, so a table
+    /// whose creation was interrupted never becomes visible.
+    pub fn add_table(&mut self, table_id: i32, table_name: String, columns: Vec<TableColumn>) -> Result<()> {
+        let table = TableMetadata::new(table_id, table_name, columns);
+
+        let column_rows = table.columns.iter()
+            .map(|column| column_row(table_id, column))
+            .collect();
+        self.append_rows(SYS_COLUMNS, column_rows)?;
+
+        let index = self.primary_index_for(&table);
+        if let Some(index) = &index {
+            self.append_rows(SYS_INDEXES, vec![index_row(index)])?;
+        }
+
+        self.append_rows(SYS_TABLES, vec![table_row(&table)])?;
+
+        self.max_table_id = self.max_table_id.max(table_id);
+        if let Some(index) = index {
+            self.max_index_id = self.max_index_id.max(index.index_id);
+        }
+        self.cache_table(&table);
         
-        tables
+        Ok(())
+    }
+
+    /// The primary key index a user table gets, stored in `<table>_btree.idx`.
+    fn primary_index_for(&mut self, table: &TableMetadata) -> Option<IndexMetadata> {
+        let column = table.columns.iter().find(|c| c.column.is_primary)?;
+        self.max_index_id += 1;
+        Some(IndexMetadata {
+            index_id: self.max_index_id,
+            table_id: table.table_id,
+            index_name: format!("{}_pk", table.table_name),
+            column_ordinal: column.ordinal as usize,
+            is_unique: true,
+        })
+    }
+
+    pub fn next_table_id(&self) -> i32 {
+        self.max_table_id + 1
+    }
+
+    pub fn get_all_tables(&self) -> Vec<TableMetadata> {
+        self.tables.values().cloned().collect()
     }
     
     pub fn get_table_id(&self, table_name: &str) -> Option<i64> {
         self.table_id.get(table_name).copied()
     }
+
+    /// The table's full schema history.
+    pub fn get_table_by_name(&self, table_name: &str) -> Option<&TableMetadata> {
+        self.tables.get(&self.get_table_id(table_name)?)
+    }
+
+    pub fn get_schema_version(&self, table_name: &str) -> Option<u32> {
+        self.get_table_by_name(table_name).map(|table| table.schema_version)
+    }
+    
+    /// Full column definitions (names, types, flags and defaults), in column order
+    pub fn get_table_columns(&self, table_id: i64) -> Option<&Vec<TableColumn>> {
+        self.table_id_columns.get(&table_id)
+    }
     
-    pub fn get_table_meta(&self, table_id: i64) -> Option<&Vec<MetaEnum>> {
-        self.table_id_meta.get(&table_id)
+    /// Per-column NOT NULL flags, in column order
+    pub fn get_table_not_null_by_name(&self, table_name: &str) -> Option<Vec<bool>> {
+        let table_id = self.get_table_id(table_name)?;
+        let columns = self.table_id_columns.get(&table_id)?;
+        Some(columns.iter().map(|c| c.not_null).collect())
     }
     
     pub fn get_table_meta_by_name(&self, table_name: &str) -> Option<&Vec<MetaEnum>> {
@@ -383,4 +938,190 @@ impl TableMetaHandler {
     }
 }
 
+/// Files holding a table's rows and primary key index, named after the table.
+fn table_files(table_name: &str) -> Vec<String> {
+    vec![
+        format!("{}.dat", table_name),
+        format!("{}.dwb", table_name),
+        format!("{}.ovf", table_name),
+        format!("{}_btree.idx", table_name),
+        format!("{}_btree.stale", table_name),
+    ]
+}
+
+/// Writes `pages` as the whole of `file_name` via a synced temporary file and a rename.
+/// The double-write copy of the old file's last page write is removed first, so it is
+/// never redone over the new file.
+pub fn write_pages_atomically(file_name: &Path, pages: &[RawData]) -> std::io::Result<()> {
+    let bytes: Vec<u8> = pages.iter().flat_map(|page| page.data.iter().copied()).collect();
+    DiskIo::remove_if_exists(&File_Handler::double_write_file(file_name))?;
+    DiskIo::write_file_atomically(file_name, &bytes)
+}
+
+fn system_row(table_name: &str, values: Vec<DataArray>) -> row_array {
+    let meta = system_tables().into_iter()
+        .find(|table| table.table_name == table_name)
+        .map(|table| table.layout(table.schema_version))
+        .unwrap_or_default();
+    let mut row = row_array::new();
+    row.add_meta_array(&meta);
+    row.add_array(values);
+    row
+}
+
+fn name_value(name: &str) -> DataArray {
+    DataArray::STRING(name.to_string(), MAX_NAME_LEN as i32)
+}
+
+fn table_row(table: &TableMetadata) -> row_array {
+    system_row(SYS_TABLES, vec![
+        DataArray::INTEGER(table.table_id),
+        name_value(&table.table_name),
+        DataArray::INTEGER(table.schema_version as i32),
+    ])
+}
+
+fn column_row(table_id: i32, stored: &StoredColumn) -> row_array {
+    let column = &stored.column;
+    let (data_type, type_param) = DataTypeVsId::from_meta_enum(&column.column_type);
+    let default_value = match &column.default_value {
+        Some(value) => {
+            let mut row = row_array::new();
+            row.add_meta(column.column_type.clone());
+            row.add_data(value.clone());
+            DataArray::BLOB(row.get_data_as_bytes())
+        }
+        None => DataArray::NULL,
+    };
+    system_row(SYS_COLUMNS, vec![
+        DataArray::INTEGER(table_id),
+        DataArray::INTEGER(stored.ordinal),
+        name_value(&column.column_name),
+        DataArray::INTEGER(data_type as i32),
+        type_param.map_or(DataArray::NULL, DataArray::INTEGER),
+        DataArray::BOOLEAN(column.not_null || column.is_primary),
+        DataArray::BOOLEAN(column.is_primary),
+        default_value,
+        DataArray::INTEGER(stored.added_in as i32),
+        stored.dropped_in.map_or(DataArray::NULL, |version| DataArray::INTEGER(version as i32)),
+    ])
+}
+
+fn column_from_values(values: &[DataArray]) -> Result<(i32, StoredColumn)> {
+    let table_id = int_value(values, 0)?;
+    let ordinal = int_value(values, 1)?;
+    let column_name = string_value(values, 2)?;
+    let type_id = int_value(values, 3)?;
+    let type_param = match values.get(4) {
+        Some(DataArray::INTEGER(param)) => Some(*param),
+        _ => None,
+    };
+    let column_type = u8::try_from(type_id).ok()
+        .and_then(DataTypeVsId::from_byte)
+        .ok_or_else(|| Error::corruption(format!("Invalid data type ID: {}", type_id)))?
+        .to_meta_enum(type_param);
+
+    let mut column = TableColumn::new(column_name, column_type, bool_value(values, 6)?)
+        .with_not_null(bool_value(values, 5)?);
+    if let Some(DataArray::BLOB(bytes)) = values.get(7) {
+        column.default_value = decode_values(std::slice::from_ref(&column.column_type), bytes)?.pop();
+    }
+    let dropped_in = match values.get(9) {
+        Some(DataArray::INTEGER(version)) => Some(*version as u32),
+        _ => None,
+    };
+    Ok((table_id, StoredColumn {
+        ordinal,
+        column,
+        added_in: int_value(values, 8)? as u32,
+        dropped_in,
+    }))
+}
+
+fn index_row(index: &IndexMetadata) -> row_array {
+    system_row(SYS_INDEXES, vec![
+        DataArray::INTEGER(index.index_id),
+        DataArray::INTEGER(index.table_id),
+        name_value(&index.index_name),
+        DataArray::INTEGER(index.column_ordinal as i32),
+        DataArray::BOOLEAN(index.is_unique),
+    ])
+}
+
+fn index_from_values(values: &[DataArray]) -> Result<IndexMetadata> {
+    Ok(IndexMetadata {
+        index_id: int_value(values, 0)?,
+        table_id: int_value(values, 1)?,
+        index_name: string_value(values, 2)?,
+        column_ordinal: int_value(values, 3)? as usize,
+        is_unique: bool_value(values, 4)?,
+    })
+}
+
+fn int_value(values: &[DataArray], column: usize) -> Result<i32> {
+    match values.get(column) {
+        Some(DataArray::INTEGER(value)) => Ok(*value),
+        other => Err(Error::corruption(format!("Expected INTEGER in catalog column {}, found {:?}", column, other))),
+    }
+}
+
+fn string_value(values: &[DataArray], column: usize) -> Result<String> {
+    match values.get(column) {
+        Some(DataArray::STRING(value, _)) => Ok(value.clone()),
+        other => Err(Error::corruption(format!("Expected STRING in catalog column {}, found {:?}", column, other))),
+    }
+}
 
+fn bool_value(values: &[DataArray], column: usize) -> Result<bool> {
+    match values.get(column) {
+        Some(DataArray::BOOLEAN(value)) => Ok(*value),
+        other => Err(Error::corruption(format!("Expected BOOLEAN in catalog column {}, found {:?}", column, other))),
+    }
+}
+
+fn truncated(_: std::io::Error) -> Error {
+    Error::corruption("Truncated catalog record")
+}
+
+fn read_bytes(reader: &mut &[u8], len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(bytes)
+}
+
+fn read_u8(reader: &mut &[u8]) -> Result<u8> {
+    let mut byte = [0u8; 1];
+    reader.read_exact(&mut byte).map_err(truncated)?;
+    Ok(byte[0])
+}
+
+fn read_i32(reader: &mut &[u8]) -> Result<i32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+fn read_string(reader: &mut &[u8]) -> Result<String> {
+    let len = read_i32(reader)?;
+    if len < 0 {
+        return Err(Error::corruption(format!("Invalid string length {}", len)));
+    }
+    String::from_utf8(read_bytes(reader, len as usize)?)
+        .map_err(|_| Error::corruption("Invalid UTF-8".to_string()))
+}
+
+fn read_column_type(reader: &mut &[u8]) -> Result<MetaEnum> {
+    let type_id = read_u8(reader)?;
+    read_type_payload(reader, type_id)
+}
+
+/// Reads the optional 4-byte parameter that follows a type id and builds the column type.
+fn read_type_payload(reader: &mut &[u8], type_id: u8) -> Result<MetaEnum> {
+    let data_type = DataTypeVsId::from_byte(type_id)
+        .ok_or_else(|| Error::corruption(format!("Invalid data type ID: {}", type_id)))?;
+    let type_param = match data_type {
+        DataTypeVsId::STRING | DataTypeVsId::CHAR | DataTypeVsId::DECIMAL => Some(read_i32(reader)?),
+        _ => None,
+    };
+    Ok(data_type.to_meta_enum(type_param))
+}
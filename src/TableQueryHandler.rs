@@ -1,175 +1,617 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use crate::MetaEnum::{MetaEnum, DataArray, row_array};
-use crate::RowData::RawData;
-use crate::BPlusTree::{BPlusTree, Key, data};
+use crate::RowData::{RawData, RowVersion, format_row, ROW_VERSION_SIZE};
+use crate::UniversalBPlusTree::{BPlusTree, IntBPlusTree};
+use crate::UniversalKey::{Key, data};
 use crate::FileWriter::File_Handler;
-use crate::TableMetaHandler::{meta_config, TableMetaHandler};
-use crate::BTreePersistence::{BTreePersistence, save_btree_manually};
+use crate::TableMetaHandler::{TableMetaHandler, is_system_table, write_pages_atomically, SYS_TABLES, SYS_COLUMNS, SYS_INDEXES};
+use crate::BTreePersistence::BTreePersistence;
+use crate::QueryPredicate::Predicate;
+use crate::TableCreationHandler::TableColumn;
+use crate::BlobStore::{BlobStore, BlobReader, BLOB_INLINE_LIMIT};
+use crate::TransactionManager::{TransactionManager, Snapshot, TxnStatus, INVALID_TXN};
+use crate::ErrorTypes::{Error, Result};
+use crate::ValueTypes::BlobRef;
+use crate::Backup::FileCopy;
+use std::io::Read;
 
+/// What to do with a STRING(n)/CHAR(n) value longer than its declared byte length
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StringOverflowPolicy {
+    Reject,
+    Truncate,
+}
+
+/// What the handler keeps for one table: its primary key index (once loaded or built) and
+/// where the next row goes. Each table's state has its own lock, so readers of a table
+/// share it, its writers take turns, and other tables are not held up.
+struct TableState {
+    index: Option<IntBPlusTree>,
+    file_handler: File_Handler,
+    page_info: (u64, i32),
+    /// Whether the saved index is marked as behind the pages, which the next write would do
+    index_stale: bool,
+}
+
+type SharedTable = Arc<RwLock<TableState>>;
+
+/// Page and slot of one row version
+pub type RowSlot = (u64, i32);
+
+/// A change a transaction made, kept so it can be undone if the transaction rolls back
+#[derive(Clone, Debug)]
+pub enum UndoRecord {
+    /// A new version of `key`, written at `location` in front of `prev`
+    Inserted { table: String, key: i32, location: RowSlot, prev: Option<RowSlot> },
+    /// The version at `location` was marked deleted
+    Deleted { table: String, location: RowSlot },
+}
+
+/// Reads and writes table rows. Locks are always taken in the order table, then catalog;
+/// the catalog lock is never held while waiting for a table.
 pub struct TableQueryHandler {
-    table_indexes: HashMap<String, BPlusTree>,
-    table_file_handlers: HashMap<String, File_Handler>,
-    table_page_info: HashMap<String, (u64, i32)>, 
+    catalog: Arc<RwLock<TableMetaHandler>>,
+    transactions: Arc<TransactionManager>,
+    dir: PathBuf,
+    tables: RwLock<HashMap<String, SharedTable>>,
+    string_overflow: StringOverflowPolicy,
 }
 
 impl TableQueryHandler {
-    pub fn new() -> Self {
+    /// A handler for the tables of the catalog, whose files live in `dir`.
+    pub fn new(catalog: Arc<RwLock<TableMetaHandler>>, transactions: Arc<TransactionManager>, dir: &Path) -> Self {
         TableQueryHandler {
-            table_indexes: HashMap::new(),
-            table_file_handlers: HashMap::new(),
-            table_page_info: HashMap::new(),
+            catalog,
+            transactions,
+            dir: dir.to_path_buf(),
+            tables: RwLock::new(HashMap::new()),
+            string_overflow: StringOverflowPolicy::Reject,
         }
     }
 
-    fn get_file_handler(&mut self, table_name: &str) -> &File_Handler {
-        self.table_file_handlers
-            .entry(table_name.to_string())
-            .or_insert_with(|| File_Handler::new(table_name.to_string()))
+    pub fn set_string_overflow_policy(&mut self, policy: StringOverflowPolicy) {
+        self.string_overflow = policy;
+    }
+
+    /// The state of a table the catalog knows, loaded from its files on first use.
+    fn table_state(&self, table_name: &str) -> Result<SharedTable> {
+        if let Some(state) = self.tables.read().map_err(Error::poisoned)?.get(table_name) {
+            return Ok(state.clone());
+        }
+        if self.read_catalog()?.get_table_id(table_name).is_none() {
+            return Err(Error::table_not_found(table_name));
+        }
+        let mut tables = self.tables.write().map_err(Error::poisoned)?;
+        if let Some(state) = tables.get(table_name) {
+            return Ok(state.clone());
+        }
+        let state = Arc::new(RwLock::new(self.load_table_state(table_name)?));
+        tables.insert(table_name.to_string(), state.clone());
+        Ok(state)
+    }
+
+    /// A table's saved primary key index, rebuilt from the pages if they changed after it was
+    /// saved, and where its next row goes. A table without a data file has neither yet.
+    fn load_table_state(&self, table_name: &str) -> Result<TableState> {
+        let file_handler = File_Handler::new(self.data_file(table_name));
+        if !self.data_file(table_name).exists() {
+            return Ok(TableState { index: None, file_handler, page_info: (0, 0), index_stale: false });
+        }
+        let mut btree = BTreePersistence::load_btree(&self.dir, table_name)
+            .map_err(|e| e.context(format_args!("Failed to load B+Tree for table '{}'", table_name)))?;
+        let index_stale = BTreePersistence::is_stale(&self.dir, table_name);
+        if index_stale {
+            btree = self.rebuild_index(table_name, &btree)?;
+        }
+        Ok(TableState {
+            index: Some(btree),
+            file_handler,
+            page_info: self.restore_page_info(table_name)?,
+            index_stale,
+        })
+    }
+
+    fn all_table_states(&self) -> Result<Vec<(String, SharedTable)>> {
+        Ok(self.tables.read().map_err(Error::poisoned)?
+            .iter()
+            .map(|(name, state)| (name.clone(), state.clone()))
+            .collect())
+    }
+
+    /// Runs `change` with the table locked for writing, so no reader or writer of the table
+    /// sees it halfway. Schema changes to an existing table go through here.
+    pub fn with_table_locked<T, F>(&self, table_name: &str, change: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        let state = self.table_state(table_name)?;
+        let _state = state.write().map_err(Error::poisoned)?;
+        change()
     }
 
+    fn read_catalog(&self) -> Result<RwLockReadGuard<'_, TableMetaHandler>> {
+        self.catalog.read().map_err(Error::poisoned)
+    }
+
+    fn write_catalog(&self) -> Result<RwLockWriteGuard<'_, TableMetaHandler>> {
+        self.catalog.write().map_err(Error::poisoned)
+    }
+
+    fn data_file(&self, table_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.dat", table_name))
+    }
+
+    fn blob_store(&self, table_name: &str) -> BlobStore {
+        BlobStore::new(self.dir.join(format!("{}.ovf", table_name)))
+    }
+
+    /// Adds a row version for `primary_key` written by the snapshot's transaction. A key whose
+    /// newest version is deleted, as the snapshot sees it, gets a new version chained to the
+    /// old one. Returns what undoes the insert.
     pub fn insert(
-        &mut self,
+        &self,
         table_name: String,
         primary_key: i32,
-        row_data: row_array,
-    ) -> Result<(), String> {
+        mut row_data: row_array,
+        snapshot: &Snapshot,
+    ) -> Result<UndoRecord> {
+        if is_system_table(&table_name) {
+            return Err(Error::InvalidOperation(format!("System table '{}' is read-only", table_name)));
+        }
+        let state = self.table_state(&table_name)?;
+        let mut state = state.write().map_err(Error::poisoned)?;
+        let state = &mut *state;
+
         let table_meta = self.get_table_metadata(&table_name)?;
+        let not_null = self.get_table_not_null(&table_name)?;
         
-        self.validate_row_data(&table_meta, &row_data)?;
-        
-        if !self.table_indexes.contains_key(&table_name) {
-            self.table_indexes.insert(table_name.clone(), BPlusTree::new());
-        }
-
-        let (current_page_id, _current_row_count) = self.get_current_page_info(&table_name);
-        let mut raw_data = if std::path::Path::new(&format!("{}.dat", table_name)).exists() {
-
-            match std::panic::catch_unwind(|| {
-                File_Handler::read_from_file(
-                    table_name.clone(),
-                    current_page_id,
-                    4096,
-                )
-            }) {
-                Ok(data) => data,
-                Err(_) => {
-                    RawData::new_without_array(
-                        table_name.clone(),
-                        &table_meta,
-                        4096, 
-                        64, 
-                        current_page_id,
-                    )
-                }
-            }
+        self.validate_row_data(&table_meta, &not_null, &row_data)?;
+        self.enforce_string_lengths(&table_meta, &mut row_data)?;
+        self.conform_decimals(&table_meta, &mut row_data)?;
+        let prev = self.check_insert(state, &table_name, primary_key, snapshot)?;
+        let row_len = self.stored_row_len(&row_data);
+
+        let (mut current_page_id, _current_row_count) = state.page_info;
+        let mut raw_data = if current_page_id < File_Handler::page_count(&self.data_file(&table_name), crate::PAGE_SIZE) {
+            self.read_page(&table_name, current_page_id)?
         } else {
             // Create new page
-            RawData::new_without_array(
-                table_name.clone(),
-                &table_meta,
-                4096, 
-                64,   
-                current_page_id,
-            )
+            self.new_page(&table_name, current_page_id)?
         };
         
-        let current_row_count = self.get_current_row_count(&raw_data)?;
+        // A page only holds rows of one schema version, so rows after an ALTER start a new page;
+        // likewise rows with a version header do not go onto a page written before them
+        let schema_version = self.get_schema_version(&table_name)?;
+        if !raw_data.has_room_for(row_len) || raw_data.schema_version() != schema_version || !raw_data.is_versioned() {
+            if raw_data.row_count() > 0 {
+                current_page_id += 1;
+            }
+            raw_data = self.new_page(&table_name, current_page_id)?;
+            if !raw_data.has_room_for(row_len) {
+                return Err(Error::ConstraintViolation(format!("Row of {} bytes does not fit in a page", row_len)));
+            }
+        }
+        // Only a row known to fit writes its large BLOBs out
+        self.spill_large_blobs(&table_name, &mut row_data)?;
+        let mut row_bytes = RowVersion::new(primary_key, snapshot.txn_id, prev).to_bytes().to_vec();
+        row_bytes.extend(row_data.get_data_as_bytes());
         
-        let row_bytes = row_data.get_data_as_bytes();
+        let current_row_count = raw_data.row_count();
         raw_data.add_new_row(&row_bytes);
         
         let row_offset = current_row_count;
         
-        let file_handler = self.get_file_handler(&table_name);
-        file_handler.write_to_file(&raw_data);
+        self.write_page(state, &table_name, &raw_data)?;
         
-        self.table_page_info.insert(table_name.clone(), (current_page_id, current_row_count + 1));
+        state.page_info = (current_page_id, current_row_count + 1);
         
+        let btree = state.index.get_or_insert_with(BPlusTree::new);
         let data_ptr = Box::new(data::new(current_page_id as i64, row_offset));
-        let key_entry = Box::new(Key::new(primary_key, Some(data_ptr)));
-        
-        let btree = self.table_indexes.get_mut(&table_name).unwrap();
-        btree.insert(Some(key_entry));
+        btree.insert(Some(Box::new(Key::new(primary_key, Some(data_ptr)))));
         
-        println!("Inserted row with primary key {} into table '{}' at page {} offset {}", 
-                 primary_key, table_name, current_page_id, row_offset);
+        Ok(UndoRecord::Inserted {
+            table: table_name,
+            key: primary_key,
+            location: (current_page_id, row_offset),
+            prev,
+        })
+    }
+
+    /// Decides whether the snapshot's transaction may insert `primary_key`, and returns the
+    /// version the new one replaces. A live row is a duplicate; a row another transaction is
+    /// still writing, or deleted after our snapshot, is a write conflict.
+    fn check_insert(&self, state: &TableState, table_name: &str, primary_key: i32, snapshot: &Snapshot) -> Result<Option<RowSlot>> {
+        let (location, version) = match self.newest_version(state, table_name, primary_key)? {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+        let conflict = || Error::WriteConflict { table: table_name.to_string(), key: primary_key };
+        if version.xmin != snapshot.txn_id && self.transactions.status(version.xmin)? == TxnStatus::InProgress {
+            return Err(conflict());
+        }
+        match self.deleter_status(&version)? {
+            None => Err(Error::DuplicateKey { table: table_name.to_string(), key: primary_key }),
+            Some(_) if version.xmax == snapshot.txn_id => Ok(Some(location)),
+            Some(TxnStatus::Committed) if snapshot.may_follow(version.xmax) => Ok(Some(location)),
+            Some(_) => Err(conflict()),
+        }
+    }
+
+    /// Marks the newest version of `primary_key` as deleted by the snapshot's transaction.
+    /// Returns what undoes the delete, or `None` when the snapshot sees no such row.
+    pub fn delete(&self, table_name: &str, primary_key: i32, snapshot: &Snapshot) -> Result<Option<UndoRecord>> {
+        if is_system_table(table_name) {
+            return Err(Error::InvalidOperation(format!("System table '{}' is read-only", table_name)));
+        }
+        let state = self.table_state(table_name)?;
+        let mut state = state.write().map_err(Error::poisoned)?;
+        let (location, version) = match self.newest_version(&state, table_name, primary_key)? {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+        let conflict = || Error::WriteConflict { table: table_name.to_string(), key: primary_key };
+
+        if version.xmin != snapshot.txn_id {
+            let created_unseen = match self.transactions.status(version.xmin)? {
+                TxnStatus::InProgress => true,
+                _ => !snapshot.may_follow(version.xmin),
+            };
+            if created_unseen {
+                // Deleting a row we never saw is nothing to do, unless we see an older version
+                return match self.visible_version(table_name, version.prev, snapshot)? {
+                    Some(_) => Err(conflict()),
+                    None => Ok(None),
+                };
+            }
+        }
+        match self.deleter_status(&version)? {
+            None => {}
+            Some(_) if version.xmax == snapshot.txn_id => return Ok(None),
+            Some(TxnStatus::Committed) if snapshot.may_follow(version.xmax) => return Ok(None),
+            Some(_) => return Err(conflict()),
+        }
+
+        self.update_version(&mut state, table_name, location, |version| version.xmax = snapshot.txn_id)?;
+        Ok(Some(UndoRecord::Deleted { table: table_name.to_string(), location }))
+    }
+
+    /// Reverts one insert or delete of a transaction that is rolling back. Undoing its changes
+    /// newest first leaves every row and index entry as it was before the transaction.
+    pub fn undo(&self, record: &UndoRecord) -> Result<()> {
+        match record {
+            UndoRecord::Inserted { table, key, location, prev } => {
+                let state = self.table_state(table)?;
+                let mut state = state.write().map_err(Error::poisoned)?;
+                self.update_version(&mut state, table, *location, |version| version.xmin = INVALID_TXN)?;
+                if let Some(btree) = state.index.as_ref() {
+                    match prev {
+                        Some((page_id, offset)) => {
+                            btree.insert(Some(Box::new(Key::new(*key, Some(Box::new(data::new(*page_id as i64, *offset)))))));
+                        }
+                        None => {
+                            btree.remove(key);
+                        }
+                    }
+                }
+            }
+            UndoRecord::Deleted { table, location } => {
+                let state = self.table_state(table)?;
+                let mut state = state.write().map_err(Error::poisoned)?;
+                self.update_version(&mut state, table, *location, |version| version.xmax = 0)?;
+            }
+        }
         Ok(())
     }
 
+    /// The newest version of a key that a transaction may still commit: versions whose
+    /// insert was rolled back or cut short by a crash are passed over.
+    fn newest_version(&self, state: &TableState, table_name: &str, primary_key: i32) -> Result<Option<(RowSlot, RowVersion)>> {
+        let mut location = match state.index.as_ref().and_then(|btree| btree.search(&primary_key)) {
+            Some(data_ref) => Some((data_ref.page_id as u64, data_ref.offset)),
+            None => return Ok(None),
+        };
+        while let Some(current) = location {
+            let version = match self.read_page(table_name, current.0)?.row_version(current.1 as usize)? {
+                Some(version) => version,
+                None => return Ok(None),
+            };
+            if self.transactions.status(version.xmin)? != TxnStatus::Aborted {
+                return Ok(Some((current, version)));
+            }
+            location = version.prev;
+        }
+        Ok(None)
+    }
+
+    /// Where the delete of a version stands; `None` when it was never deleted or the delete
+    /// was rolled back.
+    fn deleter_status(&self, version: &RowVersion) -> Result<Option<TxnStatus>> {
+        if version.xmax == 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.transactions.status(version.xmax)?).filter(|status| *status != TxnStatus::Aborted))
+    }
+
+    /// Follows a version chain from `location` back to the first version the snapshot sees,
+    /// and decodes it in the current column order.
+    fn visible_version(&self, table_name: &str, location: Option<RowSlot>, snapshot: &Snapshot) -> Result<Option<Vec<DataArray>>> {
+        match self.find_visible(table_name, location, snapshot)? {
+            Some(((_, offset), raw_data)) => self.decode_current(table_name, &raw_data, offset as usize).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The first version along a chain that the snapshot sees, with the page it is on.
+    fn find_visible(&self, table_name: &str, mut location: Option<RowSlot>, snapshot: &Snapshot) -> Result<Option<(RowSlot, RawData)>> {
+        while let Some((page_id, offset)) = location {
+            let raw_data = self.read_page(table_name, page_id)?;
+            let version = match raw_data.row_version(offset as usize)? {
+                Some(version) => version,
+                None => return Ok(None),
+            };
+            if snapshot.can_see(&version) {
+                return Ok(Some(((page_id, offset), raw_data)));
+            }
+            location = version.prev;
+        }
+        Ok(None)
+    }
+
+    /// The snapshot a read goes through: the transaction's, or for a read outside one, the
+    /// latest committed data. The latter is only taken once the table is locked, so vacuum
+    /// cannot remove a version it still needs.
+    fn read_snapshot(&self, snapshot: Option<&Snapshot>) -> Result<Snapshot> {
+        match snapshot {
+            Some(snapshot) => Ok(snapshot.clone()),
+            None => self.transactions.snapshot(),
+        }
+    }
+
+    /// Rewrites the version header of one row in place.
+    fn update_version<F>(&self, state: &mut TableState, table_name: &str, (page_id, offset): RowSlot, change: F) -> Result<()>
+    where
+        F: FnOnce(&mut RowVersion),
+    {
+        let mut raw_data = self.read_page(table_name, page_id)?;
+        let mut version = raw_data.row_version(offset as usize)?
+            .ok_or_else(|| Error::corruption(format!("Row {} on page {} of '{}' was removed while still in use", offset, page_id, table_name)))?;
+        change(&mut version);
+        raw_data.set_row_version(offset as usize, &version)?;
+        self.write_page(state, table_name, &raw_data)
+    }
+
+    /// Writes one page of a table in place. The first write after its index was saved marks
+    /// the saved index stale, so if the process dies before the next save, the next open
+    /// rebuilds the index from the pages.
+    fn write_page(&self, state: &mut TableState, table_name: &str, raw_data: &RawData) -> Result<()> {
+        self.mark_index_stale(state, table_name)?;
+        state.file_handler.write_to_file(raw_data)?;
+        Ok(())
+    }
+
+    fn mark_index_stale(&self, state: &mut TableState, table_name: &str) -> Result<()> {
+        if !state.index_stale {
+            BTreePersistence::mark_stale(&self.dir, table_name)?;
+            state.index_stale = true;
+        }
+        Ok(())
+    }
+
+    /// Saves a table's index and clears the stale mark.
+    fn save_index(&self, state: &mut TableState, table_name: &str) -> Result<()> {
+        if let Some(btree) = &state.index {
+            BTreePersistence::save_btree(&self.dir, table_name, btree)?;
+            BTreePersistence::clear_stale(&self.dir, table_name)?;
+            state.index_stale = false;
+        }
+        Ok(())
+    }
 
     pub fn select(
         &self,
         table_name: String,
         primary_key: i32,
-    ) -> Result<Option<String>, String> {
-        if !self.table_indexes.contains_key(&table_name) {
-            return Err(format!("Table '{}' not found or has no data", table_name));
-        }
-        
-        let btree = self.table_indexes.get(&table_name).unwrap();
-        let search_result = btree.search(primary_key);
+        snapshot: Option<&Snapshot>,
+    ) -> Result<Option<String>> {
+        let table_meta = self.get_table_metadata(&table_name)?;
+        Ok(self.get(&table_name, primary_key, snapshot)?
+            .map(|row| format_row(&table_meta, &row)))
+    }
+
+    /// Looks a row up by primary key and returns the version the snapshot sees, in the current
+    /// column order.
+    pub fn get(&self, table_name: &str, primary_key: i32, snapshot: Option<&Snapshot>) -> Result<Option<Vec<DataArray>>> {
+        let state = self.table_state(table_name)?;
+        let state = state.read().map_err(Error::poisoned)?;
+        let snapshot = &self.read_snapshot(snapshot)?;
+        let btree = match &state.index {
+            Some(btree) => btree,
+            // A table nothing was inserted into in this session has no index yet
+            None => {
+                self.get_table_metadata(table_name)?;
+                return Ok(None);
+            }
+        };
         
-        match search_result {
-            Some(data_ref) => {
-                let page_id = data_ref.page_id as u64;
-                let offset = data_ref.offset;
-                
-                let raw_data = File_Handler::read_from_file(
-                    table_name.clone(),
-                    page_id,
-                    4096, 
-                );
-                
-                let row_string = raw_data.data_as_str(offset as usize);
-                Ok(Some(row_string))
-            },
+        match btree.search(&primary_key) {
+            Some(data_ref) => self.visible_version(table_name, Some((data_ref.page_id as u64, data_ref.offset)), snapshot),
             None => Ok(None),
         }
     }
 
-
-
-    fn get_table_metadata(&self, table_name: &str) -> Result<Vec<MetaEnum>, String> {
-        let guard = meta_config.lock().map_err(|_| "Failed to lock meta_config")?;
-        let config = guard.as_ref().ok_or("Meta config not initialized")?;
+    fn get_table_metadata(&self, table_name: &str) -> Result<Vec<MetaEnum>> {
+        let config = self.read_catalog()?;
         
         config.get_table_meta_by_name(table_name)
             .cloned()
-            .ok_or_else(|| format!("Table '{}' not found", table_name))
+            .ok_or_else(|| Error::table_not_found(table_name))
+    }
+
+    fn get_table_not_null(&self, table_name: &str) -> Result<Vec<bool>> {
+        let config = self.read_catalog()?;
+        
+        config.get_table_not_null_by_name(table_name)
+            .ok_or_else(|| Error::table_not_found(table_name))
     }
 
-    fn validate_row_data(&self, table_meta: &[MetaEnum], row_data: &row_array) -> Result<(), String> {
+    fn validate_row_data(&self, table_meta: &[MetaEnum], not_null: &[bool], row_data: &row_array) -> Result<()> {
         if table_meta.len() != row_data.data.len() {
-            return Err(format!(
+            return Err(Error::SchemaMismatch(format!(
                 "Column count mismatch: expected {}, got {}",
                 table_meta.len(),
                 row_data.data.len()
-            ));
+            )));
         }
 
         for (i, (expected_type, actual_data)) in table_meta.iter().zip(row_data.data.iter()).enumerate() {
+            if actual_data.is_null() {
+                if not_null.get(i).copied().unwrap_or(false) {
+                    return Err(Error::ConstraintViolation(format!("NULL value in NOT NULL column {}", i)));
+                }
+                continue;
+            }
             if !self.types_match(expected_type, actual_data) {
-                return Err(format!(
+                return Err(Error::TypeMismatch(format!(
                     "Type mismatch at column {}: expected {}, got {}",
                     i, 
                     self.type_name(expected_type),
                     self.data_type_name(actual_data)
-                ));
+                )));
             }
         }
 
         Ok(())
     }
 
-    fn types_match(&self, meta_type: &MetaEnum, data_type: &DataArray) -> bool {
-        match (meta_type, data_type) {
-            (MetaEnum::INTEGER, DataArray::INTEGER(_)) => true,
-            (MetaEnum::FLOAT, DataArray::FLOAT(_)) => true,
-            (MetaEnum::DOUBLE, DataArray::DOUBLE(_)) => true,
-            (MetaEnum::BIGINT, DataArray::BIGINT(_)) => true,
-            (MetaEnum::STRING(_), DataArray::STRING(_, _)) => true,
-            _ => false,
+    /// Checks every STRING(n)/CHAR(n) value against its declared byte length,
+    /// rejecting or truncating according to the configured policy.
+    fn enforce_string_lengths(&self, table_meta: &[MetaEnum], row_data: &mut row_array) -> Result<()> {
+        for (i, (meta, value)) in table_meta.iter().zip(row_data.data.iter_mut()).enumerate() {
+            let limit = match meta {
+                MetaEnum::STRING(n) | MetaEnum::CHAR(n) => *n as usize,
+                _ => continue,
+            };
+            let s = match value {
+                DataArray::STRING(s, _) | DataArray::CHAR(s, _) => s,
+                _ => continue,
+            };
+            if s.len() <= limit {
+                continue;
+            }
+            match self.string_overflow {
+                StringOverflowPolicy::Reject => {
+                    return Err(Error::ConstraintViolation(format!(
+                        "Value too long at column {}: {} bytes exceeds {}",
+                        i,
+                        s.len(),
+                        self.type_name(meta)
+                    )));
+                }
+                StringOverflowPolicy::Truncate => {
+                    let mut cut = limit;
+                    while !s.is_char_boundary(cut) {
+                        cut -= 1;
+                    }
+                    s.truncate(cut);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rounds DECIMAL values to their column's scale and rejects those exceeding its precision.
+    fn conform_decimals(&self, table_meta: &[MetaEnum], row_data: &mut row_array) -> Result<()> {
+        for (i, (meta, value)) in table_meta.iter().zip(row_data.data.iter_mut()).enumerate() {
+            if let (MetaEnum::DECIMAL(precision, scale), DataArray::DECIMAL(d)) = (meta, &mut *value) {
+                let rescaled = d.rescale(*scale)
+                    .filter(|r| r.fits_precision(*precision))
+                    .ok_or_else(|| Error::ConstraintViolation(format!(
+                        "Value {} out of range at column {}: exceeds {}",
+                        d, i, self.type_name(meta)
+                    )))?;
+                *d = rescaled;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves BLOB values too large to keep inline into the table's overflow pages.
+    fn spill_large_blobs(&self, table_name: &str, row_data: &mut row_array) -> Result<()> {
+        for value in row_data.data.iter_mut() {
+            if let DataArray::BLOB(bytes) = value
+                && bytes.len() > BLOB_INLINE_LIMIT
+            {
+                let blob_ref = self.blob_store(table_name).write_bytes(bytes)
+                    .map_err(|e| Error::from(e).context("Failed to write BLOB overflow pages"))?;
+                *value = DataArray::BLOB_REF(blob_ref);
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes the row takes on a page, version header included, once `spill_large_blobs` has
+    /// moved its large BLOBs out. Nothing is written, so a row found too large for a page
+    /// leaves no overflow pages behind.
+    fn stored_row_len(&self, row_data: &row_array) -> usize {
+        let mut stored = row_array::new();
+        stored.add_meta_array(&row_data.meta_data);
+        for value in &row_data.data {
+            stored.add_data(match value {
+                DataArray::BLOB(bytes) if bytes.len() > BLOB_INLINE_LIMIT => {
+                    DataArray::BLOB_REF(BlobRef { first_page: 0, len: bytes.len() as u64 })
+                }
+                other => other.clone(),
+            });
+        }
+        ROW_VERSION_SIZE + stored.get_data_as_bytes().len()
+    }
+
+    /// Streams a BLOB into the table's overflow pages without buffering it in memory.
+    /// The returned value goes into a row like any other BLOB value.
+    pub fn write_blob<R: Read>(&self, table_name: &str, reader: &mut R) -> Result<DataArray> {
+        let state = self.table_state(table_name)?;
+        let _state = state.write().map_err(Error::poisoned)?;
+        self.blob_store(table_name).write_from(reader)
+            .map(DataArray::BLOB_REF)
+            .map_err(|e| Error::from(e).context("Failed to write BLOB"))
+    }
+
+    /// Opens a streaming reader over the BLOB in `column` of the row with `primary_key`.
+    pub fn read_blob(&self, table_name: &str, primary_key: i32, column: usize, snapshot: Option<&Snapshot>) -> Result<Option<BlobReader>> {
+        let row = match self.get(table_name, primary_key, snapshot)? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        match row.into_iter().nth(column) {
+            Some(DataArray::BLOB(bytes)) => Ok(Some(BlobReader::from_bytes(bytes))),
+            Some(DataArray::BLOB_REF(blob_ref)) => self.blob_store(table_name).open(blob_ref)
+                .map(Some)
+                .map_err(|e| Error::from(e).context("Failed to open BLOB")),
+            Some(DataArray::NULL) => Ok(None),
+            Some(other) => Err(Error::TypeMismatch(format!("Column {} is {}, not a BLOB", column, self.data_type_name(&other)))),
+            None => Err(Error::not_found(format!("Column {} does not exist", column))),
+        }
+    }
+
+    /// Replaces the spilled BLOBs of a row read from `table_name` with their bytes, so the row
+    /// can be written out whole. They are read through the row's own references, so the bytes
+    /// are those of the version the row came from.
+    pub fn inline_blobs(&self, table_name: &str, row: &mut [DataArray]) -> Result<()> {
+        for value in row.iter_mut() {
+            if let DataArray::BLOB_REF(blob_ref) = value {
+                let bytes = self.blob_store(table_name).read_all(*blob_ref)
+                    .map_err(|e| Error::from(e).context(format_args!("Failed to read a BLOB of table '{}'", table_name)))?;
+                *value = DataArray::BLOB(bytes);
+            }
         }
+        Ok(())
+    }
+
+    fn types_match(&self, meta_type: &MetaEnum, data_type: &DataArray) -> bool {
+        meta_type.accepts(data_type)
     }
 
     fn type_name(&self, meta_type: &MetaEnum) -> String {
@@ -179,6 +621,12 @@ impl TableQueryHandler {
             MetaEnum::DOUBLE => "DOUBLE".to_string(),
             MetaEnum::BIGINT => "BIGINT".to_string(),
             MetaEnum::STRING(len) => format!("STRING({})", len),
+            MetaEnum::CHAR(len) => format!("CHAR({})", len),
+            MetaEnum::BOOLEAN => "BOOLEAN".to_string(),
+            MetaEnum::DATE => "DATE".to_string(),
+            MetaEnum::TIMESTAMP => "TIMESTAMP".to_string(),
+            MetaEnum::DECIMAL(precision, scale) => format!("DECIMAL({}, {})", precision, scale),
+            MetaEnum::BLOB => "BLOB".to_string(),
         }
     }
 
@@ -189,34 +637,21 @@ impl TableQueryHandler {
             DataArray::DOUBLE(_) => "DOUBLE".to_string(),
             DataArray::BIGINT(_) => "BIGINT".to_string(),
             DataArray::STRING(_, len) => format!("STRING({})", len),
+            DataArray::CHAR(_, len) => format!("CHAR({})", len),
+            DataArray::BOOLEAN(_) => "BOOLEAN".to_string(),
+            DataArray::DATE(_) => "DATE".to_string(),
+            DataArray::TIMESTAMP(_) => "TIMESTAMP".to_string(),
+            DataArray::DECIMAL(d) => format!("DECIMAL(scale {})", d.scale),
+            DataArray::BLOB(_) | DataArray::BLOB_REF(_) => "BLOB".to_string(),
+            DataArray::NULL => "NULL".to_string(),
         }
     }
 
-    fn get_current_page_info(&mut self, table_name: &str) -> (u64, i32) {
-
-        *self.table_page_info.entry(table_name.to_string()).or_insert((0, 0))
-    }
-
-    fn get_current_row_count(&self, raw_data: &RawData) -> Result<i32, String> {
-        const OFFSET_SIZE: usize = std::mem::size_of::<i32>();
-        
-        let row_count_bytes: [u8; OFFSET_SIZE] = raw_data.data[raw_data.header_size..raw_data.header_size + OFFSET_SIZE]
-            .try_into()
-            .map_err(|_| "Failed to read row count")?;
-        
-        let row_count = i32::from_le_bytes(row_count_bytes);
-        Ok(row_count)
-    }
-
-    fn get_row_count_after_insert(&self, raw_data: &RawData) -> Result<i32, String> {
-        self.get_current_row_count(raw_data)
-    }
-
     pub fn create_row(
         &self,
         table_name: &str,
         values: Vec<DataArray>,
-    ) -> Result<row_array, String> {
+    ) -> Result<row_array> {
         let table_meta = self.get_table_metadata(table_name)?;
         
         let mut row = row_array::new();
@@ -226,143 +661,634 @@ impl TableQueryHandler {
         Ok(row)
     }
 
+    /// Position of a column by name, for building predicates.
+    pub fn column_index(&self, table_name: &str, column_name: &str) -> Result<usize> {
+        self.get_table_columns(table_name)?
+            .iter()
+            .position(|c| c.column_name == column_name)
+            .ok_or_else(|| Error::not_found(format!("Table '{}' has no column '{}'", table_name, column_name)))
+    }
+
+    pub fn get_table_columns(&self, table_name: &str) -> Result<Vec<TableColumn>> {
+        let config = self.read_catalog()?;
+        
+        config.get_table_id(table_name)
+            .and_then(|table_id| config.get_table_columns(table_id))
+            .cloned()
+            .ok_or_else(|| Error::table_not_found(table_name))
+    }
+
+    /// Inserts many rows as versions written by the snapshot's transaction, for imports. The
+    /// rows are packed into pages with one write per page, rather than a page read and write
+    /// per row, and every row is checked before the first page is written. An empty index is
+    /// built bottom-up from the sorted keys; otherwise the keys are added one by one.
     pub fn batch_insert(
-        &mut self,
+        &self,
         table_name: String,
         rows: Vec<(i32, row_array)>, 
-    ) -> Result<(), String> {
-        for (primary_key, row_data) in rows {
-            self.insert(table_name.clone(), primary_key, row_data)?;
+        snapshot: &Snapshot,
+    ) -> Result<Vec<UndoRecord>> {
+        if is_system_table(&table_name) {
+            return Err(Error::InvalidOperation(format!("System table '{}' is read-only", table_name)));
         }
-        Ok(())
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let state = self.table_state(&table_name)?;
+        let mut state = state.write().map_err(Error::poisoned)?;
+        let state = &mut *state;
+
+        let table_meta = self.get_table_metadata(&table_name)?;
+        let not_null = self.get_table_not_null(&table_name)?;
+        let empty_page = self.new_page(&table_name, 0)?;
+        let mut batch_keys = HashSet::new();
+        let mut checked = Vec::with_capacity(rows.len());
+        for (primary_key, mut row_data) in rows {
+            if !batch_keys.insert(primary_key) {
+                return Err(Error::DuplicateKey { table: table_name, key: primary_key });
+            }
+            self.validate_row_data(&table_meta, &not_null, &row_data)?;
+            self.enforce_string_lengths(&table_meta, &mut row_data)?;
+            self.conform_decimals(&table_meta, &mut row_data)?;
+            let prev = self.check_insert(state, &table_name, primary_key, snapshot)?;
+            // Every row must fit before any BLOB is spilled, so a rejected batch writes nothing
+            let row_len = self.stored_row_len(&row_data);
+            if !empty_page.has_room_for(row_len) {
+                return Err(Error::ConstraintViolation(format!("Row of {} bytes does not fit in a page", row_len)));
+            }
+            checked.push((primary_key, prev, row_len, row_data));
+        }
+
+        // The current page takes rows first, if it holds rows of this kind
+        let schema_version = self.get_schema_version(&table_name)?;
+        let (mut page_id, _) = state.page_info;
+        let mut page = if page_id < File_Handler::page_count(&self.data_file(&table_name), crate::PAGE_SIZE) {
+            self.read_page(&table_name, page_id)?
+        } else {
+            self.new_page(&table_name, page_id)?
+        };
+        if page.schema_version() != schema_version || !page.is_versioned() {
+            if page.row_count() > 0 {
+                page_id += 1;
+            }
+            page = self.new_page(&table_name, page_id)?;
+        }
+
+        let mut pages = Vec::new();
+        let mut undo = Vec::with_capacity(checked.len());
+        let mut page_changed = false;
+        for (primary_key, prev, row_len, mut row_data) in checked {
+            if !page.has_room_for(row_len) && page.row_count() > 0 {
+                page_id += 1;
+                let full = std::mem::replace(&mut page, self.new_page(&table_name, page_id)?);
+                if page_changed {
+                    pages.push(full);
+                }
+            }
+            self.spill_large_blobs(&table_name, &mut row_data)?;
+            let mut row_bytes = RowVersion::new(primary_key, snapshot.txn_id, prev).to_bytes().to_vec();
+            row_bytes.extend(row_data.get_data_as_bytes());
+            undo.push(UndoRecord::Inserted {
+                table: table_name.clone(),
+                key: primary_key,
+                location: (page.page_id, page.row_count()),
+                prev,
+            });
+            page.add_new_row(&row_bytes);
+            page_changed = true;
+        }
+        pages.push(page);
+
+        for page in &pages {
+            self.write_page(state, &table_name, page)?;
+            state.page_info = (page.page_id, page.row_count());
+        }
+
+        let mut entries: Vec<Key<i32>> = undo.iter()
+            .filter_map(|record| match record {
+                UndoRecord::Inserted { key, location: (page_id, offset), .. } => {
+                    Some(Key::new(*key, Some(Box::new(data::new(*page_id as i64, *offset)))))
+                }
+                UndoRecord::Deleted { .. } => None,
+            })
+            .collect();
+        let btree = state.index.get_or_insert_with(BPlusTree::new);
+        if btree.is_empty() {
+            entries.sort_by_key(|entry| entry.key);
+            *btree = BPlusTree::bulk_load(entries)?;
+        } else {
+            for entry in entries {
+                btree.insert(Some(Box::new(entry)));
+            }
+        }
+
+        Ok(undo)
     }
 
-    pub fn get_available_tables(&self) -> Vec<String> {
-        self.table_indexes.keys().cloned().collect()
+    /// Reads every row of a table the snapshot sees, in storage order.
+    pub fn scan(&self, table_name: &str, snapshot: Option<&Snapshot>) -> Result<Vec<Vec<DataArray>>> {
+        let state = self.table_state(table_name)?;
+        let _state = state.read().map_err(Error::poisoned)?;
+        let snapshot = &self.read_snapshot(snapshot)?;
+
+        let mut rows = Vec::new();
+        for page_id in 0..File_Handler::page_count(&self.data_file(table_name), crate::PAGE_SIZE) {
+            rows.extend(self.read_page_rows(table_name, page_id, snapshot)?
+                .into_iter()
+                .map(|(_, values)| values));
+        }
+        Ok(rows)
     }
 
-    pub fn key_exists(&self, table_name: &str, primary_key: i32) -> bool {
-        if let Some(btree) = self.table_indexes.get(table_name) {
-            btree.search(primary_key).is_some()
-        } else {
-            false
+    /// Every row the snapshot sees with its primary key, in key order. Keys come from the
+    /// index, which also has them for rows written before row versions recorded them.
+    pub fn scan_keyed(&self, table_name: &str, snapshot: Option<&Snapshot>) -> Result<Vec<(i32, Vec<DataArray>)>> {
+        let state = self.table_state(table_name)?;
+        let state = state.read().map_err(Error::poisoned)?;
+        let snapshot = &self.read_snapshot(snapshot)?;
+        let entries = match &state.index {
+            Some(btree) => btree.entries(),
+            None => {
+                self.get_table_metadata(table_name)?;
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut rows = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let head = entry.data.map(|location| (location.page_id as u64, location.offset));
+            if let Some(values) = self.visible_version(table_name, head, snapshot)? {
+                rows.push((entry.key, values));
+            }
         }
+        Ok(rows)
     }
 
-    pub fn load_existing_btrees(&mut self) {
-        let table_names = self.discover_existing_tables();
-        
-        for table_name in table_names {
-            match BTreePersistence::load_btree(&table_name) {
-                Ok(btree) => {
-                    self.get_file_handler(&table_name);
-                    self.table_indexes.insert(table_name.clone(), btree);
-                    
-                    self.restore_page_info(&table_name);
-                    
-                    println!("Loaded table '{}' with B+Tree", table_name);
-                },
-                Err(e) => {
-                    eprintln!("Failed to load BTree for table {}: {}", table_name, e);
-                }
+    /// Decodes the rows of a page the snapshot sees, with their slots, converted from the
+    /// page's schema version to the current one.
+    fn read_page_rows(&self, table_name: &str, page_id: u64, snapshot: &Snapshot) -> Result<Vec<(usize, Vec<DataArray>)>> {
+        let raw_data = self.read_page(table_name, page_id)?;
+        let mut rows = Vec::new();
+        for offset in 0..raw_data.row_count() as usize {
+            if raw_data.row_version(offset)?.is_some_and(|version| snapshot.can_see(&version)) {
+                rows.push((offset, self.decode_current(table_name, &raw_data, offset)?));
             }
         }
+        Ok(rows)
+    }
+
+    /// Decodes one row of a page, converted from the page's schema version to the current one.
+    fn decode_current(&self, table_name: &str, raw_data: &RawData, offset: usize) -> Result<Vec<DataArray>> {
+        let row = raw_data.decode_row(offset)?;
+        let config = self.read_catalog()?;
+        let table = config.get_table_by_name(table_name)
+            .ok_or_else(|| Error::table_not_found(table_name))?;
+        Ok(table.upgrade_row(raw_data.schema_version(), row))
+    }
+
+    /// Reads one page, with the column layout of the schema version it was written with.
+    /// The catalog stays locked while the page is read, since system tables are written
+    /// under the catalog's lock rather than a table lock.
+    fn read_page(&self, table_name: &str, page_id: u64) -> Result<RawData> {
+        let config = self.read_catalog()?;
+        let data = File_Handler::read_page_bytes(&self.data_file(table_name), page_id, crate::PAGE_SIZE)
+            .map_err(|e| Error::from(e).context(format_args!("Failed to read page {} of '{}'", page_id, table_name)))?;
+        
+        config.page_from_bytes(table_name, page_id, data)
     }
 
-    fn restore_page_info(&mut self, table_name: &str) {
-        let data_file = format!("{}.dat", table_name);
+    /// An empty page of the current schema version whose rows carry version headers.
+    fn new_page(&self, table_name: &str, page_id: u64) -> Result<RawData> {
+        let config = self.read_catalog()?;
         
-        if !std::path::Path::new(&data_file).exists() {
-            return;
+        let mut raw_data = config.create_raw_data_for_table(table_name, crate::PAGE_SIZE, crate::HEADER_SIZE, page_id)
+            .ok_or_else(|| Error::table_not_found(table_name))?;
+        raw_data.set_versioned();
+        Ok(raw_data)
+    }
+
+    fn get_schema_version(&self, table_name: &str) -> Result<u32> {
+        let config = self.read_catalog()?;
+        
+        config.get_schema_version(table_name)
+            .ok_or_else(|| Error::table_not_found(table_name))
+    }
+
+    /// Rewrites every page of a table in the current schema version, so its rows no longer
+    /// need converting on read and dropped columns stop taking space. Only live rows the
+    /// primary key index reaches are kept, as frozen versions. The new data file replaces the old one in a single rename; the
+    /// primary key index is rebuilt after it.
+    pub fn rewrite_table(&self, table_name: &str) -> Result<()> {
+        if is_system_table(table_name) {
+            return Err(Error::InvalidOperation(format!("System table '{}' cannot be rewritten", table_name)));
         }
+        self.refuse_during_transactions("rewrite", table_name)?;
+        let state = self.table_state(table_name)?;
+        let mut state = state.write().map_err(Error::poisoned)?;
+        let old_entries = match &state.index {
+            Some(btree) => btree.entries(),
+            None => BTreePersistence::load_btree(&self.dir, table_name)?.entries(),
+        };
         
-        match std::fs::metadata(&data_file) {
-            Ok(metadata) => {
-                let file_size = metadata.len();
-                let page_size = 4096u64;
-                
-                if file_size == 0 {
-                    self.table_page_info.insert(table_name.to_string(), (0, 0));
-                    return;
+        // The live version each key reaches
+        let snapshot = self.transactions.snapshot()?;
+        let mut keys: HashMap<RowSlot, i32> = HashMap::new();
+        for entry in old_entries {
+            let head = entry.data.map(|old| (old.page_id as u64, old.offset));
+            if let Some((location, _)) = self.find_visible(table_name, head, &snapshot)? {
+                keys.insert(location, entry.key);
+            }
+        }
+        
+        let mut pages = vec![self.new_page(table_name, 0)?];
+        let mut entries = Vec::with_capacity(keys.len());
+        for page_id in 0..File_Handler::page_count(&self.data_file(table_name), crate::PAGE_SIZE) {
+            for (offset, values) in self.read_page_rows(table_name, page_id, &snapshot)? {
+                // A row the index does not reach cannot be looked up, so it is not kept
+                let key = match keys.get(&(page_id, offset as i32)) {
+                    Some(key) => *key,
+                    None => continue,
+                };
+                let mut row_bytes = RowVersion::frozen(key).to_bytes().to_vec();
+                row_bytes.extend(self.create_row(table_name, values)?.get_data_as_bytes());
+                let page = pages.last_mut().unwrap();
+                if !page.has_room_for(row_bytes.len()) {
+                    let next_page = self.new_page(table_name, page.page_id + 1)?;
+                    pages.push(next_page);
                 }
                 
-                let num_pages = (file_size + page_size - 1) / page_size; // Round up
-                
-                let last_page_id = if num_pages > 0 { num_pages - 1 } else { 0 };
-                
-                let table_name_clone = table_name.to_string();
-                let row_count = std::panic::catch_unwind(move || {
-                    let raw_data = File_Handler::read_from_file(table_name_clone, last_page_id, 4096);
-                    const OFFSET_SIZE: usize = std::mem::size_of::<i32>();
-                    if raw_data.data.len() >= raw_data.header_size + OFFSET_SIZE {
-                        let row_count_bytes: [u8; OFFSET_SIZE] = raw_data.data[raw_data.header_size..raw_data.header_size + OFFSET_SIZE]
-                            .try_into()
-                            .unwrap_or([0; OFFSET_SIZE]);
-                        i32::from_le_bytes(row_count_bytes)
-                    } else {
-                        0
-                    }
-                }).unwrap_or(0);
-                
-                self.table_page_info.insert(table_name.to_string(), (last_page_id, row_count));
-                
-                println!("Restored page info for '{}': page={}, row_count={}, file_size={}", 
-                        table_name, last_page_id, row_count, file_size);
-            },
-            Err(e) => {
-                println!("Failed to get file metadata for {}: {}", table_name, e);
-                self.table_page_info.insert(table_name.to_string(), (0, 0));
+                let page = pages.last_mut().unwrap();
+                let location = data::new(page.page_id as i64, page.row_count());
+                entries.push(Key::new(key, Some(Box::new(location))));
+                page.add_new_row(&row_bytes);
             }
         }
+        entries.sort_by_key(|entry| entry.key);
+        let btree = BPlusTree::bulk_load(entries)?;
+        
+        self.mark_index_stale(&mut state, table_name)?;
+        write_pages_atomically(&self.data_file(table_name), &pages)
+            .map_err(|e| Error::from(e).context(format_args!("Failed to rewrite table '{}'", table_name)))?;
+        
+        let last_page = pages.last().unwrap();
+        state.page_info = (last_page.page_id, last_page.row_count());
+        state.index = Some(btree);
+        self.save_index(&mut state, table_name)?;
+        
+        Ok(())
     }
 
-    fn discover_existing_tables(&self) -> Vec<String> {
-        let mut table_names = Vec::new();
+    /// Scans a table and keeps the rows for which `predicate` is TRUE.
+    pub fn select_where(&self, table_name: &str, predicate: &Predicate, snapshot: Option<&Snapshot>) -> Result<Vec<Vec<DataArray>>> {
+        Ok(self.scan(table_name, snapshot)?
+            .into_iter()
+            .filter(|row| predicate.matches(row))
+            .collect())
+    }
+
+    /// Drops a table: its catalog entries, data and index files.
+    pub fn drop_table(&self, table_name: &str) -> Result<()> {
+        self.change_table_storage(table_name, |config| config.drop_table(table_name))?;
+        self.tables.write().map_err(Error::poisoned)?.remove(table_name);
         
-        println!("Discovering existing tables...");
+        Ok(())
+    }
+
+    /// Removes every row of a table and empties its indexes, keeping the schema.
+    pub fn truncate_table(&self, table_name: &str) -> Result<()> {
+        self.change_table_storage(table_name, |config| config.truncate_table(table_name))?;
         
-        if let Ok(entries) = std::fs::read_dir(".") {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if let Some(extension) = path.extension() {
-                        if extension == "dat" {
-                            if let Some(stem) = path.file_stem() {
-                                if let Some(table_name) = stem.to_str() {
-                                    // Filter out internal system files
-                                    if !self.is_system_file(table_name) {
-                                        println!("Found table file: {}.dat", table_name);
-                                        table_names.push(table_name.to_string());
-                                    } else {
-                                        println!("Skipping system file: {}.dat", table_name);
-                                    }
-                                }
-                            }
-                        }
+        Ok(())
+    }
+
+    /// Renames a table. Its rows and index stay as they are.
+    pub fn rename_table(&self, table_name: &str, new_name: &str) -> Result<()> {
+        self.refuse_during_transactions("rename", table_name)?;
+        let shared = self.table_state(table_name)?;
+        let mut state = shared.write().map_err(Error::poisoned)?;
+        
+        self.write_catalog()?.rename_table(table_name, new_name)
+            .map_err(|e| e.context(format_args!("Failed to rename table '{}'", table_name)))?;
+        state.file_handler = File_Handler::new(self.data_file(new_name));
+        
+        let mut tables = self.tables.write().map_err(Error::poisoned)?;
+        tables.remove(table_name);
+        tables.insert(new_name.to_string(), shared.clone());
+        
+        Ok(())
+    }
+
+    /// Runs a catalog-level DROP/TRUNCATE with the table locked and forgets its index and
+    /// page position.
+    fn change_table_storage<F>(&self, table_name: &str, change: F) -> Result<i32>
+    where
+        F: FnOnce(&mut TableMetaHandler) -> Result<()>,
+    {
+        self.refuse_during_transactions("drop or truncate", table_name)?;
+        let state = self.table_state(table_name)?;
+        let mut state = state.write().map_err(Error::poisoned)?;
+        let mut config = self.write_catalog()?;
+        
+        let table_id = config.get_table_id(table_name)
+            .ok_or_else(|| Error::table_not_found(table_name))?;
+        change(&mut config).map_err(|e| e.context(format_args!("Failed to change table '{}'", table_name)))?;
+        drop(config);
+        
+        state.index = None;
+        state.page_info = (0, 0);
+        state.index_stale = false;
+        Ok(table_id as i32)
+    }
+
+    /// Operations that replace a table's files would pull rows from under an open
+    /// transaction's snapshot, so they wait until none is open.
+    fn refuse_during_transactions(&self, what: &str, table_name: &str) -> Result<()> {
+        if self.transactions.has_open_transactions()? {
+            return Err(Error::InvalidOperation(format!(
+                "Cannot {} table '{}' while transactions are open", what, table_name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Removes row versions no snapshot can see any more: inserts that were rolled back or
+    /// cut short, and versions whose delete committed before every running transaction
+    /// began. Rows keep their slots, so index entries and version chains stay valid; a key
+    /// whose every version is gone leaves the index. Returns the number of versions removed.
+    pub fn vacuum(&self, table_name: &str) -> Result<usize> {
+        if is_system_table(table_name) {
+            return Err(Error::InvalidOperation(format!("System table '{}' cannot be vacuumed", table_name)));
+        }
+        let state = self.table_state(table_name)?;
+        let mut state = state.write().map_err(Error::poisoned)?;
+        let horizon = self.transactions.horizon()?;
+
+        let mut pages = Vec::new();
+        let mut removed: HashSet<RowSlot> = HashSet::new();
+        let mut chains: HashMap<RowSlot, Option<RowSlot>> = HashMap::new();
+        for page_id in 0..File_Handler::page_count(&self.data_file(table_name), crate::PAGE_SIZE) {
+            let mut raw_data = self.read_page(table_name, page_id)?;
+            if !raw_data.is_versioned() {
+                continue;
+            }
+            let mut changed = false;
+            for offset in 0..raw_data.row_count() as usize {
+                let mut version = match raw_data.row_version(offset)? {
+                    Some(version) => version,
+                    None => continue,
+                };
+                let location = (page_id, offset as i32);
+                chains.insert(location, version.prev);
+                let deleter = self.deleter_status(&version)?;
+                if self.transactions.status(version.xmin)? == TxnStatus::Aborted
+                    || (deleter == Some(TxnStatus::Committed) && version.xmax < horizon)
+                {
+                    removed.insert(location);
+                    changed = true;
+                } else if version.xmax != 0 && deleter.is_none() {
+                    // The delete was cut short by a crash; the row is live again
+                    version.xmax = 0;
+                    raw_data.set_row_version(offset, &version)?;
+                    changed = true;
+                }
+            }
+            if changed {
+                pages.push(raw_data);
+            }
+        }
+        if removed.is_empty() && pages.is_empty() {
+            return Ok(0);
+        }
+
+        // An index entry on a removed version moves to the newest older version that stays
+        if let Some(btree) = state.index.as_ref() {
+            for entry in btree.entries() {
+                let head = match entry.data {
+                    Some(data_ref) => (data_ref.page_id as u64, data_ref.offset),
+                    None => continue,
+                };
+                if !removed.contains(&head) {
+                    continue;
+                }
+                let mut kept = chains.get(&head).copied().flatten();
+                while let Some(location) = kept.filter(|location| removed.contains(location)) {
+                    kept = chains.get(&location).copied().flatten();
+                }
+                match kept {
+                    Some((page_id, offset)) => {
+                        btree.insert(Some(Box::new(Key::new(entry.key, Some(Box::new(data::new(page_id as i64, offset)))))));
+                    }
+                    None => {
+                        btree.remove(&entry.key);
                     }
                 }
             }
         }
+
+        for mut raw_data in pages {
+            let page_id = raw_data.page_id;
+            raw_data.compact(|offset| !removed.contains(&(page_id, offset as i32)));
+            self.write_page(&mut state, table_name, &raw_data)?;
+        }
         
-        println!("Discovered {} tables: {:?}", table_names.len(), table_names);
-        table_names
+        Ok(removed.len())
     }
 
-    fn is_system_file(&self, table_name: &str) -> bool {
-        match table_name {
-            "table_metadata" => true,
-            "meta_config" => true,
-            _ => false,
+    /// The system table files and the names of the user tables, read with the catalog locked
+    /// for reading so no schema change is halfway through.
+    pub(crate) fn backup_catalog(&self) -> Result<(Vec<String>, Vec<FileCopy>)> {
+        let config = self.read_catalog()?;
+        let mut table_names: Vec<String> = config.get_all_tables().into_iter()
+            .map(|table| table.table_name)
+            .filter(|table_name| !is_system_table(table_name))
+            .collect();
+        table_names.sort();
+        let mut files = Vec::new();
+        for table_name in [SYS_TABLES, SYS_COLUMNS, SYS_INDEXES] {
+            let file_name = format!("{}.dat", table_name);
+            files.push((file_name.clone(), std::fs::read(config.path(&file_name))?));
+        }
+        Ok((table_names, files))
+    }
+
+    /// A table's data, overflow and index files as a copy should hold them to show what
+    /// `snapshot` sees. They are read with the table locked for reading, so writers of other
+    /// tables carry on. Versions the snapshot does not see are marked rolled back, and
+    /// deletes it does not see are cleared; the copy's index still finds every row, since a
+    /// key's versions stay chained from the newest. Files the table does not have are skipped.
+    pub(crate) fn backup_table(&self, table_name: &str, snapshot: &Snapshot) -> Result<Vec<FileCopy>> {
+        let state = self.table_state(table_name)?;
+        let _state = state.read().map_err(Error::poisoned)?;
+
+        let mut pages = Vec::new();
+        for page_id in 0..File_Handler::page_count(&self.data_file(table_name), crate::PAGE_SIZE) {
+            let mut raw_data = self.read_page(table_name, page_id)?;
+            if raw_data.is_versioned() {
+                for offset in 0..raw_data.row_count() as usize {
+                    let mut version = match raw_data.row_version(offset)? {
+                        Some(version) => version,
+                        None => continue,
+                    };
+                    if !snapshot.sees(version.xmin) {
+                        version.xmin = INVALID_TXN;
+                    } else if version.xmax != 0 && !snapshot.sees(version.xmax) {
+                        version.xmax = 0;
+                    } else {
+                        continue;
+                    }
+                    raw_data.set_row_version(offset, &version)?;
+                }
+            }
+            pages.extend_from_slice(&raw_data.data);
+        }
+
+        let mut files = Vec::new();
+        if self.data_file(table_name).exists() {
+            files.push((format!("{}.dat", table_name), pages));
+        }
+        for file_name in [
+            format!("{}.ovf", table_name),
+            format!("{}_btree.idx", table_name),
+            format!("{}_btree.stale", table_name),
+        ] {
+            match std::fs::read(self.dir.join(&file_name)) {
+                Ok(bytes) => files.push((file_name, bytes)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(Error::from(e).context(format_args!("Failed to read '{}'", file_name))),
+            }
         }
+        Ok(files)
     }
 
-    pub fn save_btrees(&self) -> Result<(), String> {
-        BTreePersistence::save_all_btrees(&self.table_indexes)
+    /// Tables with a primary key index in memory.
+    pub fn get_available_tables(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for (table_name, state) in self.all_table_states()? {
+            if state.read().map_err(Error::poisoned)?.index.is_some() {
+                names.push(table_name);
+            }
+        }
+        Ok(names)
     }
-}
 
+    /// Loads the primary key index of every table file in the directory now rather than on
+    /// the table's first use, saving those that had to be rebuilt. A damaged index or data
+    /// file is reported rather than skipped.
+    pub fn load_existing_btrees(&self) -> Result<()> {
+        let table_names = self.discover_existing_tables();
+        
+        for table_name in table_names {
+            if self.read_catalog()?.get_table_id(&table_name).is_none() {
+                continue;
+            }
+            let state = self.table_state(&table_name)?;
+            let mut state = state.write().map_err(Error::poisoned)?;
+            if state.index_stale {
+                self.save_index(&mut state, &table_name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A table's index as its pages have it. Each key of a versioned row points at its newest
+    /// version not rolled back: the one no other such version replaced. A key with no such
+    /// version keeps its `saved` entry only if that is a row on a page from before row
+    /// versions, which have no key to read back.
+    fn rebuild_index(&self, table_name: &str, saved: &IntBPlusTree) -> Result<IntBPlusTree> {
+        let mut versions: Vec<(i32, RowSlot)> = Vec::new();
+        let mut replaced: HashSet<RowSlot> = HashSet::new();
+        let mut unversioned: HashSet<u64> = HashSet::new();
+        for page_id in 0..File_Handler::page_count(&self.data_file(table_name), crate::PAGE_SIZE) {
+            let raw_data = self.read_page(table_name, page_id)?;
+            if !raw_data.is_versioned() {
+                unversioned.insert(page_id);
+                continue;
+            }
+            for offset in 0..raw_data.row_count() as usize {
+                let version = match raw_data.row_version(offset)? {
+                    Some(version) => version,
+                    None => continue,
+                };
+                if self.transactions.status(version.xmin)? == TxnStatus::Aborted {
+                    continue;
+                }
+                versions.push((version.key, (page_id, offset as i32)));
+                if let Some(prev) = version.prev {
+                    replaced.insert(prev);
+                }
+            }
+        }
+
+        let mut heads: BTreeMap<i32, RowSlot> = BTreeMap::new();
+        for entry in saved.entries() {
+            if let Some(data_ref) = entry.data {
+                let location = (data_ref.page_id as u64, data_ref.offset);
+                if unversioned.contains(&location.0) {
+                    heads.insert(entry.key, location);
+                }
+            }
+        }
+        // Versions are in the order they were written, so a later head wins
+        for (key, location) in versions {
+            if !replaced.contains(&location) {
+                heads.insert(key, location);
+            }
+        }
 
+        BPlusTree::bulk_load(heads.into_iter()
+            .map(|(key, (page_id, offset))| Key::new(key, Some(Box::new(data::new(page_id as i64, offset))))))
+    }
 
+    /// Where the next row of a table goes: the last complete page of its data file.
+    fn restore_page_info(&self, table_name: &str) -> Result<(u64, i32)> {
+        let data_file = self.data_file(table_name);
+        
+        if !data_file.exists() {
+            return Ok((0, 0));
+        }
+        
+        let file_size = std::fs::metadata(&data_file)?.len();
+        let page_size = crate::PAGE_SIZE as u64;
+        
+        if file_size < page_size {
+            return Ok((0, 0));
+        }
+        
+        // A partial page at the end was never completely written; it is not counted
+        let last_page_id = file_size / page_size - 1;
+        let row_count = self.read_page(table_name, last_page_id)?.row_count();
+        
+        Ok((last_page_id, row_count))
+    }
 
+    fn discover_existing_tables(&self) -> Vec<String> {
+        let mut table_names = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|extension| extension == "dat")
+                    && let Some(table_name) = path.file_stem().and_then(|stem| stem.to_str())
+                    // Filter out internal system files
+                    && !self.is_system_file(table_name)
+                {
+                    table_names.push(table_name.to_string());
+                }
+            }
+        }
+        table_names
+    }
 
+    fn is_system_file(&self, table_name: &str) -> bool {
+        is_system_table(table_name)
+    }
 
+    /// Saves the primary key index of every table that has one, each under its table's lock
+    /// so no insert is half in it.
+    pub fn save_btrees(&self) -> Result<()> {
+        for (table_name, state) in self.all_table_states()? {
+            let mut state = state.write().map_err(Error::poisoned)?;
+            self.save_index(&mut state, &table_name)?;
+        }
+        Ok(())
+    }
+}
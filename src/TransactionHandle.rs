@@ -0,0 +1,265 @@
+use std::time::Duration;
+use crate::BlobStore::BlobReader;
+use crate::DatabaseHandler::Database;
+use crate::ErrorTypes::{Error, Result};
+use crate::LockManager::LockMode;
+use crate::MetaEnum::DataArray;
+use crate::QueryPredicate::Predicate;
+use crate::TableQueryHandler::UndoRecord;
+use crate::TransactionManager::Snapshot;
+
+/// A transaction on an open database, from `Database::begin`.
+///
+/// Reads see the database as it was when the transaction began, plus the transaction's own
+/// writes. Writes stay invisible to everyone else until `commit`. Writing a row that another
+/// transaction changed after this one began fails with `Error::WriteConflict`; roll back and
+/// retry. A transaction dropped without `commit` is rolled back.
+///
+/// Every row a transaction writes is locked exclusively until it ends; `get_for_share` and
+/// `get_for_update` lock rows it only reads. A lock request waits for the holders up to the
+/// lock timeout (`Error::LockTimeout`). If waiting would deadlock, the youngest transaction
+/// in the cycle is rolled back and its request fails with `Error::Deadlock`.
+///
+/// `savepoint` marks a point within the transaction that `rollback_to` returns to, undoing
+/// only the writes made since, so a failed part can be retried without starting over.
+///
+/// ```no_run
+/// # use oxidedb::{Database, Options, Value};
+/// # let db = Database::open("./data", Options::default())?;
+/// let mut txn = db.begin()?;
+/// txn.delete("accounts", 1)?;
+/// txn.insert("accounts", 1, vec![Value::INTEGER(1), Value::INTEGER(90)])?;
+/// txn.commit()?;
+/// # Ok::<(), oxidedb::Error>(())
+/// ```
+pub struct Transaction<'db> {
+    db: &'db Database,
+    snapshot: Snapshot,
+    /// Changes made so far, oldest first
+    undo: Vec<UndoRecord>,
+    /// Open savepoints, oldest first, with how many changes had been made when each was set
+    savepoints: Vec<(String, usize)>,
+    lock_timeout: Option<Duration>,
+    finished: bool,
+    /// A rollback to a savepoint could not undo every write; only `rollback` is left
+    failed: bool,
+    /// What went wrong rolling back after a deadlock, for `rollback` to report
+    abort_error: Option<Error>,
+}
+
+impl<'db> Transaction<'db> {
+    pub(crate) fn begin(db: &'db Database) -> Result<Transaction<'db>> {
+        let snapshot = db.transaction_manager().begin(true)?;
+        Ok(Transaction { db, snapshot, undo: Vec::new(), savepoints: Vec::new(), lock_timeout: db.lock_timeout(), finished: false, failed: false, abort_error: None })
+    }
+
+    /// The transaction's id; ids grow in the order transactions begin.
+    pub fn id(&self) -> u64 {
+        self.snapshot.txn_id
+    }
+
+    pub(crate) fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    /// How long this transaction waits for a row lock; `None` waits indefinitely. Starts as
+    /// `Options::lock_timeout`.
+    pub fn set_lock_timeout(&mut self, timeout: Option<Duration>) {
+        self.lock_timeout = timeout;
+    }
+
+    fn check_open(&self) -> Result<()> {
+        if self.finished {
+            return Err(Error::InvalidOperation(format!("Transaction {} has already ended", self.snapshot.txn_id)));
+        }
+        if self.failed {
+            return Err(Error::InvalidOperation(format!("Transaction {} failed to roll back to a savepoint and can only be rolled back", self.snapshot.txn_id)));
+        }
+        Ok(())
+    }
+
+    /// Sets a savepoint. A name used before is shadowed until this one is released or
+    /// rolled past.
+    pub fn savepoint(&mut self, name: &str) -> Result<()> {
+        self.check_open()?;
+        self.savepoints.push((name.to_string(), self.undo.len()));
+        Ok(())
+    }
+
+    /// Undoes every write made since the savepoint `name` was set, newest first, and drops
+    /// the savepoints set after it. The savepoint itself stays, so it can be rolled back to
+    /// again. Row locks taken since are kept until the transaction ends. If a write cannot
+    /// be undone, the transaction fails: everything but `rollback` is refused from then on.
+    pub fn rollback_to(&mut self, name: &str) -> Result<()> {
+        self.check_open()?;
+        let position = self.savepoint_position(name)?;
+        let mark = self.savepoints[position].1;
+        self.savepoints.truncate(position + 1);
+        let tables = self.db.query_handler();
+        while self.undo.len() > mark {
+            // A record leaves the list only once undone, so `rollback` retries the rest
+            let record = self.undo.last().unwrap();
+            if let Err(e) = tables.undo(record) {
+                self.failed = true;
+                return Err(e);
+            }
+            self.undo.pop();
+        }
+        Ok(())
+    }
+
+    /// Forgets the savepoint `name` and those set after it. Their writes stay part of the
+    /// transaction.
+    pub fn release(&mut self, name: &str) -> Result<()> {
+        self.check_open()?;
+        let position = self.savepoint_position(name)?;
+        self.savepoints.truncate(position);
+        Ok(())
+    }
+
+    fn savepoint_position(&self, name: &str) -> Result<usize> {
+        self.savepoints.iter()
+            .rposition(|(savepoint, _)| savepoint == name)
+            .ok_or_else(|| Error::not_found(format!("Savepoint '{}' does not exist", name)))
+    }
+
+    /// Locks a row, waiting for other holders. A deadlock rolls this transaction back; the
+    /// caller gets `Error::Deadlock` even if that rollback fails, and `rollback` reports it.
+    fn lock(&mut self, table_name: &str, primary_key: i32, mode: LockMode) -> Result<()> {
+        self.check_open()?;
+        let result = self.db.lock_manager().lock(self.snapshot.txn_id, table_name, primary_key, mode, self.lock_timeout);
+        if let Err(Error::Deadlock { .. }) = result {
+            self.abort_error = self.abort().err();
+        }
+        result
+    }
+
+    /// Inserts one value per column, in column order, indexed under `primary_key`.
+    pub fn insert(&mut self, table_name: &str, primary_key: i32, values: Vec<DataArray>) -> Result<()> {
+        self.lock(table_name, primary_key, LockMode::Exclusive)?;
+        let tables = self.db.query_handler();
+        let row = tables.create_row(table_name, values)?;
+        let record = tables.insert(table_name.to_string(), primary_key, row, &self.snapshot)?;
+        self.undo.push(record);
+        Ok(())
+    }
+
+    /// Inserts many rows, each a primary key with one value per column, packed into pages
+    /// with one write per page. If any row is rejected, none is inserted.
+    pub fn bulk_insert(&mut self, table_name: &str, rows: Vec<(i32, Vec<DataArray>)>) -> Result<()> {
+        for (primary_key, _) in &rows {
+            self.lock(table_name, *primary_key, LockMode::Exclusive)?;
+        }
+        let tables = self.db.query_handler();
+        let rows = rows.into_iter()
+            .map(|(primary_key, values)| Ok((primary_key, tables.create_row(table_name, values)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let records = tables.batch_insert(table_name.to_string(), rows, &self.snapshot)?;
+        self.undo.extend(records);
+        Ok(())
+    }
+
+    /// Deletes the row with `primary_key`. Returns false if this transaction sees no such row.
+    pub fn delete(&mut self, table_name: &str, primary_key: i32) -> Result<bool> {
+        self.lock(table_name, primary_key, LockMode::Exclusive)?;
+        match self.db.query_handler().delete(table_name, primary_key, &self.snapshot)? {
+            Some(record) => {
+                self.undo.push(record);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Replaces the row with `primary_key`. Returns false, and changes nothing, if this
+    /// transaction sees no such row.
+    pub fn update(&mut self, table_name: &str, primary_key: i32, values: Vec<DataArray>) -> Result<bool> {
+        if !self.delete(table_name, primary_key)? {
+            return Ok(false);
+        }
+        self.insert(table_name, primary_key, values)?;
+        Ok(true)
+    }
+
+    /// Looks a row up by primary key.
+    pub fn get(&self, table_name: &str, primary_key: i32) -> Result<Option<Vec<DataArray>>> {
+        self.db.query_handler().get(table_name, primary_key, Some(&self.snapshot))
+    }
+
+    /// Locks a row shared, so no other transaction can change it until this one ends, and
+    /// looks it up.
+    pub fn get_for_share(&mut self, table_name: &str, primary_key: i32) -> Result<Option<Vec<DataArray>>> {
+        self.lock(table_name, primary_key, LockMode::Shared)?;
+        self.get(table_name, primary_key)
+    }
+
+    /// Locks a row exclusively, as a write would, and looks it up.
+    pub fn get_for_update(&mut self, table_name: &str, primary_key: i32) -> Result<Option<Vec<DataArray>>> {
+        self.lock(table_name, primary_key, LockMode::Exclusive)?;
+        self.get(table_name, primary_key)
+    }
+
+    pub fn scan(&self, table_name: &str) -> Result<Vec<Vec<DataArray>>> {
+        self.db.query_handler().scan(table_name, Some(&self.snapshot))
+    }
+
+    pub fn select_where(&self, table_name: &str, predicate: &Predicate) -> Result<Vec<Vec<DataArray>>> {
+        self.db.query_handler().select_where(table_name, predicate, Some(&self.snapshot))
+    }
+
+    /// Opens a BLOB column of a row as this transaction sees it.
+    pub fn read_blob(&self, table_name: &str, primary_key: i32, column: usize) -> Result<Option<BlobReader>> {
+        self.db.query_handler().read_blob(table_name, primary_key, column, Some(&self.snapshot))
+    }
+
+    /// Makes the transaction's writes visible to transactions that begin from now on.
+    pub fn commit(mut self) -> Result<()> {
+        if self.finished {
+            return Err(Error::InvalidOperation(format!("Transaction {} was rolled back", self.snapshot.txn_id)));
+        }
+        self.check_open()?;
+        self.finished = true;
+        self.db.transaction_manager().finish(self.snapshot.txn_id, true)?;
+        self.db.lock_manager().release_all(self.snapshot.txn_id)
+    }
+
+    /// Undoes every write of the transaction. After a deadlock, which has already rolled it
+    /// back, returns the error that rollback hit, if any.
+    pub fn rollback(mut self) -> Result<()> {
+        if self.finished {
+            return self.abort_error.take().map_or(Ok(()), Err);
+        }
+        self.abort()
+    }
+
+    /// Ends the transaction and releases its locks whatever goes wrong undoing its writes,
+    /// then returns the first error. If a write could not be undone, the transaction's row
+    /// versions are left to count as aborted.
+    fn abort(&mut self) -> Result<()> {
+        self.finished = true;
+        let tables = self.db.query_handler();
+        let mut undone = Ok(());
+        while let Some(record) = self.undo.pop() {
+            if let Err(e) = tables.undo(&record) {
+                undone = undone.and(Err(e));
+            }
+        }
+        let transactions = self.db.transaction_manager();
+        let ended = match undone {
+            Ok(()) => transactions.finish(self.snapshot.txn_id, false),
+            Err(_) => transactions.abandon(self.snapshot.txn_id),
+        };
+        let released = self.db.lock_manager().release_all(self.snapshot.txn_id);
+        undone.and(ended).and(released)
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        // Drop cannot report an error; call `rollback` to see one. The transaction ends and
+        // releases its locks either way.
+        if !self.finished {
+            let _ = self.abort();
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::RowData::RowVersion;
+use crate::DiskIo;
+use crate::ErrorTypes::{Error, Result};
+
+pub type TxnId = u64;
+
+/// Creator id of rows every transaction sees: rows from before row versions and rewritten rows
+pub const FROZEN_TXN: TxnId = 0;
+/// Creator id of a rolled back insert, which no transaction sees
+pub const INVALID_TXN: TxnId = u64::MAX;
+
+/// Transaction ids are reserved in blocks, so the log is not written for every statement
+const ID_BLOCK: TxnId = 1024;
+
+pub(crate) const LOG_FILE: &str = "transactions.log";
+const LOG_RESERVE: u8 = 1;
+const LOG_BEGIN: u8 = 2;
+const LOG_COMMIT: u8 = 3;
+const LOG_ROLLBACK: u8 = 4;
+const LOG_RECORD_SIZE: usize = 9;
+
+/// Where a transaction stands when a writer checks a row version against it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TxnStatus {
+    InProgress,
+    Committed,
+    Aborted,
+}
+
+/// Which transactions a reader sees: those that committed before the snapshot was taken, plus
+/// its own changes. Ids from `xmax` on started later; ids in `active` were still running.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// The transaction reading, or `FROZEN_TXN` for a single read outside a transaction
+    pub txn_id: TxnId,
+    xmax: TxnId,
+    active: Arc<BTreeSet<TxnId>>,
+    aborted: Arc<HashSet<TxnId>>,
+    /// A single statement outside `begin`, which writes over the latest committed data
+    statement: bool,
+}
+
+impl Snapshot {
+    /// Whether a writer may build on a committed change by `txn_id`: a transaction only on
+    /// changes it sees, a single statement on any.
+    pub fn may_follow(&self, txn_id: TxnId) -> bool {
+        self.statement || self.sees(txn_id)
+    }
+
+    /// Whether a change by `txn_id` is visible: it is our own or committed before the snapshot.
+    pub fn sees(&self, txn_id: TxnId) -> bool {
+        txn_id == FROZEN_TXN
+            || txn_id == self.txn_id
+            || (txn_id != INVALID_TXN
+                && txn_id < self.xmax
+                && !self.active.contains(&txn_id)
+                && !self.aborted.contains(&txn_id))
+    }
+
+    /// Whether a row version exists for this snapshot: its insert is visible and its delete is not.
+    pub fn can_see(&self, version: &RowVersion) -> bool {
+        self.sees(version.xmin) && !(version.xmax != 0 && self.sees(version.xmax))
+    }
+}
+
+struct TxnState {
+    next_id: TxnId,
+    /// Ids below this are reserved in the log
+    reserved: TxnId,
+    /// Running transactions, each with the oldest id its snapshot may still need
+    active: BTreeMap<TxnId, TxnId>,
+    /// Running transactions that were started with `begin` rather than for one statement
+    explicit: BTreeSet<TxnId>,
+    /// Running transactions with a BEGIN in the log: the explicit ones and batch statements
+    logged: BTreeSet<TxnId>,
+    /// Transactions whose changes count as rolled back although their row versions say otherwise
+    aborted: Arc<HashSet<TxnId>>,
+    log: File,
+}
+
+/// Hands out transaction ids and snapshots, and keeps the transaction log: id reservations,
+/// and BEGIN/COMMIT/ROLLBACK of explicit transactions and batch statements. A transaction whose BEGIN has no
+/// COMMIT or ROLLBACK after it was cut short by a crash; its row versions count as aborted.
+pub struct TransactionManager {
+    state: Mutex<TxnState>,
+}
+
+impl TransactionManager {
+    /// Reads the transaction log in `dir` and rewrites it with only what is still needed: the
+    /// next id and the transactions that never finished.
+    pub fn open(dir: &Path) -> Result<TransactionManager> {
+        let log_path = dir.join(LOG_FILE);
+        let mut bytes = Vec::new();
+        if log_path.exists() {
+            File::open(&log_path)?.read_to_end(&mut bytes)?;
+        }
+
+        let mut next_id = FROZEN_TXN + 1;
+        let mut unfinished = BTreeSet::new();
+        // A record cut short by a crash is ignored
+        for record in bytes.chunks_exact(LOG_RECORD_SIZE) {
+            let txn_id = u64::from_le_bytes(record[1..].try_into().unwrap());
+            match record[0] {
+                LOG_RESERVE => next_id = next_id.max(txn_id),
+                LOG_BEGIN => {
+                    unfinished.insert(txn_id);
+                }
+                LOG_COMMIT | LOG_ROLLBACK => {
+                    unfinished.remove(&txn_id);
+                }
+                kind => return Err(Error::corruption(format!("Unknown transaction log record {}", kind))),
+            }
+            next_id = next_id.max(txn_id.saturating_add(1));
+        }
+
+        let reserved = next_id + ID_BLOCK;
+        let mut compacted = Vec::new();
+        compacted.extend(log_record(LOG_RESERVE, reserved));
+        for txn_id in &unfinished {
+            compacted.extend(log_record(LOG_BEGIN, *txn_id));
+        }
+        DiskIo::write_file_atomically(&log_path, &compacted)?;
+        let log = OpenOptions::new().append(true).open(&log_path)?;
+
+        Ok(TransactionManager {
+            state: Mutex::new(TxnState {
+                next_id,
+                reserved,
+                active: BTreeMap::new(),
+                explicit: BTreeSet::new(),
+                logged: BTreeSet::new(),
+                aborted: Arc::new(unfinished.into_iter().collect()),
+                log,
+            }),
+        })
+    }
+
+    fn lock_state(&self) -> Result<MutexGuard<'_, TxnState>> {
+        self.state.lock().map_err(Error::poisoned)
+    }
+
+    /// Starts a transaction and takes its snapshot. An explicit transaction is logged, so a
+    /// crash before it commits is recognised on the next open.
+    pub fn begin(&self, explicit: bool) -> Result<Snapshot> {
+        self.start(explicit, explicit)
+    }
+
+    /// Starts the transaction of one statement that writes many rows, such as a bulk insert.
+    /// It is logged like an explicit transaction, so a crash part way through leaves none of
+    /// its rows, but it does not hold off the operations `has_open_transactions` guards.
+    pub fn begin_batch(&self) -> Result<Snapshot> {
+        self.start(false, true)
+    }
+
+    fn start(&self, explicit: bool, logged: bool) -> Result<Snapshot> {
+        let mut state = self.lock_state()?;
+        let txn_id = state.next_id;
+        if txn_id + 1 >= state.reserved {
+            let reserved = state.reserved + ID_BLOCK;
+            append_log(&mut state.log, LOG_RESERVE, reserved)?;
+            state.reserved = reserved;
+        }
+        if logged {
+            append_log(&mut state.log, LOG_BEGIN, txn_id)?;
+            state.logged.insert(txn_id);
+        }
+        if explicit {
+            state.explicit.insert(txn_id);
+        }
+        state.next_id += 1;
+
+        let mut snapshot = self.snapshot_of(&state, txn_id);
+        snapshot.statement = !explicit;
+        let oldest = snapshot.active.iter().next().copied().unwrap_or(txn_id);
+        state.active.insert(txn_id, oldest);
+        Ok(snapshot)
+    }
+
+    /// A snapshot for reading outside any transaction.
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        let state = self.lock_state()?;
+        Ok(self.snapshot_of(&state, FROZEN_TXN))
+    }
+
+    fn snapshot_of(&self, state: &TxnState, txn_id: TxnId) -> Snapshot {
+        Snapshot {
+            txn_id,
+            xmax: state.next_id,
+            active: Arc::new(state.active.keys().copied().collect()),
+            aborted: state.aborted.clone(),
+            statement: false,
+        }
+    }
+
+    /// Ends a transaction. Its changes become visible to snapshots taken from now on if it
+    /// committed; a rolled back transaction must have undone its changes first.
+    pub fn finish(&self, txn_id: TxnId, committed: bool) -> Result<()> {
+        let mut state = self.lock_state()?;
+        state.explicit.remove(&txn_id);
+        if state.logged.remove(&txn_id) {
+            append_log(&mut state.log, if committed { LOG_COMMIT } else { LOG_ROLLBACK }, txn_id)?;
+        }
+        state.active.remove(&txn_id);
+        Ok(())
+    }
+
+    /// Ends a rolled back transaction whose changes could not all be undone. It writes no
+    /// ROLLBACK, so its BEGIN stays unfinished in the log and its row versions count as
+    /// aborted now and after the next open.
+    pub fn abandon(&self, txn_id: TxnId) -> Result<()> {
+        let mut state = self.lock_state()?;
+        state.explicit.remove(&txn_id);
+        state.logged.remove(&txn_id);
+        Arc::make_mut(&mut state.aborted).insert(txn_id);
+        state.active.remove(&txn_id);
+        Ok(())
+    }
+
+    pub fn status(&self, txn_id: TxnId) -> Result<TxnStatus> {
+        if txn_id == FROZEN_TXN {
+            return Ok(TxnStatus::Committed);
+        }
+        if txn_id == INVALID_TXN {
+            return Ok(TxnStatus::Aborted);
+        }
+        let state = self.lock_state()?;
+        Ok(if state.aborted.contains(&txn_id) {
+            TxnStatus::Aborted
+        } else if state.active.contains_key(&txn_id) || txn_id >= state.next_id {
+            TxnStatus::InProgress
+        } else {
+            TxnStatus::Committed
+        })
+    }
+
+    /// Transactions below this id finished before any running transaction's snapshot was
+    /// taken, so a version they deleted is invisible to every reader now and later.
+    pub fn horizon(&self) -> Result<TxnId> {
+        let state = self.lock_state()?;
+        Ok(state.active.values().copied().min().unwrap_or(state.next_id))
+    }
+
+    /// Whether any transaction started with `begin` is still running.
+    pub fn has_open_transactions(&self) -> Result<bool> {
+        Ok(!self.lock_state()?.explicit.is_empty())
+    }
+
+    /// The transaction log of a copy of the database whose row versions were rewritten to
+    /// what `snapshot` sees: every id below the snapshot's committed, and none unfinished.
+    pub(crate) fn log_for_copy(snapshot: &Snapshot) -> Vec<u8> {
+        log_record(LOG_RESERVE, snapshot.xmax).to_vec()
+    }
+}
+
+fn log_record(kind: u8, txn_id: TxnId) -> [u8; LOG_RECORD_SIZE] {
+    let mut record = [0; LOG_RECORD_SIZE];
+    record[0] = kind;
+    record[1..].copy_from_slice(&txn_id.to_le_bytes());
+    record
+}
+
+fn append_log(log: &mut File, kind: u8, txn_id: TxnId) -> Result<()> {
+    DiskIo::write_all(log, &log_record(kind, txn_id))?;
+    log.sync_data()?;
+    Ok(())
+}
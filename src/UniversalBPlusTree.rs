@@ -1,355 +1,475 @@
 use std::sync::{Arc, RwLock};
 use crate::UniversalKey::{Key, data};
-use crate::Comparable::Comparable;
+use crate::KeyComparable::Comparable;
+use crate::ErrorTypes::{Error, Result};
 
+type NodeRef<T> = Arc<RwLock<Box<Node<T>>>>;
+
+/// A node of a B-link tree (Lehman and Yao). Besides the usual keys and children every node
+/// has a right link `next` to its sibling on the same level and a `high_key`: the smallest key
+/// that is no longer in this node. A node only ever hands its upper half to a new right
+/// sibling, so a thread that arrives after a split finds its key by following `next`.
 #[derive(Clone, Debug)]
 struct Node<T: Comparable> {
+    /// Entries in a leaf, separators in an internal node
     keys: Vec<Box<Key<T>>>,
-    count: usize,
-    size: usize,
-    pointers: Vec<Option<Arc<RwLock<Box<Node<T>>>>>>, 
-    next: Option<Arc<RwLock<Box<Node<T>>>>>, 
-    is_leaf: bool,
+    /// Children of an internal node, one more than there are separators
+    pointers: Vec<NodeRef<T>>,
+    next: Option<NodeRef<T>>,
+    /// Exclusive upper bound of the keys under this node; `None` on the rightmost node
+    high_key: Option<T>,
+    /// 0 for leaves, counting up towards the root
+    level: usize,
+}
+
+/// A node that split: the separator and new right sibling still to be added to its parent.
+struct Split<T: Comparable> {
+    left: NodeRef<T>,
+    separator: Box<Key<T>>,
+    right: NodeRef<T>,
 }
 
-const MAX_KEYS: usize = 3; 
+const MAX_KEYS: usize = 32;
 
+/// Generic B+Tree that many threads can insert into and search at once through `&self`.
+///
+/// Every node has its own lock and no operation holds more than one of them at a time:
+/// readers and writers descend with read locks, taken and released node by node, and a writer
+/// only locks the one node it changes. A split is finished bottom-up, one level at a time;
+/// until the separator reaches the parent, the right link makes the new node reachable.
 pub struct BPlusTree<T: Comparable> {
-    root: Option<Arc<RwLock<Box<Node<T>>>>>,
+    root: RwLock<NodeRef<T>>,
 }
 
 impl<T: Comparable + Send + Sync + 'static> BPlusTree<T> {
     pub fn new() -> BPlusTree<T> {
-        BPlusTree { root: None }
+        BPlusTree { root: RwLock::new(Self::new_node(0)) }
     }
 
-    pub fn insert(&mut self, node: Option<Box<Key<T>>>) {
-        if node.is_none() {
-            return;
+    /// Builds a tree from entries in strictly increasing key order. Rather than descending
+    /// and splitting once per key, the leaves are packed full from left to right and each
+    /// level of internal nodes is built over the one below, until one node is left to be
+    /// the root.
+    pub fn bulk_load<I>(entries: I) -> Result<BPlusTree<T>>
+    where
+        I: IntoIterator<Item = Key<T>>,
+    {
+        let entries: Vec<Box<Key<T>>> = entries.into_iter().map(Box::new).collect();
+        if let Some(pos) = entries.windows(2).position(|pair| !pair[0].key.is_less(&pair[1].key)) {
+            return Err(Error::InvalidOperation(format!(
+                "Bulk load keys {:?} and {:?} are not in increasing order", entries[pos].key, entries[pos + 1].key
+            )));
         }
-        let value = node.unwrap();
-        if self.root.is_none() {
-            let new_root = Box::new(Node {
-                keys: Vec::new(),
-                count: 0,
-                size: MAX_KEYS,
-                pointers: vec![None; MAX_KEYS + 1],
-                next: None,
-                is_leaf: true,
-            });
-            self.root = Some(Arc::new(RwLock::new(new_root)));
+        if entries.is_empty() {
+            return Ok(Self::new());
         }
 
-        let root_arc = self.root.as_ref().unwrap().clone();
-        if let Some((promoted_key, left_node, right_node)) = self._insert_rec(root_arc.clone(), value) {
-            let mut new_root = Box::new(Node {
-                keys: Vec::new(),
-                count: 0,
-                size: MAX_KEYS,
-                pointers: vec![None; MAX_KEYS + 1],
-                next: None,
-                is_leaf: false,
-            });
-
-            new_root.keys.push(promoted_key);
-            new_root.count = 1;
-            new_root.pointers[0] = Some(left_node);
-            new_root.pointers[1] = Some(right_node);
-
-            self.root = Some(Arc::new(RwLock::new(new_root)));
+        // Each node of the level being built, with the smallest key under it
+        let mut nodes: Vec<(T, NodeRef<T>)> = Self::pack(entries, MAX_KEYS)
+            .into_iter()
+            .map(|keys| {
+                let low = keys[0].key.clone();
+                let leaf = Self::new_node(0);
+                leaf.write().unwrap().keys = keys;
+                (low, leaf)
+            })
+            .collect();
+        Self::link_level(&nodes);
+
+        let mut level = 0;
+        while nodes.len() > 1 {
+            level += 1;
+            nodes = Self::pack(nodes, MAX_KEYS + 1)
+                .into_iter()
+                .map(|children| {
+                    let low = children[0].0.clone();
+                    let parent = Self::new_node(level);
+                    {
+                        let mut node = parent.write().unwrap();
+                        for (i, (child_low, child)) in children.into_iter().enumerate() {
+                            // A separator is the smallest key of the child to its right
+                            if i > 0 {
+                                node.keys.push(Box::new(Key::new(child_low, None)));
+                            }
+                            node.pointers.push(child);
+                        }
+                    }
+                    (low, parent)
+                })
+                .collect();
+            Self::link_level(&nodes);
         }
+        let (_, root) = nodes.pop().unwrap();
+        Ok(BPlusTree { root: RwLock::new(root) })
     }
 
-    fn _insert_rec(
-        &mut self,
-        current: Arc<RwLock<Box<Node<T>>>>,
-        value: Box<Key<T>>,
-    ) -> Option<(Box<Key<T>>, Arc<RwLock<Box<Node<T>>>>, Arc<RwLock<Box<Node<T>>>>)> {
-        let is_leaf = {
-            let node = current.read().unwrap();
-            node.is_leaf
-        };
+    /// Splits `items` into as few groups of at most `capacity` as there can be, their sizes
+    /// differing by one at most.
+    fn pack<E>(items: Vec<E>, capacity: usize) -> Vec<Vec<E>> {
+        let groups = items.len().div_ceil(capacity);
+        let (size, larger) = (items.len() / groups, items.len() % groups);
+        let mut items = items.into_iter();
+        (0..groups)
+            .map(|group| items.by_ref().take(size + usize::from(group < larger)).collect())
+            .collect()
+    }
 
-        if is_leaf {
-            self.add_new_element(&current, value);
+    /// Joins the nodes of one level, left to right, with right links and high keys.
+    fn link_level(nodes: &[(T, NodeRef<T>)]) {
+        for pair in nodes.windows(2) {
+            let mut node = pair[0].1.write().unwrap();
+            node.next = Some(pair[1].1.clone());
+            node.high_key = Some(pair[1].0.clone());
+        }
+    }
 
-            let count = {
-                let node = current.read().unwrap();
-                node.count
-            };
+    fn new_node(level: usize) -> NodeRef<T> {
+        Arc::new(RwLock::new(Box::new(Node {
+            keys: Vec::new(),
+            pointers: Vec::new(),
+            next: None,
+            high_key: None,
+            level,
+        })))
+    }
 
-            if count > MAX_KEYS {
-                return Some(self.split_leaf(&current));
-            } else {
-                return None;
+    /// Adds a key. A key that is already in the tree gets the new row location.
+    pub fn insert(&self, node: Option<Box<Key<T>>>) {
+        let value = match node {
+            Some(value) => value,
+            None => return,
+        };
+        let key = value.key.clone();
+        let mut parents = Vec::new();
+        let leaf = self.descend(&key, 0, &mut parents);
+
+        let mut split = Self::with_node_locked(leaf, &key, |current, node| {
+            match Self::position(&node.keys, &key) {
+                Ok(pos) => node.keys[pos] = value,
+                Err(pos) => node.keys.insert(pos, value),
             }
-        } else {
-            let pos = Self::_binary_search(&current, &value.key);
-            
-            let child_opt = {
-                let node = current.read().unwrap();
-                node.pointers.get(pos).and_then(|p| p.as_ref().cloned())
-            };
+            Self::split_if_full(current, node)
+        });
 
-            if child_opt.is_none() {
-                let new_child = Box::new(Node {
-                    keys: Vec::new(),
-                    count: 0,
-                    size: MAX_KEYS,
-                    pointers: vec![None; MAX_KEYS + 1],
-                    next: None,
-                    is_leaf: true,
-                });
-                let arc = Arc::new(RwLock::new(new_child));
-                {
-                    let mut node = current.write().unwrap();
-                    if pos < node.pointers.len() {
-                        node.pointers[pos] = Some(arc.clone());
-                    }
+        while let Some(Split { left, separator, right }) = split {
+            split = match parents.pop() {
+                Some(parent) => {
+                    let separator_key = separator.key.clone();
+                    Self::with_node_locked(parent, &separator_key, |current, node| {
+                        Self::add_child(node, separator, right);
+                        Self::split_if_full(current, node)
+                    })
                 }
-                if let Some((prom_key, left, right)) = self._insert_rec(arc, value) {
-                    self.insert_into_internal(&current, prom_key, left, right);
-                    let count = {
-                        let node = current.read().unwrap();
-                        node.count
-                    };
-                    if count > MAX_KEYS {
-                        return Some(self.split_internal(&current));
+                None => self.add_root_level(left, separator, right),
+            };
+        }
+    }
+
+    /// Finishes a split of a node that had no parent on the way down: either it was the root,
+    /// and a new root goes on top, or another thread has grown the tree since, and the parent
+    /// level is found from the new root.
+    fn add_root_level(&self, left: NodeRef<T>, separator: Box<Key<T>>, right: NodeRef<T>) -> Option<Split<T>> {
+        let level = left.read().unwrap().level;
+        loop {
+            {
+                let mut root = self.root.write().unwrap();
+                if Arc::ptr_eq(&root, &left) {
+                    let new_root = Self::new_node(level + 1);
+                    {
+                        let mut node = new_root.write().unwrap();
+                        node.keys.push(separator);
+                        node.pointers.push(left);
+                        node.pointers.push(right);
                     }
+                    *root = new_root;
+                    return None;
                 }
-            } else {
-                let child = child_opt.unwrap();
-                if let Some((prom_key, left, right)) = self._insert_rec(child, value) {
-                    self.insert_into_internal(&current, prom_key, left, right);
-                    let count = {
-                        let node = current.read().unwrap();
-                        node.count
-                    };
-                    if count > MAX_KEYS {
-                        return Some(self.split_internal(&current));
-                    }
+                if root.read().unwrap().level > level {
+                    break;
                 }
             }
-            return None;
+            // `left` is right of a root that has split but whose new root is not in place yet
+            std::thread::yield_now();
         }
-    }
-
-    fn insert_into_internal(
-        &mut self,
-        current: &Arc<RwLock<Box<Node<T>>>>,
-        promoted_key: Box<Key<T>>,
-        left: Arc<RwLock<Box<Node<T>>>>,
-        right: Arc<RwLock<Box<Node<T>>>>,
-    ) {
-        let pos = Self::_binary_search(current, &promoted_key.key);
-
-        {
-            let mut node = current.write().unwrap();
-            node.keys.insert(pos, promoted_key);
-            node.count += 1;
-
-            if node.pointers.len() < node.keys.len() + 1 {
-                let sz = node.keys.len();
-                node.pointers.resize(sz + 1, None);
-            }
 
-            node.pointers.insert(pos + 1, Some(right));
-            if pos < node.pointers.len() {
-                node.pointers[pos] = Some(left);
-            }
+        let separator_key = separator.key.clone();
+        let parent = self.descend(&separator_key, level + 1, &mut Vec::new());
+        Self::with_node_locked(parent, &separator_key, |current, node| {
+            Self::add_child(node, separator, right);
+            Self::split_if_full(current, node)
+        })
+    }
 
-            if node.pointers.len() > MAX_KEYS + 1 {
-                node.pointers.pop();
-            }
+    /// Walks down to the node on `level` whose key range holds `key`, recording the nodes it
+    /// came down through. No lock is held when it returns.
+    fn descend(&self, key: &T, level: usize, parents: &mut Vec<NodeRef<T>>) -> NodeRef<T> {
+        let mut current = self.root.read().unwrap().clone();
+        loop {
+            let next = {
+                let node = current.read().unwrap();
+                if let Some(right) = Self::move_right(&node, key) {
+                    right
+                } else if node.level <= level {
+                    break;
+                } else {
+                    let child = node.pointers[Self::child_index(&node.keys, key)].clone();
+                    parents.push(current.clone());
+                    child
+                }
+            };
+            current = next;
         }
+        current
     }
 
-    fn add_new_element(&mut self, current: &Arc<RwLock<Box<Node<T>>>>, value: Box<Key<T>>) {
-        let pos = BPlusTree::_binary_search(current, &value.key);
-        let mut node = current.write().unwrap();
-        node.keys.insert(pos, value);
-        node.count += 1;
+    /// Locks `current` for writing, moving right past any splits that happened since it was
+    /// found, and runs `change` on the node that holds `key`.
+    fn with_node_locked<R, F>(mut current: NodeRef<T>, key: &T, change: F) -> R
+    where
+        F: FnOnce(&NodeRef<T>, &mut Node<T>) -> R,
+    {
+        loop {
+            let right = {
+                let mut node = current.write().unwrap();
+                match Self::move_right(&node, key) {
+                    Some(right) => right,
+                    None => return change(&current, &mut node),
+                }
+            };
+            current = right;
+        }
     }
 
-    fn split_leaf(
-        &mut self,
-        current: &Arc<RwLock<Box<Node<T>>>>,
-    ) -> (Box<Key<T>>, Arc<RwLock<Box<Node<T>>>>, Arc<RwLock<Box<Node<T>>>>) {
-        let mut node = current.write().unwrap();
-        let total = node.keys.len();
-        let mid = (total + 1) / 2;
-
-        let right_keys = node.keys.split_off(mid);
-        let left_keys = node.keys.clone(); 
-        let left_count = left_keys.len();
-        let right_count = right_keys.len();
-
-        node.keys = left_keys;
-        node.count = left_count;
-        
-        let right_node = Box::new(Node {
-            keys: right_keys,
-            count: right_count,
-            size: MAX_KEYS,
-            pointers: vec![None; MAX_KEYS + 1],
-            next: node.next.clone(),
-            is_leaf: true,
-        });
-        let right_arc = Arc::new(RwLock::new(right_node));
+    /// The right sibling to follow when `key` is past the node's high key.
+    fn move_right(node: &Node<T>, key: &T) -> Option<NodeRef<T>> {
+        match &node.high_key {
+            Some(high_key) if !key.is_less(high_key) => node.next.clone(),
+            _ => None,
+        }
+    }
 
-        node.next = Some(right_arc.clone());
+    /// Inserts a separator and the child to its right. The child to its left, the node that
+    /// split, is already in this node.
+    fn add_child(node: &mut Node<T>, separator: Box<Key<T>>, right: NodeRef<T>) {
+        let pos = Self::child_index(&node.keys, &separator.key);
+        node.keys.insert(pos, separator);
+        node.pointers.insert(pos + 1, right);
+    }
 
-        let promoted_key = {
-            let right_node = right_arc.read().unwrap();
-            right_node.keys[0].clone()
+    /// Moves the upper half of an overfull node into a new right sibling.
+    fn split_if_full(current: &NodeRef<T>, node: &mut Node<T>) -> Option<Split<T>> {
+        if node.keys.len() <= MAX_KEYS {
+            return None;
+        }
+        let mid = node.keys.len() / 2;
+        let right_node = Self::new_node(node.level);
+        let separator = {
+            let mut right = right_node.write().unwrap();
+            let separator = if node.level == 0 {
+                right.keys = node.keys.split_off(mid);
+                right.keys[0].key.clone()
+            } else {
+                // The middle separator moves up; it is the first key of the right node's subtree
+                right.keys = node.keys.split_off(mid + 1);
+                right.pointers = node.pointers.split_off(mid + 1);
+                node.keys.pop().unwrap().key
+            };
+            right.next = node.next.take();
+            right.high_key = node.high_key.take();
+            Box::new(Key::new(separator, None))
         };
+        node.next = Some(right_node.clone());
+        node.high_key = Some(separator.key.clone());
 
-
-        let left_arc = current.clone();
-
-        drop(node);
-
-        (promoted_key, left_arc, right_arc)
+        Some(Split { left: current.clone(), separator, right: right_node })
     }
 
+    /// The child of an internal node that covers `key`: the number of separators not above it.
+    fn child_index(keys: &[Box<Key<T>>], key: &T) -> usize {
+        keys.partition_point(|separator| !key.is_less(&separator.key))
+    }
 
-    fn split_internal(
-        &mut self,
-        current: &Arc<RwLock<Box<Node<T>>>>,
-    ) -> (Box<Key<T>>, Arc<RwLock<Box<Node<T>>>>, Arc<RwLock<Box<Node<T>>>>) {
-        let mut node = current.write().unwrap();
-        let total = node.keys.len();
-        let mid_index = total / 2; 
-        let promoted_key = node.keys[mid_index].clone();
-
-
-        let left_keys = node.keys[..mid_index].to_vec();
-
-        let right_keys = node.keys[mid_index + 1..].to_vec();
+    fn position(keys: &[Box<Key<T>>], key: &T) -> std::result::Result<usize, usize> {
+        keys.binary_search_by(|entry| entry.key.compare(key))
+    }
 
-        let mut all_ptrs = node.pointers.clone();
-        if all_ptrs.len() < total + 1 {
-            all_ptrs.resize(total + 1, None);
+    pub fn search(&self, key: &T) -> Option<Box<data>> {
+        let mut current = self.descend(key, 0, &mut Vec::new());
+        loop {
+            let right = {
+                let node = current.read().unwrap();
+                match Self::move_right(&node, key) {
+                    Some(right) => right,
+                    None => {
+                        return Self::position(&node.keys, key).ok()
+                            .and_then(|pos| node.keys[pos].data.clone());
+                    }
+                }
+            };
+            current = right;
         }
+    }
 
-        let mut left_ptrs: Vec<Option<Arc<RwLock<Box<Node<T>>>>>> = Vec::new();
-        let mut right_ptrs: Vec<Option<Arc<RwLock<Box<Node<T>>>>>> = Vec::new();
+    /// Takes a key out of its leaf. Returns false if it was not there. Nodes are not merged
+    /// afterwards, as in Lehman and Yao's tree: separators and high keys stay valid bounds,
+    /// so concurrent searches and inserts are unaffected by emptier leaves.
+    pub fn remove(&self, key: &T) -> bool {
+        let leaf = self.descend(key, 0, &mut Vec::new());
+        Self::with_node_locked(leaf, key, |_, node| match Self::position(&node.keys, key) {
+            Ok(pos) => {
+                node.keys.remove(pos);
+                true
+            }
+            Err(_) => false,
+        })
+    }
 
-        for i in 0..=mid_index {
-            if i < all_ptrs.len() {
-                left_ptrs.push(all_ptrs[i].as_ref().cloned());
+    /// Every entry with a key from `low` to `high`, both included, in key order, read along
+    /// the right links from the leaf that would hold `low`.
+    pub fn range(&self, low: &T, high: &T) -> Vec<Key<T>> {
+        let mut entries = Vec::new();
+        let mut current = Some(self.descend(low, 0, &mut Vec::new()));
+        while let Some(leaf) = current {
+            let node = leaf.read().unwrap();
+            for entry in &node.keys {
+                if entry.key.is_greater(high) {
+                    return entries;
+                }
+                if !entry.key.is_less(low) {
+                    entries.push((**entry).clone());
+                }
             }
+            current = node.next.clone();
         }
-        for i in (mid_index + 1)..all_ptrs.len() {
-            right_ptrs.push(all_ptrs[i].as_ref().cloned());
-        }
-
-        let left_node = Box::new(Node {
-            keys: left_keys,
-            count: left_ptrs.iter().filter(|p| p.is_some()).count(),
-            size: MAX_KEYS,
-            pointers: {
-                let mut v = left_ptrs;
-                v.resize(MAX_KEYS + 1, None);
-                v
-            },
-            next: None,
-            is_leaf: false,
-        });
-        let right_node = Box::new(Node {
-            keys: right_keys,
-            count: {
-                let rk_len = node.keys.len() - (mid_index + 1);
-                rk_len
-            },
-            size: MAX_KEYS,
-            pointers: {
-                let mut v = right_ptrs;
-                v.resize(MAX_KEYS + 1, None);
-                v
-            },
-            next: None,
-            is_leaf: false,
-        });
-
-        let left_arc = Arc::new(RwLock::new(left_node));
-        let right_arc = Arc::new(RwLock::new(right_node));
-
-        drop(node);
+        entries
+    }
 
-        (promoted_key, left_arc, right_arc)
+    /// The leftmost leaf, where the leaf level's right links start.
+    fn first_leaf(&self) -> NodeRef<T> {
+        let mut current = self.root.read().unwrap().clone();
+        loop {
+            let child = {
+                let node = current.read().unwrap();
+                if node.level == 0 {
+                    return current.clone();
+                }
+                node.pointers[0].clone()
+            };
+            current = child;
+        }
     }
 
-    pub fn _binary_search(current: &Arc<RwLock<Box<Node<T>>>>, target: &T) -> usize {
-        let node = current.read().unwrap();
-        let mut low: usize = 0;
-        let mut high: usize = node.count;
-        while low < high {
-            let mid = (low + high) / 2;
-            if node.keys[mid].key.is_equal(target) {
-                return mid;
-            } else if node.keys[mid].key.is_less(target) {
-                low = mid + 1;
-            } else {
-                high = mid;
+    /// Whether no leaf holds an entry. Leaves emptied by `remove` are passed over along the
+    /// right links, so this stops at the first entry rather than reading every leaf.
+    pub fn is_empty(&self) -> bool {
+        let mut current = Some(self.first_leaf());
+        while let Some(leaf) = current {
+            let node = leaf.read().unwrap();
+            if !node.keys.is_empty() {
+                return false;
             }
+            current = node.next.clone();
         }
-        low
+        true
     }
 
-    pub fn search(&self, key: &T) -> Option<Box<data>> {
-        if self.root.is_none() {
-            return None;
+    /// Every entry in key order, read leaf by leaf along the right links.
+    pub fn entries(&self) -> Vec<Key<T>> {
+        let mut current = self.first_leaf();
+        let mut entries = Vec::new();
+        loop {
+            let next = {
+                let node = current.read().unwrap();
+                entries.extend(node.keys.iter().map(|entry| (**entry).clone()));
+                node.next.clone()
+            };
+            match next {
+                Some(next) => current = next,
+                None => return entries,
+            }
         }
-        let current = self.root.as_ref().unwrap().clone();
-        self.search_rec(current, key)
     }
 
-    fn search_rec(&self, current: Arc<RwLock<Box<Node<T>>>>, key: &T) -> Option<Box<data>> {
-        let pos = BPlusTree::_binary_search(&current, key);
-        let node = current.read().unwrap();
-        if node.is_leaf {
-            if pos < node.keys.len() && node.keys[pos].key.is_equal(key) {
-                return node.keys[pos].data.clone();
-            } else {
-                return None;
-            }
-        } else {
-            if pos < node.pointers.len() {
-                if let Some(ref child) = node.pointers[pos] {
-                    let child_clone = child.clone();
-                    drop(node); 
-                    return self.search_rec(child_clone, key);
-                } else {
-                    return None;
+    /// Verifies the structure of a tree no other thread is changing: no node holds more than
+    /// `MAX_KEYS` keys, keys increase within a node and fall in the range the separators above
+    /// give their subtree, each node's high key is the upper end of that range, an internal
+    /// node has one child more than separators on the level below it, and the right links
+    /// join every level left to right. Needs the `index-checks` feature.
+    #[cfg(any(test, feature = "index-checks"))]
+    pub fn check_invariants(&self) -> Result<()> {
+        let root = self.root.read().unwrap().clone();
+        let levels = root.read().unwrap().level + 1;
+        let mut nodes_by_level: Vec<Vec<NodeRef<T>>> = vec![Vec::new(); levels];
+        Self::check_rec(&root, None, None, &mut nodes_by_level)?;
+
+        for (level, nodes) in nodes_by_level.iter().enumerate() {
+            for (i, current) in nodes.iter().enumerate() {
+                let next = current.read().unwrap().next.clone();
+                let linked = match (&next, nodes.get(i + 1)) {
+                    (Some(next), Some(expected)) => Arc::ptr_eq(next, expected),
+                    (None, None) => true,
+                    _ => false,
+                };
+                if !linked {
+                    return Err(Error::corruption(format!(
+                        "Node {} of {} on level {} is not linked to the node after it", i, nodes.len(), level
+                    )));
                 }
-            } else {
-                return None;
             }
         }
+        Ok(())
     }
 
-    pub fn print_tree(&self) {
-        if let Some(ref root) = self.root {
-            Self::print_rec(root, 0);
+    #[cfg(any(test, feature = "index-checks"))]
+    fn check_rec(current: &NodeRef<T>, low: Option<&T>, high: Option<&T>, nodes_by_level: &mut Vec<Vec<NodeRef<T>>>) -> Result<()> {
+        let node = current.read().unwrap();
+        let keys: Vec<&T> = node.keys.iter().map(|entry| &entry.key).collect();
+        let broken = |what: &str| Err(Error::corruption(format!("Node {:?} on level {}: {}", keys, node.level, what)));
+        if keys.len() > MAX_KEYS {
+            return broken("too many keys");
         }
-    }
+        if keys.windows(2).any(|pair| !pair[0].is_less(pair[1])) {
+            return broken("keys out of order");
+        }
+        // A separator is the smallest key its right subtree may hold
+        if keys.iter().any(|key| low.is_some_and(|low| key.is_less(low)) || high.is_some_and(|high| !key.is_less(high))) {
+            return broken("key outside the separators around the node");
+        }
+        let high_key_matches = match (&node.high_key, high) {
+            (Some(high_key), Some(high)) => high_key.is_equal(high),
+            (None, None) => true,
+            _ => false,
+        };
+        if !high_key_matches {
+            return broken("high key is not the separator after the node");
+        }
+        nodes_by_level[node.level].push(current.clone());
 
-    fn print_rec(current: &Arc<RwLock<Box<Node<T>>>>, level: i32) {
-        let node = current.read().unwrap();
-        println!(" level {} Node with {} keys", level, node.keys.len());
-        let pointers = node.pointers.clone();
-        drop(node); 
-        
-        for pointer in pointers.iter() {
-            if let Some( child) = pointer {
-                Self::print_rec(child, level + 1);
+        if node.level == 0 {
+            return match node.pointers.is_empty() {
+                true => Ok(()),
+                false => broken("leaf with children"),
+            };
+        }
+        if node.pointers.len() != keys.len() + 1 {
+            return broken("internal node without one child more than separators");
+        }
+        for (i, child) in node.pointers.iter().enumerate() {
+            if child.read().unwrap().level + 1 != node.level {
+                return broken("child not on the level below");
             }
+            let child_low = if i == 0 { low } else { Some(keys[i - 1]) };
+            let child_high = if i == keys.len() { high } else { Some(keys[i]) };
+            Self::check_rec(child, child_low, child_high, nodes_by_level)?;
         }
+        Ok(())
+    }
+}
+
+impl<T: Comparable + Send + Sync + 'static> Default for BPlusTree<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 pub type IntBPlusTree = BPlusTree<i32>;
-pub type StringBPlusTree = BPlusTree<String>;
-pub type BigIntBPlusTree = BPlusTree<i64>;
-pub type DoubleBPlusTree = BPlusTree<f64>;
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::Comparable::Comparable;
+use crate::KeyComparable::Comparable;
 
 #[derive(Clone, Debug)]
 pub struct data {
@@ -51,8 +51,3 @@ impl<T: Comparable> Key<T> {
         self.key.is_less_equal(&other.key)
     }
 }
-
-pub type IntKey = Key<i32>;
-pub type StringKey = Key<String>;
-pub type BigIntKey = Key<i64>;
-pub type DoubleKey = Key<f64>;
\ No newline at end of file
@@ -0,0 +1,266 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_DAY: i64 = 86_400 * MICROS_PER_SECOND;
+
+/// Largest precision a DECIMAL can have while its unscaled value still fits in an i128
+pub const MAX_DECIMAL_PRECISION: u8 = 38;
+
+/// Years a DATE or TIMESTAMP can be parsed or built from, 0001 through 9999 as in standard
+/// SQL. Every timestamp in them fits in an i64 of microseconds.
+const MIN_YEAR: i64 = 1;
+const MAX_YEAR: i64 = 9999;
+
+/// Calendar date stored as days since 1970-01-01, for years 0001 through 9999.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date(pub i32);
+
+/// UTC instant stored as microseconds since 1970-01-01 00:00:00, for years 0001 through 9999.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(pub i64);
+
+/// Fixed-point number: `value * 10^-scale`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decimal {
+    pub value: i128,
+    pub scale: u8,
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        _ => 28,
+    }
+}
+
+// Howard Hinnant's days_from_civil / civil_from_days
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str, what: &str) -> Result<T, String> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Invalid {}: '{}'", what, text));
+    }
+    text.parse().map_err(|_| format!("Invalid {}: '{}'", what, text))
+}
+
+impl Date {
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> Result<Date, String> {
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+            return Err(format!("Date out of range: {:04}-{:02}-{:02}, years run from {:04} to {}", year, month, day, MIN_YEAR, MAX_YEAR));
+        }
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(format!("Invalid date: {:04}-{:02}-{:02}", year, month, day));
+        }
+        Ok(Date(days_from_civil(year, month, day) as i32))
+    }
+
+    /// Parses `YYYY-MM-DD`.
+    pub fn parse(text: &str) -> Result<Date, String> {
+        let parts: Vec<&str> = text.trim().split('-').collect();
+        if parts.len() != 3 {
+            return Err(format!("Invalid date '{}', expected YYYY-MM-DD", text));
+        }
+        let year = parse_number(parts[0], "year")?;
+        let month = parse_number(parts[1], "month")?;
+        let day = parse_number(parts[2], "day")?;
+        Date::from_ymd(year, month, day)
+    }
+
+    pub fn to_ymd(&self) -> (i64, u32, u32) {
+        civil_from_days(self.0 as i64)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = self.to_ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl Timestamp {
+    /// Parses `YYYY-MM-DD[ |T]HH:MM:SS[.ffffff][Z]`; a bare date means midnight UTC.
+    pub fn parse(text: &str) -> Result<Timestamp, String> {
+        let text = text.trim();
+        let text = text.strip_suffix('Z').unwrap_or(text);
+        let (date_part, time_part) = match text.find([' ', 'T']) {
+            Some(pos) => (&text[..pos], &text[pos + 1..]),
+            None => (text, "00:00:00"),
+        };
+        let date = Date::parse(date_part)?;
+
+        let (clock, fraction) = match time_part.split_once('.') {
+            Some((clock, fraction)) => (clock, fraction),
+            None => (time_part, ""),
+        };
+        let fields: Vec<&str> = clock.split(':').collect();
+        if fields.len() != 3 {
+            return Err(format!("Invalid timestamp '{}', expected HH:MM:SS", text));
+        }
+        let hour: i64 = parse_number(fields[0], "hour")?;
+        let minute: i64 = parse_number(fields[1], "minute")?;
+        let second: i64 = parse_number(fields[2], "second")?;
+        if hour > 23 || minute > 59 || second > 59 {
+            return Err(format!("Invalid time of day in '{}'", text));
+        }
+        if fraction.len() > 6 {
+            return Err(format!("Timestamp '{}' is more precise than microseconds", text));
+        }
+        let micros: i64 = if fraction.is_empty() {
+            0
+        } else {
+            parse_number::<i64>(fraction, "fraction")? * 10i64.pow(6 - fraction.len() as u32)
+        };
+
+        let seconds = hour * 3600 + minute * 60 + second;
+        (date.0 as i64).checked_mul(MICROS_PER_DAY)
+            .and_then(|day| day.checked_add(seconds * MICROS_PER_SECOND + micros))
+            .map(Timestamp)
+            .ok_or_else(|| format!("Timestamp out of range: '{}'", text))
+    }
+
+    pub fn date(&self) -> Date {
+        Date(self.0.div_euclid(MICROS_PER_DAY) as i32)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time_of_day = self.0.rem_euclid(MICROS_PER_DAY);
+        let seconds = time_of_day / MICROS_PER_SECOND;
+        let micros = time_of_day % MICROS_PER_SECOND;
+        write!(f, "{} {:02}:{:02}:{:02}", self.date(), seconds / 3600, seconds / 60 % 60, seconds % 60)?;
+        if micros != 0 {
+            write!(f, ".{:06}", micros)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decimal {
+    pub fn new(value: i128, scale: u8) -> Decimal {
+        Decimal { value, scale }
+    }
+
+    /// Parses `[-]digits[.digits]` and rounds it to `scale` fractional digits.
+    pub fn parse(text: &str, scale: u8) -> Result<Decimal, String> {
+        let text = text.trim();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+        if (int_part.is_empty() && frac_part.is_empty())
+            || !int_part.bytes().chain(frac_part.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return Err(format!("Invalid decimal: '{}'", text));
+        }
+        if frac_part.len() > MAX_DECIMAL_PRECISION as usize {
+            return Err(format!("Too many fractional digits in decimal: '{}'", text));
+        }
+
+        let mut value: i128 = 0;
+        for b in int_part.bytes().chain(frac_part.bytes()) {
+            value = value.checked_mul(10)
+                .and_then(|v| v.checked_add((b - b'0') as i128))
+                .ok_or_else(|| format!("Decimal out of range: '{}'", text))?;
+        }
+        if negative {
+            value = -value;
+        }
+        Decimal::new(value, frac_part.len() as u8)
+            .rescale(scale)
+            .ok_or_else(|| format!("Decimal out of range: '{}'", text))
+    }
+
+    /// Changes the number of fractional digits, rounding half away from zero when reducing it.
+    pub fn rescale(&self, scale: u8) -> Option<Decimal> {
+        if scale >= self.scale {
+            let factor = 10i128.checked_pow((scale - self.scale) as u32)?;
+            return Some(Decimal::new(self.value.checked_mul(factor)?, scale));
+        }
+        let factor = 10i128.checked_pow((self.scale - scale) as u32)?;
+        let quotient = self.value / factor;
+        let remainder = self.value % factor;
+        let rounded = if remainder.abs() * 2 >= factor {
+            quotient + self.value.signum()
+        } else {
+            quotient
+        };
+        Some(Decimal::new(rounded, scale))
+    }
+
+    /// Number of significant digits in the unscaled value.
+    pub fn digits(&self) -> u32 {
+        let mut magnitude = self.value.unsigned_abs();
+        let mut digits = 1;
+        while magnitude >= 10 {
+            magnitude /= 10;
+            digits += 1;
+        }
+        digits
+    }
+
+    pub fn fits_precision(&self, precision: u8) -> bool {
+        self.digits() <= precision as u32
+    }
+
+    pub fn compare(&self, other: &Decimal) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        match (self.rescale(scale), other.rescale(scale)) {
+            (Some(a), Some(b)) => a.value.cmp(&b.value),
+            _ => self.to_f64().partial_cmp(&other.to_f64()).unwrap_or(Ordering::Equal),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.value as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.value < 0 { "-" } else { "" };
+        let magnitude = self.value.unsigned_abs();
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, magnitude);
+        }
+        let factor = 10u128.pow(self.scale as u32);
+        write!(f, "{}{}.{:0width$}", sign, magnitude / factor, magnitude % factor, width = self.scale as usize)
+    }
+}
+
+/// Location of a BLOB value spilled to the table's overflow file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlobRef {
+    pub first_page: u64,
+    pub len: u64,
+}
@@ -0,0 +1,81 @@
+//! OxideDB is a small embedded database engine: tables with typed columns, B+Tree primary
+//! key indexes and a page-based storage format, all kept in one directory.
+//!
+//! ```no_run
+//! use oxidedb::{Column, Database, DataType, Options, Value};
+//!
+//! let db = Database::open("./data", Options::default())?;
+//! db.create_table("users", vec![
+//!     Column::new("id".to_string(), DataType::INTEGER, true),
+//!     Column::new("name".to_string(), DataType::STRING(100), false),
+//! ])?;
+//!
+//! let users = db.table("users")?;
+//! users.insert(vec![Value::INTEGER(1), Value::STRING("Ada".to_string(), 100)])?;
+//! if let Some(row) = users.get(1)? {
+//!     println!("{}", row["name"]);
+//! }
+//!
+//! for row in db.query("users").eq("name", Value::STRING("Ada".to_string(), 100)).run()? {
+//!     println!("{}", row);
+//! }
+//! db.save()?;
+//! # Ok::<(), oxidedb::Error>(())
+//! ```
+//!
+//! The public API is the set of items re-exported here; the storage modules behind them
+//! are internal.
+
+// Module and type names follow the project's own naming scheme rather than rustc's defaults.
+#![allow(non_snake_case, non_camel_case_types, non_upper_case_globals, clippy::upper_case_acronyms)]
+
+mod MetaEnum;
+mod RowData;
+mod FileWriter;
+mod TableMetaHandler;
+mod TableCreationHandler;
+mod TableQueryHandler;
+mod KeyComparable;
+mod UniversalKey;
+mod UniversalBPlusTree;
+mod BTreePersistence;
+mod QueryPredicate;
+mod ValueTypes;
+mod BlobStore;
+mod DatabaseHandler;
+mod ErrorTypes;
+mod TableHandle;
+mod TransactionManager;
+mod TransactionHandle;
+mod LockManager;
+mod DiskIo;
+mod CsvCopy;
+mod JsonLines;
+mod SqlDump;
+mod Backup;
+
+pub use crate::DatabaseHandler::{Database, Options};
+pub use crate::TransactionHandle::Transaction;
+pub use crate::ErrorTypes::{Error, Result};
+pub use crate::TableHandle::{Table, Row, Query};
+pub use crate::MetaEnum::{DataArray as Value, MetaEnum as DataType};
+pub use crate::TableCreationHandler::TableColumn as Column;
+pub use crate::QueryPredicate::Predicate;
+pub use crate::TableQueryHandler::StringOverflowPolicy;
+pub use crate::ValueTypes::{Date, Timestamp, Decimal};
+pub use crate::BlobStore::BlobReader;
+/// Simulated crashes at file write points, for testing recovery. Needs the
+/// `fault-injection` feature.
+#[cfg(any(test, feature = "fault-injection"))]
+pub use crate::DiskIo::FaultInjector;
+pub use crate::CsvCopy::CsvOptions;
+/// The generic, thread-safe B+Tree behind the primary key indexes, for applications that
+/// want a concurrent ordered map of keys to row locations.
+pub use crate::UniversalBPlusTree::BPlusTree as IndexTree;
+pub use crate::UniversalKey::{Key as IndexKey, data as RowLocation};
+pub use crate::KeyComparable::Comparable;
+
+/// Size of a data page in bytes
+pub const PAGE_SIZE: usize = 4096;
+/// Bytes at the start of each page reserved for the page header
+pub const HEADER_SIZE: usize = 64;
@@ -1,33 +1,23 @@
-mod BTree;
-mod MetaEnum;
-mod LruDict;
-mod RowData;
-mod FileWriter;
-mod TableMetaHandler;
-mod TableCreationHandler;
-mod TableQueryHandler;
-mod BPlusTree;
-mod Comparable;
-mod UniversalKey;
-mod UniversalBPlusTree;
-mod TableBTreeManager;
-mod BTreePersistence;
+// Module and type names follow the project's own naming scheme rather than rustc's defaults.
+#![allow(non_snake_case, non_camel_case_types, non_upper_case_globals, clippy::upper_case_acronyms)]
 
-use MetaEnum::{MetaEnum as Type, DataArray};
-use TableCreationHandler::{TableCreationHandler as TCH, TableColumn};
-use TableQueryHandler::TableQueryHandler as TQH;
-use TableBTreeManager::{initialize_btree_manager, register_table, TableKey};
-use crate::TableMetaHandler::meta_config;
-
-const PAGE_SIZE: usize = 4096;
-const HEADER_SIZE: usize = 64;
+use oxidedb::{Column as TableColumn, Database as DB, DataType as Type, Options, Predicate as Filter, Value as DataArray};
 
 // Configuration flags
 const CREATE_TABLES_ON_STARTUP: bool = true;
 const LOAD_EXISTING_BTREES: bool = false;
-const ENABLE_BTREE_TESTING: bool = true;
 
 fn main() {
+    // Statements after the directory (COPY, .dump <file>, .restore <file>) are run instead of the demo
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 2 {
+        if let Err(e) = run_statements(&args[1], &args[2..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     println!("=== RustDB - Database System Demo ===\n");
     
     if let Err(e) = run_database_demo() {
@@ -39,71 +29,80 @@ fn main() {
 }
 
 fn run_database_demo() -> Result<(), String> {
-    // Step 1: Initialize core systems
-    initialize_systems()?;
+    // Step 1: Open the database (directory from the first argument, default: current directory)
+    let path = std::env::args().nth(1).unwrap_or_else(|| ".".to_string());
+    let db = open_database(&path)?;
     
     // Step 2: Setup tables (conditional)
     if CREATE_TABLES_ON_STARTUP {
-        setup_demo_tables()?;
+        setup_demo_tables(&db)?;
     }
     
-    // Step 3: Initialize query handler
-    let mut query_handler = TQH::new();
-    
-    // Step 4: Load existing data (conditional)
+    // Step 3: Show existing data (conditional)
     if LOAD_EXISTING_BTREES {
-        load_existing_data(&mut query_handler)?;
+        load_existing_data(&db)?;
     }
     
-    // Step 5: Run demo operations
-    run_demo_operations(&mut query_handler)?;
+    // Step 4: Run demo operations
+    run_demo_operations(&db)?;
     
-    // Step 6: Cleanup and save
-    cleanup_and_save(&query_handler)?;
+    // Step 5: Cleanup and save
+    cleanup_and_save(&db)?;
     
     Ok(())
 }
 
-fn initialize_systems() -> Result<(), String> {
-    println!("Initializing database systems...");
-    
-    // Initialize configuration
-    initialize_config().map_err(|e| format!("Config initialization failed: {}", e))?;
-    
-    // Initialize B+Tree manager
-    initialize_btree_manager();
-    
-    println!("Systems initialized successfully\n");
-    Ok(())
+fn run_statements(path: &str, statements: &[String]) -> Result<(), String> {
+    let db = DB::open(path, Options::default())
+        .map_err(|e| format!("Database initialization failed: {}", e))?;
+    for statement in statements {
+        // Dot commands take a file; the database logs its progress on stdout
+        let (command, file) = statement.split_once(' ').unwrap_or((statement.as_str(), ""));
+        match command {
+            ".dump" | ".restore" if file.trim().is_empty() => return Err(format!("Usage: {} <file>", command)),
+            ".dump" => {
+                let out = std::fs::File::create(file.trim()).map_err(|e| format!("Failed to create '{}': {}", file.trim(), e))?;
+                let rows = db.dump(std::io::BufWriter::new(out)).map_err(|e| e.to_string())?;
+                println!("Dumped {} rows to '{}'", rows, file.trim());
+            }
+            ".restore" => {
+                let rows = db.restore_dump(file.trim()).map_err(|e| e.to_string())?;
+                println!("Restored {} rows from '{}'", rows, file.trim());
+            }
+            _ => {
+                let rows = db.execute_copy(statement).map_err(|e| e.to_string())?;
+                println!("COPY {}", rows);
+            }
+        }
+    }
+    db.save().map_err(|e| format!("Failed to save: {}", e))
 }
 
-fn initialize_config() -> Result<(), String> {
-    let mut config_lock = meta_config.lock()
-        .map_err(|_| "Failed to acquire config lock")?;
-    
-    if config_lock.is_none() {
-        let mut new_config = TableMetaHandler::TableMetaHandler::new("meta_config.db".to_string());
-        new_config.load_meta_file()
-            .map_err(|e| format!("Failed to load meta file: {}", e))?;
-        *config_lock = Some(new_config);
-    }
-    
-    Ok(())
+fn open_database(path: &str) -> Result<DB, String> {
+    println!("Opening database in '{}'...", path);
+    
+    let options = Options {
+        load_indexes: LOAD_EXISTING_BTREES,
+        ..Options::default()
+    };
+    let db = DB::open(path, options)
+        .map_err(|e| format!("Database initialization failed: {}", e))?;
+    
+    println!("Database opened successfully\n");
+    Ok(db)
 }
 
-fn setup_demo_tables() -> Result<(), String> {
+fn setup_demo_tables(db: &DB) -> Result<(), String> {
     println!("Creating demo tables...");
     
-    let mut handler = TCH::new();
-    
-    create_users_table(&mut handler)?;
-    create_products_table(&mut handler)?;
+    create_users_table(db)?;
+    create_products_table(db)?;
     
     println!("Demo tables created successfully\n");
     Ok(())
 }
 
-fn create_users_table(handler: &mut TCH) -> Result<(), String> {
+fn create_users_table(db: &DB) -> Result<(), String> {
     let user_columns = vec![
         TableColumn::new("id".to_string(), Type::INTEGER, true),
         TableColumn::new("name".to_string(), Type::STRING(100), false),
@@ -112,17 +111,16 @@ fn create_users_table(handler: &mut TCH) -> Result<(), String> {
         TableColumn::new("salary".to_string(), Type::DOUBLE, false),
     ];
     
-    match handler.create_table_with_validation("users".to_string(), user_columns) {
+    match db.create_table("users", user_columns) {
         Ok(table_id) => {
             println!("Created 'users' table (ID: {})", table_id);
-            register_table(table_id, Type::INTEGER)?;
             Ok(())
         },
         Err(e) => Err(format!("Failed to create users table: {}", e))
     }
 }
 
-fn create_products_table(handler: &mut TCH) -> Result<(), String> {
+fn create_products_table(db: &DB) -> Result<(), String> {
     let product_columns = vec![
         TableColumn::new("product_id".to_string(), Type::INTEGER, true),
         TableColumn::new("product_name".to_string(), Type::STRING(200), false),
@@ -130,129 +128,117 @@ fn create_products_table(handler: &mut TCH) -> Result<(), String> {
         TableColumn::new("stock".to_string(), Type::INTEGER, false),
     ];
     
-    match handler.create_table_with_validation("products".to_string(), product_columns) {
+    match db.create_table("products", product_columns) {
         Ok(table_id) => {
             println!("Created 'products' table (ID: {})", table_id);
-            register_table(table_id, Type::INTEGER)?;
             Ok(())
         },
         Err(e) => Err(format!("Failed to create products table: {}", e))
     }
 }
 
-fn load_existing_data(query_handler: &mut TQH) -> Result<(), String> {
+fn load_existing_data(db: &DB) -> Result<(), String> {
     println!("Loading existing tables and B+Trees...");
     
-    // Uncomment when load_existing_btrees is implemented
-    // query_handler.load_existing_btrees();
-    
-    let tables = query_handler.get_available_tables();
+    let tables = db.loaded_tables();
     println!("  Loaded tables: {:?}\n", tables);
     
     Ok(())
 }
 
-fn run_demo_operations(query_handler: &mut TQH) -> Result<(), String> {
+fn run_demo_operations(db: &DB) -> Result<(), String> {
     println!("Running demo operations...\n");
     
     // Insert sample data
-    insert_sample_data(query_handler)?;
+    insert_sample_data(db)?;
     
     // Query and display data
-    query_sample_data(query_handler)?;
-    
-    // Test B+Tree functionality (if enabled)
-    if ENABLE_BTREE_TESTING {
-        test_btree_operations(query_handler)?;
-    }
+    query_sample_data(db)?;
     
     Ok(())
 }
 
-fn insert_sample_data(query_handler: &mut TQH) -> Result<(), String> {
+fn insert_sample_data(db: &DB) -> Result<(), String> {
     println!("Inserting sample data...");
     
-    insert_users_data(query_handler)?;
-    insert_products_data(query_handler)?;
+    insert_users_data(db)?;
+    insert_products_data(db)?;
     
     println!("Sample data inserted successfully\n");
     Ok(())
 }
 
-fn insert_users_data(query_handler: &mut TQH) -> Result<(), String> {
+fn insert_users_data(db: &DB) -> Result<(), String> {
     let users = get_sample_users();
     
     println!("Inserting users:");
     for (id, user_data) in users {
-        insert_record(query_handler, "users", id, user_data)?;
+        insert_record(db, "users", id, user_data)?;
     }
     
     Ok(())
 }
 
-fn insert_products_data(query_handler: &mut TQH) -> Result<(), String> {
+fn insert_products_data(db: &DB) -> Result<(), String> {
     let products = get_sample_products();
     
     println!("Inserting products:");
     for (id, product_data) in products {
-        insert_record(query_handler, "products", id, product_data)?;
+        insert_record(db, "products", id, product_data)?;
     }
     
     Ok(())
 }
 
 fn insert_record(
-    query_handler: &mut TQH, 
+    db: &DB, 
     table_name: &str, 
     id: i32, 
     data: Vec<DataArray>
 ) -> Result<(), String> {
-    match query_handler.create_row(table_name, data) {
-        Ok(row) => {
-            match query_handler.insert(table_name.to_string(), id, row) {
-                Ok(_) => {
-                    println!("Inserted {} {}", table_name.trim_end_matches('s'), id);
-                    Ok(())
-                },
-                Err(e) => Err(format!("Failed to insert {} {}: {}", table_name, id, e))
-            }
+    match db.insert(table_name, id, data) {
+        Ok(_) => {
+            println!("Inserted {} {}", table_name.trim_end_matches('s'), id);
+            Ok(())
         },
-        Err(e) => Err(format!("Failed to create row for {} {}: {}", table_name, id, e))
+        Err(e) => Err(format!("Failed to insert {} {}: {}", table_name, id, e))
     }
 }
 
-fn query_sample_data(query_handler: &TQH) -> Result<(), String> {
+fn query_sample_data(db: &DB) -> Result<(), String> {
     println!("🔍 Querying sample data...");
     
-    query_users_data(query_handler)?;
-    query_products_data(query_handler)?;
-    test_non_existent_records(query_handler)?;
+    query_users_data(db)?;
+    query_products_data(db)?;
+    test_non_existent_records(db)?;
+    query_with_predicate(db)?;
+    list_catalog_tables(db)?;
     
     // Show available tables
-    let tables = query_handler.get_available_tables();
+    let tables = db.loaded_tables();
     println!("  Available tables: {:?}\n", tables);
     
     Ok(())
 }
 
-fn query_users_data(query_handler: &TQH) -> Result<(), String> {
+fn query_users_data(db: &DB) -> Result<(), String> {
     println!("  Users:");
     for id in 1..=3 {
-        query_record(query_handler, "users", id)?;
+        query_record(db, "users", id)?;
     }
     Ok(())
 }
 
-fn query_products_data(query_handler: &TQH) -> Result<(), String> {
+fn query_products_data(db: &DB) -> Result<(), String> {
     println!("  Products:");
     for id in [101, 102, 103] {
-        query_record(query_handler, "products", id)?;
+        query_record(db, "products", id)?;
     }
     Ok(())
 }
 
-fn query_record(query_handler: &TQH, table_name: &str, id: i32) -> Result<(), String> {
-    match query_handler.select(table_name.to_string(), id) {
+fn query_record(db: &DB, table_name: &str, id: i32) -> Result<(), String> {
+    match db.select(table_name, id) {
         Ok(Some(data)) => {
             println!("    {} {}: {}", 
                 table_name.trim_end_matches('s').to_uppercase(), id, data);
@@ -266,9 +252,9 @@ fn query_record(query_handler: &TQH, table_name: &str, id: i32) -> Result<(), St
     }
 }
 
-fn test_non_existent_records(query_handler: &TQH) -> Result<(), String> {
+fn test_non_existent_records(db: &DB) -> Result<(), String> {
     println!("Testing non-existent records:");
-    match query_handler.select("users".to_string(), 999) {
+    match db.select("users", 999) {
         Ok(Some(data)) => println!("Unexpected: Found user 999: {}", data),
         Ok(None) => println!(" User 999 not found (as expected)"),
         Err(e) => return Err(format!("Error testing non-existent record: {}", e)),
@@ -276,41 +262,27 @@ fn test_non_existent_records(query_handler: &TQH) -> Result<(), String> {
     Ok(())
 }
 
-fn test_btree_operations(_query_handler: &TQH) -> Result<(), String> {
-    println!("Testing Universal B+Tree system:");
-    
-    // Test users table (ID 3)
-    test_btree_search(3, TableKey::Int(1), "user 1")?;
-    
-    // Test products table (ID 4)
-    test_btree_search(4, TableKey::Int(101), "product 101")?;
-    
-    println!();
+fn query_with_predicate(db: &DB) -> Result<(), String> {
+    println!("Users with salary > 80000:");
+    let predicate = Filter::Gt(4, DataArray::DOUBLE(80000.0));
+    for row in db.select_where("users", &predicate).map_err(|e| e.to_string())? {
+        println!("    {:?}", row);
+    }
     Ok(())
 }
 
-fn test_btree_search(table_id: i32, key: TableKey, description: &str) -> Result<(), String> {
-    match TableBTreeManager::search_in_table(table_id, &key) {
-        Ok(Some(data)) => {
-            println!(" Found {} via B+Tree: page_id={}, offset={}", 
-                description, data.page_id, data.offset);
-            Ok(())
-        },
-        Ok(None) => {
-            println!(" {} not found in B+Tree", description);
-            Ok(())
-        },
-        Err(e) => {
-            println!(" B+Tree search error for {}: {}", description, e);
-            Ok(()) // Don't fail the demo for B+Tree errors
-        }
+fn list_catalog_tables(db: &DB) -> Result<(), String> {
+    println!("Tables in sys_tables:");
+    for row in db.scan("sys_tables").map_err(|e| e.to_string())? {
+        println!("    {}: {}", row[0], row[1]);
     }
+    Ok(())
 }
 
-fn cleanup_and_save(query_handler: &TQH) -> Result<(), String> {
+fn cleanup_and_save(db: &DB) -> Result<(), String> {
     println!("💾 Saving database state...");
     
-    match query_handler.save_btrees() {
+    match db.save() {
         Ok(_) => {
             println!("B+Trees saved successfully");
             Ok(())
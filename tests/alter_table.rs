@@ -0,0 +1,89 @@
+//! ALTER TABLE with schema versions: rows written before an ADD, DROP or RENAME COLUMN are
+//! upgraded as they are read, before and after a restart, until a rewrite stores them in the
+//! current version.
+
+use oxidedb::{Column, DataType, Database, Error, Options, Value};
+
+mod common;
+use common::TempDir;
+
+/// Every row of the table as text, in storage order.
+fn contents(db: &Database) -> Vec<Vec<String>> {
+    db.scan("items").unwrap().iter()
+        .map(|row| row.iter().map(|value| value.to_string()).collect())
+        .collect()
+}
+
+fn names(db: &Database) -> Vec<String> {
+    db.columns("items").unwrap().into_iter().map(|column| column.column_name).collect()
+}
+
+#[test]
+fn old_rows_are_upgraded_as_they_are_read() {
+    let dir = TempDir::new("alter-upgrade");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("items", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("size".to_string(), DataType::INTEGER, false),
+        Column::new("label".to_string(), DataType::STRING(16), false),
+    ]).unwrap();
+    for id in 1..=2 {
+        db.insert("items", id, vec![Value::INTEGER(id), Value::INTEGER(id * 10), Value::STRING(format!("v0-{}", id), 16)]).unwrap();
+    }
+
+    // Version 1: rows of version 0 read the new column's default
+    db.add_column("items", Column::new("stock".to_string(), DataType::INTEGER, false).with_default(Value::INTEGER(5))).unwrap();
+    db.insert("items", 3, vec![Value::INTEGER(3), Value::INTEGER(30), Value::STRING("v1-3".to_string(), 16), Value::INTEGER(33)]).unwrap();
+    assert_eq!(contents(&db), vec![
+        vec!["1", "10", "v0-1", "5"],
+        vec!["2", "20", "v0-2", "5"],
+        vec!["3", "30", "v1-3", "33"],
+    ]);
+
+    // Version 2: the dropped column leaves every row, whichever version wrote it
+    db.drop_column("items", "size").unwrap();
+    // Version 3: a renamed column keeps its values
+    db.rename_column("items", "label", "name").unwrap();
+    // Version 4: a column of a dropped column's name starts out empty, not with the old values
+    db.add_column("items", Column::new("size".to_string(), DataType::INTEGER, false)).unwrap();
+    db.insert("items", 4, vec![Value::INTEGER(4), Value::STRING("v4-4".to_string(), 16), Value::INTEGER(44), Value::INTEGER(400)]).unwrap();
+    let expected = vec![
+        vec!["1", "v0-1", "5", "NULL"],
+        vec!["2", "v0-2", "5", "NULL"],
+        vec!["3", "v1-3", "33", "NULL"],
+        vec!["4", "v4-4", "44", "400"],
+    ];
+    assert_eq!(names(&db), vec!["id", "name", "stock", "size"]);
+    assert_eq!(contents(&db), expected);
+    let row = db.table("items").unwrap().get(1).unwrap().unwrap();
+    assert_eq!(row["name"].to_string(), "v0-1");
+    assert_eq!(db.query("items").eq("stock", Value::INTEGER(5)).run().unwrap().len(), 2);
+
+    // Schema changes that cannot be applied to the existing rows are refused
+    let refused = db.add_column("items", Column::new("required".to_string(), DataType::INTEGER, false).with_not_null(true));
+    assert!(matches!(refused, Err(Error::ConstraintViolation(_))), "{:?}", refused);
+    assert!(matches!(db.drop_column("items", "id"), Err(Error::InvalidOperation(_))));
+    assert!(matches!(db.drop_column("items", "missing"), Err(Error::NotFound(_))));
+    assert!(matches!(db.rename_column("items", "name", "stock"), Err(Error::AlreadyExists(_))));
+    assert!(matches!(db.add_column("items", Column::new("name".to_string(), DataType::INTEGER, false)), Err(Error::AlreadyExists(_))));
+    assert_eq!(names(&db), vec!["id", "name", "stock", "size"]);
+    drop(db);
+
+    // Pages keep the version they were written in across a restart
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_eq!(names(&db), vec!["id", "name", "stock", "size"]);
+    assert_eq!(contents(&db), expected);
+
+    // A rewrite stores every row in the current version, and changes no value
+    db.rewrite_table("items").unwrap();
+    assert_eq!(contents(&db), expected);
+    db.add_column("items", Column::new("later".to_string(), DataType::BOOLEAN, false).with_default(Value::BOOLEAN(false))).unwrap();
+    drop(db);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    let rows = contents(&db);
+    assert_eq!(rows.len(), expected.len());
+    for (row, before) in rows.iter().zip(&expected) {
+        assert_eq!(&row[..4], &before[..]);
+        assert_eq!(row[4], "false");
+    }
+}
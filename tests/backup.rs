@@ -0,0 +1,171 @@
+//! Online backup and restore: a backup taken while other threads write holds one consistent
+//! snapshot, a restored directory opens with its rows, BLOBs and indexes, and a damaged
+//! archive is rejected before anything is replaced.
+
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use oxidedb::{Column, DataType, Database, Error, Options, Value};
+
+mod common;
+use common::TempDir;
+
+fn keyed_table(db: &Database, table_name: &str) {
+    db.create_table(table_name, vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("note".to_string(), DataType::STRING(32), false),
+    ]).unwrap();
+}
+
+fn note(id: i32, text: &str) -> Vec<Value> {
+    vec![Value::INTEGER(id), Value::STRING(text.to_string(), 32)]
+}
+
+/// Keys of a table's rows in order
+fn keys(db: &Database, table_name: &str) -> Vec<i32> {
+    db.scan(table_name).unwrap().iter()
+        .map(|row| match row[0] {
+            Value::INTEGER(id) => id,
+            ref other => panic!("unexpected key {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn backup_is_a_consistent_snapshot_while_writes_continue() {
+    let dir = TempDir::new("backup-online");
+    let archive = dir.join("online.bak");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    keyed_table(&db, "orders");
+    keyed_table(&db, "order_log");
+    for id in 0..100 {
+        db.insert("orders", id, note(id, &format!("order {}", id))).unwrap();
+    }
+
+    // Open when the backup starts: neither its insert nor its delete is in the backup
+    let mut pending = db.begin().unwrap();
+    pending.insert("orders", 1000, note(1000, "pending")).unwrap();
+    assert!(pending.delete("orders", 1).unwrap());
+
+    // Each transaction writes one row to both tables, so any snapshot has as many of each
+    let done = AtomicBool::new(false);
+    let written = thread::scope(|scope| {
+        let writer = scope.spawn(|| {
+            let mut id = 2000;
+            while !done.load(Ordering::SeqCst) || id < 2050 {
+                let mut txn = db.begin().unwrap();
+                txn.insert("orders", id, note(id, "new")).unwrap();
+                txn.insert("order_log", id, note(id, "added")).unwrap();
+                txn.commit().unwrap();
+                id += 1;
+            }
+            id - 2000
+        });
+        let files = db.backup(&archive).unwrap();
+        done.store(true, Ordering::SeqCst);
+        assert!(files >= 4, "{} files", files);
+        writer.join().unwrap()
+    });
+    pending.commit().unwrap();
+    assert_eq!(keys(&db, "order_log").len(), written as usize);
+
+    let copy_dir = TempDir::new("backup-online-copy");
+    Database::restore(&archive, &copy_dir).unwrap();
+    let copy = Database::open(&copy_dir, Options::default()).unwrap();
+    let orders = keys(&copy, "orders");
+    let log = keys(&copy, "order_log");
+    let added: Vec<i32> = orders.iter().copied().filter(|id| *id >= 2000).collect();
+    assert_eq!(added, log);
+    assert_eq!(added, (2000..2000 + added.len() as i32).collect::<Vec<_>>());
+    assert_eq!(orders[..100], (0..100).collect::<Vec<_>>());
+    assert!(copy.get("orders", 1000).unwrap().is_none());
+
+    // The copy takes new writes, and keeps them across a reopen
+    let mut txn = copy.begin().unwrap();
+    txn.insert("orders", 5000, note(5000, "after")).unwrap();
+    txn.commit().unwrap();
+    assert!(copy.delete("orders", 0).unwrap());
+    copy.save().unwrap();
+    drop(copy);
+    let copy = Database::open(&copy_dir, Options::default()).unwrap();
+    assert!(copy.get("orders", 5000).unwrap().is_some());
+    assert!(copy.get("orders", 0).unwrap().is_none());
+    assert!(copy.get("orders", 1).unwrap().is_some());
+}
+
+#[test]
+fn restore_brings_back_blobs_indexes_and_schema_changes() {
+    let dir = TempDir::new("backup-round-trip");
+    let archive = dir.join("round-trip.bak");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("files", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("data".to_string(), DataType::BLOB, false),
+    ]).unwrap();
+    let large: Vec<u8> = (0..50_000).map(|i| (i % 239) as u8).collect();
+    db.insert("files", 1, vec![Value::INTEGER(1), Value::BLOB(large.clone())]).unwrap();
+    db.insert("files", 2, vec![Value::INTEGER(2), Value::BLOB(vec![1, 2, 3])]).unwrap();
+    db.save().unwrap();
+    // Written after the index was saved, so the backup carries its stale mark
+    db.add_column("files", Column::new("name".to_string(), DataType::STRING(16), false)
+        .with_default(Value::STRING("unnamed".to_string(), 16))).unwrap();
+    db.insert("files", 3, vec![Value::INTEGER(3), Value::NULL, Value::STRING("empty".to_string(), 16)]).unwrap();
+    keyed_table(&db, "notes");
+    db.insert("notes", -7, note(-7, "minus seven")).unwrap();
+
+    assert_eq!(db.backup(&archive).unwrap(), 3 + 4 + 2 + 1);
+    drop(db);
+
+    // Restoring over an existing directory replaces what it held
+    let copy_dir = TempDir::new("backup-round-trip-copy");
+    std::fs::create_dir_all(&copy_dir).unwrap();
+    std::fs::write(copy_dir.join("leftover.dat"), b"old").unwrap();
+    assert_eq!(Database::restore(&archive, &copy_dir).unwrap(), 10);
+    assert!(!copy_dir.join("leftover.dat").exists());
+
+    let copy = Database::open(&copy_dir, Options::default()).unwrap();
+    assert_eq!(copy.columns("files").unwrap().len(), 3);
+    assert_eq!(keys(&copy, "files"), vec![1, 2, 3]);
+    assert_eq!(copy.get("files", 2).unwrap().unwrap()[2].to_string(), "unnamed");
+    let mut bytes = Vec::new();
+    copy.read_blob("files", 1, 1).unwrap().unwrap().read_to_end(&mut bytes).unwrap();
+    assert_eq!(bytes, large);
+    assert_eq!(copy.get("notes", -7).unwrap().unwrap()[1].to_string(), "minus seven");
+}
+
+#[test]
+fn damaged_archives_are_rejected() {
+    let dir = TempDir::new("backup-damaged");
+    let archive = dir.join("damaged.bak");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    keyed_table(&db, "items");
+    db.insert("items", 1, note(1, "one")).unwrap();
+    db.backup(&archive).unwrap();
+    drop(db);
+
+    let target = TempDir::new("backup-damaged-target");
+    std::fs::create_dir_all(&target).unwrap();
+    std::fs::write(target.join("keep.dat"), b"untouched").unwrap();
+
+    let good = std::fs::read(&archive).unwrap();
+    let mut flipped = good.clone();
+    let middle = flipped.len() / 2;
+    flipped[middle] ^= 0x40;
+    let damaged = [flipped, good[..good.len() - 20].to_vec(), b"not a backup at all".to_vec()];
+    for bytes in damaged {
+        std::fs::write(&archive, &bytes).unwrap();
+        match Database::restore(&archive, &target) {
+            Err(Error::Corruption(message)) => assert!(message.contains("Backup"), "{}", message),
+            other => panic!("expected corruption, got {:?}", other),
+        }
+        assert_eq!(std::fs::read(target.join("keep.dat")).unwrap(), b"untouched");
+    }
+    assert!(matches!(Database::restore(dir.join("missing.bak"), &target), Err(Error::Io(_))));
+
+    // The intact archive still restores
+    std::fs::write(&archive, &good).unwrap();
+    Database::restore(&archive, &target).unwrap();
+    let copy = Database::open(&target, Options::default()).unwrap();
+    assert_eq!(keys(&copy, "items"), vec![1]);
+}
@@ -0,0 +1,52 @@
+//! BLOB columns: large values spilled to the table's overflow file and read back whole, and
+//! rows too large for a page rejected before any of their BLOBs are written out.
+
+use std::io::Read;
+use std::path::Path;
+
+use oxidedb::{Column, DataType, Database, Error, Options, Value};
+
+mod common;
+use common::TempDir;
+
+/// The longest STRING a column can declare; with a spilled BLOB beside it a row is too large
+const LONGEST_TEXT: i32 = 3987;
+
+fn overflow_len(dir: &Path) -> u64 {
+    std::fs::metadata(dir.join("photos.ovf")).map_or(0, |metadata| metadata.len())
+}
+
+fn read_photo(db: &Database, id: i32) -> Vec<u8> {
+    let mut photo = Vec::new();
+    db.read_blob("photos", id, 2).unwrap().expect("row has a BLOB").read_to_end(&mut photo).unwrap();
+    photo
+}
+
+#[test]
+fn rows_too_large_leave_no_overflow_pages() {
+    let dir = TempDir::new("blobs-rejected");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("photos", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("caption".to_string(), DataType::STRING(LONGEST_TEXT.into()), false),
+        Column::new("photo".to_string(), DataType::BLOB, false),
+    ]).unwrap();
+    let photo = |id: i32| (0..20_000).map(|i: i32| (i * 31 + id) as u8).collect::<Vec<u8>>();
+    db.insert("photos", 1, vec![Value::INTEGER(1), Value::STRING("small".to_string(), LONGEST_TEXT), Value::BLOB(photo(1))]).unwrap();
+    let spilled = overflow_len(&dir);
+    assert!(spilled >= 20_000, "the BLOB was not spilled: {} bytes of overflow", spilled);
+
+    let caption = Value::STRING("x".repeat(LONGEST_TEXT as usize), LONGEST_TEXT);
+    let result = db.insert("photos", 2, vec![Value::INTEGER(2), caption, Value::BLOB(photo(2))]);
+    assert!(matches!(result, Err(Error::ConstraintViolation(_))), "{:?}", result);
+    assert_eq!(overflow_len(&dir), spilled);
+    assert!(db.get("photos", 2).unwrap().is_none());
+
+    // Rows that fit still spill, after the first one's pages
+    db.insert("photos", 3, vec![Value::INTEGER(3), Value::NULL, Value::BLOB(photo(3))]).unwrap();
+    assert!(overflow_len(&dir) > spilled);
+    drop(db);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_eq!(read_photo(&db, 1), photo(1));
+    assert_eq!(read_photo(&db, 3), photo(3));
+}
@@ -0,0 +1,184 @@
+//! Bulk inserts: rows packed into pages with one write per page, an index built bottom-up,
+//! batches that go in whole or not at all, even through a crash, and bulk inserts inside
+//! transactions.
+
+use std::io::Read;
+
+use oxidedb::{Column, DataType, Database, Error, FaultInjector, Options, Value};
+
+mod common;
+use common::TempDir;
+
+const ROWS: i32 = 500;
+
+fn create_items(db: &Database, table_name: &str) {
+    db.create_table(table_name, vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("label".to_string(), DataType::STRING(32), false),
+        Column::new("value".to_string(), DataType::INTEGER, false),
+    ]).unwrap();
+}
+
+fn open(name: &str) -> (Database, TempDir) {
+    let dir = TempDir::new(name);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    create_items(&db, "items");
+    (db, dir)
+}
+
+fn item(id: i32, value: i32) -> Vec<Value> {
+    vec![Value::INTEGER(id), Value::STRING(format!("item-{}", id), 32), Value::INTEGER(value)]
+}
+
+fn items(keys: impl Iterator<Item = i32>) -> Vec<(i32, Vec<Value>)> {
+    keys.map(|id| (id, item(id, id * 10))).collect()
+}
+
+fn value(row: Option<Vec<Value>>) -> Option<i32> {
+    match row?.get(2) {
+        Some(Value::INTEGER(value)) => Some(*value),
+        other => panic!("unexpected value {:?}", other),
+    }
+}
+
+fn assert_all_rows(db: &Database) {
+    assert_eq!(db.scan("items").unwrap().len(), ROWS as usize);
+    for id in 0..ROWS {
+        assert_eq!(value(db.get("items", id).unwrap()), Some(id * 10), "row {}", id);
+    }
+}
+
+#[test]
+fn bulk_insert_writes_each_page_once() {
+    let (db, dir) = open("bulk-pages");
+    create_items(&db, "one_by_one");
+
+    FaultInjector::count_writes();
+    for id in 0..ROWS {
+        db.insert("one_by_one", id, item(id, id * 10)).unwrap();
+    }
+    let row_writes = FaultInjector::writes();
+    // Keys in no particular order; the index sorts them
+    FaultInjector::count_writes();
+    db.bulk_insert("items", items((0..ROWS).rev())).unwrap();
+    let bulk_writes = FaultInjector::writes();
+    FaultInjector::disarm();
+
+    assert!(bulk_writes * 20 < row_writes, "bulk insert made {} writes, row by row {}", bulk_writes, row_writes);
+    assert_all_rows(&db);
+
+    // The index outlives a restart, both saved and rebuilt from the pages
+    drop(db);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_all_rows(&db);
+    db.save().unwrap();
+    drop(db);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_all_rows(&db);
+    db.insert("items", ROWS, item(ROWS, 0)).unwrap();
+    assert_eq!(db.scan("items").unwrap().len(), ROWS as usize + 1);
+}
+
+#[test]
+fn rejected_batch_inserts_nothing() {
+    let (db, _dir) = open("bulk-rejected");
+    db.insert("items", 7, item(7, 0)).unwrap();
+    db.insert("items", 8, item(8, 0)).unwrap();
+    assert!(db.delete("items", 8).unwrap());
+
+    let mut repeated = items(0..5);
+    repeated.push((3, item(3, 0)));
+    assert!(matches!(db.bulk_insert("items", repeated), Err(Error::DuplicateKey { key: 3, .. })));
+    assert!(matches!(db.bulk_insert("items", items(0..10)), Err(Error::DuplicateKey { key: 7, .. })));
+    let mut mistyped = items(0..5);
+    mistyped[4].1[2] = Value::STRING("four".to_string(), 32);
+    assert!(matches!(db.bulk_insert("items", mistyped), Err(Error::TypeMismatch(_))));
+    assert_eq!(db.scan("items").unwrap().len(), 1);
+
+    // Into a table that has rows, including a key deleted before
+    db.bulk_insert("items", items((0..7).chain(8..10))).unwrap();
+    assert_eq!(db.scan("items").unwrap().len(), 10);
+    assert_eq!(value(db.get("items", 7).unwrap()), Some(0));
+    assert_eq!(value(db.get("items", 8).unwrap()), Some(80));
+    db.bulk_insert("items", Vec::new()).unwrap();
+
+    // Through a table handle, keys taken from the rows
+    let table = db.table("items").unwrap();
+    table.bulk_insert((10..20).map(|id| item(id, id * 10)).collect()).unwrap();
+    assert_eq!(table.scan().unwrap().len(), 20);
+}
+
+#[test]
+fn bulk_insert_in_a_transaction() {
+    let (db, _dir) = open("bulk-transaction");
+    db.insert("items", 0, item(0, 0)).unwrap();
+
+    let mut txn = db.begin().unwrap();
+    txn.bulk_insert("items", items(1..100)).unwrap();
+    assert_eq!(txn.scan("items").unwrap().len(), 100);
+    assert_eq!(db.scan("items").unwrap().len(), 1);
+    txn.rollback().unwrap();
+    assert_eq!(db.scan("items").unwrap().len(), 1);
+    assert!(db.get("items", 50).unwrap().is_none());
+
+    let mut txn = db.begin().unwrap();
+    txn.savepoint("before").unwrap();
+    txn.bulk_insert("items", items(1..50)).unwrap();
+    txn.rollback_to("before").unwrap();
+    txn.bulk_insert("items", items(50..100)).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(db.scan("items").unwrap().len(), 51);
+    assert!(db.get("items", 10).unwrap().is_none());
+    assert_eq!(value(db.get("items", 60).unwrap()), Some(600));
+}
+
+#[test]
+fn crash_mid_batch_keeps_all_rows_or_none() {
+    let (db, _dir) = open("bulk-crash-count");
+    FaultInjector::count_writes();
+    db.bulk_insert("items", items(0..ROWS)).unwrap();
+    let writes = FaultInjector::writes();
+    FaultInjector::disarm();
+    assert!(writes > 4, "the batch took only {} writes", writes);
+
+    for crash_point in 0..writes {
+        let (db, dir) = open(&format!("bulk-crash-{}", crash_point));
+        // Torn anywhere from nothing to past a page and its double-write header
+        let torn_bytes = (crash_point as usize * 997) % (4096 + 64);
+        FaultInjector::crash_after(crash_point, torn_bytes);
+        assert!(db.bulk_insert("items", items(0..ROWS)).is_err(), "crash at write {}", crash_point);
+        drop(db);
+        FaultInjector::disarm();
+
+        let db = Database::open(&dir, Options::default()).unwrap();
+        match db.scan("items").unwrap().len() {
+            0 => assert!(db.get("items", 0).unwrap().is_none()),
+            len if len == ROWS as usize => assert_all_rows(&db),
+            len => panic!("crash at write {} left {} of {} rows", crash_point, len, ROWS),
+        }
+        db.insert("items", ROWS, item(ROWS, 0)).unwrap();
+    }
+}
+
+#[test]
+fn oversized_row_rejects_the_batch_before_any_blob_is_written() {
+    let (db, dir) = open("bulk-oversized");
+    db.create_table("photos", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("caption".to_string(), DataType::STRING(3987), false),
+        Column::new("photo".to_string(), DataType::BLOB, false),
+    ]).unwrap();
+    let photo = |id: i32| (id, vec![Value::INTEGER(id), Value::NULL, Value::BLOB(vec![id as u8; 10_000])]);
+    // The last row's caption and spilled BLOB together do not fit in a page
+    let mut rows: Vec<_> = (0..5).map(photo).collect();
+    rows.push((5, vec![Value::INTEGER(5), Value::STRING("x".repeat(3987), 3987), Value::BLOB(vec![5; 10_000])]));
+    let result = db.bulk_insert("photos", rows);
+    assert!(matches!(result, Err(Error::ConstraintViolation(_))), "{:?}", result);
+    assert!(!dir.join("photos.ovf").exists());
+    assert!(db.scan("photos").unwrap().is_empty());
+
+    db.bulk_insert("photos", (0..5).map(photo).collect()).unwrap();
+    let mut blob = Vec::new();
+    db.read_blob("photos", 4, 2).unwrap().unwrap().read_to_end(&mut blob).unwrap();
+    assert_eq!(blob, vec![4; 10_000]);
+}
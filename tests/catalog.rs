@@ -0,0 +1,129 @@
+//! The catalog: column names, types, flags and defaults, and table ids, as they were before a
+//! restart, and the system tables that hold them, readable but not writable.
+
+use oxidedb::{Column, DataType, Database, Date, Decimal, Error, Options, Value};
+
+mod common;
+use common::TempDir;
+
+fn every_column() -> Vec<Column> {
+    vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("big".to_string(), DataType::BIGINT, false).with_default(Value::BIGINT(-1)),
+        Column::new("ratio".to_string(), DataType::FLOAT, false),
+        Column::new("exact_ratio".to_string(), DataType::DOUBLE, false).with_default(Value::DOUBLE(0.25)),
+        Column::new("name".to_string(), DataType::STRING(40), false).with_not_null(true)
+            .with_default(Value::STRING("nobody".to_string(), 40)),
+        Column::new("code".to_string(), DataType::CHAR(3), false).with_default(Value::CHAR("ab".to_string(), 3)),
+        Column::new("active".to_string(), DataType::BOOLEAN, false).with_default(Value::BOOLEAN(true)),
+        Column::new("born".to_string(), DataType::DATE, false).with_default(Value::DATE(Date::from_ymd(2000, 1, 1).unwrap())),
+        Column::new("seen".to_string(), DataType::TIMESTAMP, false).with_not_null(true),
+        Column::new("price".to_string(), DataType::DECIMAL(9, 3), false)
+            .with_default(Value::DECIMAL(Decimal::parse("1.5", 3).unwrap())),
+        Column::new("photo".to_string(), DataType::BLOB, false).with_default(Value::BLOB(vec![0, 1, 255])),
+    ]
+}
+
+/// Everything the catalog records about a column, in a comparable form.
+fn describe(columns: &[Column]) -> Vec<(String, DataType, bool, bool, String)> {
+    columns.iter()
+        .map(|column| (
+            column.column_name.clone(),
+            column.column_type.clone(),
+            column.is_primary,
+            column.not_null,
+            format!("{:?}", column.default_value),
+        ))
+        .collect()
+}
+
+#[test]
+fn column_definitions_survive_a_restart() {
+    let dir = TempDir::new("catalog-columns");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    let people_id = db.create_table("people", every_column()).unwrap();
+    let notes_id = db.create_table("notes", vec![
+        Column::new("note_id".to_string(), DataType::INTEGER, true),
+        Column::new("text".to_string(), DataType::STRING(200), false),
+    ]).unwrap();
+    assert_ne!(people_id, notes_id);
+    let created = describe(&db.columns("people").unwrap());
+    assert_eq!(created, describe(&every_column()));
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_eq!(describe(&db.columns("people").unwrap()), created);
+    assert_eq!(db.table_id("people"), Some(people_id));
+    assert_eq!(db.table_id("notes"), Some(notes_id));
+    let mut names = db.table_names();
+    names.sort();
+    assert_eq!(names, vec!["notes", "people"]);
+
+    // A row built from the restarted catalog takes the defaults it recorded
+    let people = db.table("people").unwrap();
+    people.insert_named(vec![
+        ("id", Value::INTEGER(1)),
+        ("seen", DataType::TIMESTAMP.parse_value("2024-05-06 07:08:09").unwrap()),
+    ]).unwrap();
+    let row = people.get(1).unwrap().unwrap();
+    assert_eq!(row["name"].to_string(), "nobody");
+    assert_eq!(row["price"].to_string(), "1.500");
+    assert_eq!(row["born"].to_string(), "2000-01-01");
+    assert!(row["ratio"].is_null());
+
+    // Ids handed out after a restart do not reuse those of existing tables
+    let later_id = db.create_table("later", vec![Column::new("id".to_string(), DataType::INTEGER, true)]).unwrap();
+    assert!(later_id != people_id && later_id != notes_id);
+    drop(db);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_eq!(db.table_id("later"), Some(later_id));
+}
+
+#[test]
+fn system_tables_are_readable_and_read_only() {
+    let dir = TempDir::new("catalog-system");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    let people_id = db.create_table("people", every_column()).unwrap();
+    db.rename_column("people", "big", "huge").unwrap();
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    let tables: Vec<(String, String)> = db.scan("sys_tables").unwrap().iter()
+        .map(|row| (row[0].to_string(), row[1].to_string()))
+        .collect();
+    for (table_id, table_name) in [(0, "sys_tables"), (1, "sys_columns"), (2, "sys_indexes"), (people_id, "people")] {
+        assert!(tables.contains(&(table_id.to_string(), table_name.to_string())), "{} missing from {:?}", table_name, tables);
+    }
+    assert!(db.table_names().iter().all(|name| !name.starts_with("sys_")));
+
+    // One live sys_columns row per column of the table, in ordinal order
+    let column_name = db.column_index("sys_columns", "column_name").unwrap();
+    let dropped_in = db.column_index("sys_columns", "dropped_in").unwrap();
+    let names: Vec<String> = db.query("sys_columns")
+        .eq("table_id", Value::INTEGER(people_id))
+        .run().unwrap().iter()
+        .filter(|row| row.values()[dropped_in].is_null())
+        .map(|row| row.values()[column_name].to_string())
+        .collect();
+    let expected: Vec<String> = db.columns("people").unwrap().into_iter().map(|column| column.column_name).collect();
+    assert_eq!(names, expected);
+    assert!(names.contains(&"huge".to_string()));
+
+    // No writes, schema changes or maintenance through the public API
+    let refused = [
+        db.insert("sys_tables", 99, vec![Value::INTEGER(99), Value::STRING("fake".to_string(), 128), Value::INTEGER(0)]).err(),
+        db.delete("sys_tables", people_id).err(),
+        db.bulk_insert("sys_indexes", vec![(1, vec![Value::INTEGER(1)]), (2, vec![Value::INTEGER(2)])]).err(),
+        db.drop_table("sys_columns").err(),
+        db.truncate_table("sys_tables").err(),
+        db.rename_table("sys_indexes", "indexes").err(),
+        db.rewrite_table("sys_tables").err(),
+        db.vacuum("sys_columns").err(),
+    ];
+    for (i, error) in refused.into_iter().enumerate() {
+        assert!(matches!(error, Some(Error::InvalidOperation(_))), "operation {}: {:?}", i, error);
+    }
+    assert!(matches!(db.create_table("sys_tables", every_column()), Err(Error::AlreadyExists(_))));
+    assert!(matches!(db.rename_table("people", "sys_columns"), Err(Error::AlreadyExists(_))));
+    assert_eq!(db.scan("sys_tables").unwrap().len(), tables.len());
+}
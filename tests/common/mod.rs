@@ -0,0 +1,60 @@
+//! Helpers shared by the integration tests.
+
+// Each test crate uses only some of them
+#![allow(dead_code)]
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A directory for one test under the system temp directory, removed when the test ends,
+/// whether it passed or panicked. It is not created: `Database::open` does that.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// The directory named after `name` and the process, emptied of what an earlier run left.
+    pub fn new(name: &str) -> TempDir {
+        let dir = std::env::temp_dir().join(format!("oxidedb-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A xorshift generator, so every run of a seed makes the same cases.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
@@ -0,0 +1,205 @@
+//! Many threads sharing one `Database`: writers on their own and on a shared table, readers
+//! scanning and looking up while they write. No reader may see a torn page or a half-written
+//! row, and no index entry may be lost, in memory or after a save and reopen. The same goes
+//! for threads inserting into and searching one `IndexTree` directly.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use oxidedb::{Column, DataType, Database, IndexKey, IndexTree, Options, RowLocation, Value};
+
+mod common;
+use common::TempDir;
+
+const WRITERS: i32 = 4;
+const ROWS_PER_WRITER: i32 = 200;
+const READERS: usize = 4;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+fn index_entry(key: i32) -> Option<Box<IndexKey<i32>>> {
+    Some(Box::new(IndexKey::new(key, Some(Box::new(RowLocation::new(key as i64 * 3, key % 7))))))
+}
+
+fn columns() -> Vec<Column> {
+    vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("name".to_string(), DataType::STRING(32), false),
+        Column::new("double".to_string(), DataType::INTEGER, false),
+    ]
+}
+
+fn row(id: i32) -> Vec<Value> {
+    vec![
+        Value::INTEGER(id),
+        Value::STRING(format!("row-{}", id), 32),
+        Value::INTEGER(id * 2),
+    ]
+}
+
+/// Panics unless the row holds exactly what `row(id)` wrote.
+fn check_row(values: &[Value]) -> i32 {
+    let id = match values.first() {
+        Some(Value::INTEGER(id)) => *id,
+        other => panic!("row without an INTEGER key: {:?}", other),
+    };
+    match &values[1] {
+        Value::STRING(name, _) => assert_eq!(name, &format!("row-{}", id), "torn row {}", id),
+        other => panic!("row {} has name {:?}", id, other),
+    }
+    match &values[2] {
+        Value::INTEGER(double) => assert_eq!(*double, id * 2, "torn row {}", id),
+        other => panic!("row {} has double {:?}", id, other),
+    }
+    assert_eq!(values.len(), 3);
+    id
+}
+
+fn key(writer: i32, i: i32) -> i32 {
+    writer * ROWS_PER_WRITER + i
+}
+
+fn verify(db: &Database) {
+    let mut shared_ids = HashSet::new();
+    for values in db.scan("shared").unwrap() {
+        assert!(shared_ids.insert(check_row(&values)), "duplicate row in scan");
+    }
+    assert_eq!(shared_ids.len(), (WRITERS * ROWS_PER_WRITER) as usize);
+
+    for writer in 0..WRITERS {
+        let own = format!("own_{}", writer);
+        assert_eq!(db.scan(&own).unwrap().len(), ROWS_PER_WRITER as usize);
+        for i in 0..ROWS_PER_WRITER {
+            let id = key(writer, i);
+            let values = db.get("shared", id).unwrap()
+                .unwrap_or_else(|| panic!("index entry {} of 'shared' is lost", id));
+            assert_eq!(check_row(&values), id);
+            let values = db.get(&own, i).unwrap()
+                .unwrap_or_else(|| panic!("index entry {} of '{}' is lost", i, own));
+            assert_eq!(check_row(&values), i);
+        }
+    }
+}
+
+#[test]
+fn database_is_send_and_sync() {
+    assert_send_sync::<Database>();
+}
+
+#[test]
+fn concurrent_readers_and_writers() {
+    let dir = TempDir::new("concurrency");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("shared", columns()).unwrap();
+    for writer in 0..WRITERS {
+        db.create_table(&format!("own_{}", writer), columns()).unwrap();
+    }
+
+    let writing = AtomicBool::new(true);
+    thread::scope(|scope| {
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|writer| {
+                let db = &db;
+                scope.spawn(move || {
+                    let own = format!("own_{}", writer);
+                    for i in 0..ROWS_PER_WRITER {
+                        db.insert("shared", key(writer, i), row(key(writer, i))).unwrap();
+                        db.insert(&own, i, row(i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for reader in 0..READERS {
+            let (db, writing) = (&db, &writing);
+            scope.spawn(move || {
+                let mut probe = reader as i32;
+                while writing.load(Ordering::Acquire) {
+                    let rows = db.scan("shared").unwrap();
+                    for values in &rows {
+                        check_row(values);
+                    }
+                    // A key either is not there yet or comes back whole
+                    if let Some(values) = db.get("shared", probe).unwrap() {
+                        assert_eq!(check_row(&values), probe);
+                    }
+                    probe = (probe + 37) % (WRITERS * ROWS_PER_WRITER);
+                }
+            });
+        }
+
+        for handle in writers {
+            handle.join().unwrap();
+        }
+        writing.store(false, Ordering::Release);
+    });
+
+    verify(&db);
+    db.save().unwrap();
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    verify(&db);
+}
+
+#[test]
+fn concurrent_creates_get_distinct_ids() {
+    let dir = TempDir::new("concurrent-create");
+    let db = Database::open(&dir, Options::default()).unwrap();
+
+    let ids: Vec<i32> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|n| {
+                let db = &db;
+                scope.spawn(move || db.create_table(&format!("table_{}", n), columns()).unwrap())
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+    assert_eq!(db.table_names().len(), ids.len());
+
+    // Racing creates of one name: exactly one wins
+    let results: Vec<bool> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let db = &db;
+                scope.spawn(move || db.create_table("contended", columns()).is_ok())
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+    assert_eq!(results.iter().filter(|created| **created).count(), 1);
+}
+
+#[test]
+fn index_tree_concurrent_inserts_and_searches() {
+    const THREADS: i32 = 8;
+    const KEYS_PER_THREAD: i32 = 5_000;
+    assert_send_sync::<IndexTree<i32>>();
+
+    let tree = IndexTree::new();
+    thread::scope(|scope| {
+        for t in 0..THREADS {
+            let tree = &tree;
+            scope.spawn(move || {
+                // Interleaved keys, so threads split the same leaves
+                for i in 0..KEYS_PER_THREAD {
+                    let key = i * THREADS + t;
+                    tree.insert(index_entry(key));
+                    let location = tree.search(&key)
+                        .unwrap_or_else(|| panic!("key {} not found right after insert", key));
+                    assert_eq!((location.page_id, location.offset), (key as i64 * 3, key % 7));
+                }
+            });
+        }
+    });
+
+    let keys: Vec<i32> = tree.entries().iter().map(|entry| entry.key).collect();
+    assert_eq!(keys, (0..THREADS * KEYS_PER_THREAD).collect::<Vec<_>>());
+    for key in 0..THREADS * KEYS_PER_THREAD {
+        assert!(tree.search(&key).is_some(), "key {} lost", key);
+    }
+}
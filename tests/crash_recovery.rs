@@ -0,0 +1,252 @@
+//! Crash recovery under fault injection. A randomized workload of autocommit writes,
+//! transactions, bulk inserts, vacuums, rewrites and index saves runs once to count its file
+//! writes, then again for every one of them with a crash there, tearing that write at a
+//! random length.
+//! After each crash the database is reopened: every committed row must be readable, the
+//! operation cut short must have happened entirely or not at all, and the primary key index
+//! must agree with the rows.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use oxidedb::{Column, DataType, Database, FaultInjector, Options, Result, Value};
+
+mod common;
+use common::{TempDir, Rng};
+
+const KEYS: i32 = 24;
+const OPERATIONS: usize = 40;
+/// Torn writes keep up to this many bytes: past a page and its double-write header, so some
+/// crashing writes still complete
+const TORN_LIMIT: u64 = 4096 + 64;
+
+#[derive(Clone, Debug)]
+enum Write {
+    /// Insert the key, or update it when it exists
+    Put(i32, i32),
+    Delete(i32),
+}
+
+#[derive(Clone, Debug)]
+enum Operation {
+    Insert(i32, i32),
+    Delete(i32),
+    Transaction { writes: Vec<Write>, commit: bool },
+    /// A bulk insert of keys not in the table
+    Bulk(Vec<(i32, i32)>),
+    Vacuum,
+    Rewrite,
+    Save,
+}
+
+type Rows = BTreeMap<i32, i32>;
+
+fn row(id: i32, value: i32) -> Vec<Value> {
+    vec![Value::INTEGER(id), Value::STRING(format!("value-{}", value), 32), Value::INTEGER(value)]
+}
+
+/// A workload whose every operation succeeds when nothing crashes.
+fn workload(seed: u64) -> Vec<Operation> {
+    let mut rng = Rng::new(seed);
+    let mut rows = initial_rows();
+    let mut operations = Vec::new();
+    for _ in 0..OPERATIONS {
+        let key = rng.below(KEYS as u64) as i32;
+        let value = rng.below(1000) as i32;
+        let operation = match rng.below(10) {
+            0..=3 if rows.contains_key(&key) => Operation::Delete(key),
+            0..=3 => Operation::Insert(key, value),
+            4..=6 => {
+                let writes = (0..1 + rng.below(4))
+                    .map(|_| {
+                        let key = rng.below(KEYS as u64) as i32;
+                        match rng.below(3) {
+                            0 => Write::Delete(key),
+                            _ => Write::Put(key, rng.below(1000) as i32),
+                        }
+                    })
+                    .collect();
+                Operation::Transaction { writes, commit: rng.below(4) != 0 }
+            }
+            7 if rng.below(2) == 0 => Operation::Vacuum,
+            7 => {
+                let mut bulk = Vec::new();
+                for key in (0..KEYS).filter(|key| !rows.contains_key(key)) {
+                    if rng.below(3) == 0 {
+                        bulk.push((key, rng.below(1000) as i32));
+                    }
+                }
+                Operation::Bulk(bulk)
+            }
+            8 => Operation::Rewrite,
+            _ => Operation::Save,
+        };
+        rows = expected(&rows, &operation);
+        operations.push(operation);
+    }
+    operations
+}
+
+/// The rows after `operation` succeeds.
+fn expected(rows: &Rows, operation: &Operation) -> Rows {
+    let mut rows = rows.clone();
+    match operation {
+        Operation::Insert(key, value) => {
+            rows.insert(*key, *value);
+        }
+        Operation::Delete(key) => {
+            rows.remove(key);
+        }
+        Operation::Transaction { writes, commit: true } => {
+            for write in writes {
+                match write {
+                    Write::Put(key, value) => rows.insert(*key, *value),
+                    Write::Delete(key) => rows.remove(key),
+                };
+            }
+        }
+        Operation::Bulk(bulk) => rows.extend(bulk.iter().copied()),
+        _ => {}
+    }
+    rows
+}
+
+fn apply(db: &Database, operation: &Operation) -> Result<()> {
+    match operation {
+        Operation::Insert(key, value) => db.insert("items", *key, row(*key, *value)),
+        Operation::Delete(key) => db.delete("items", *key).map(|_| ()),
+        Operation::Transaction { writes, commit } => {
+            let mut txn = db.begin()?;
+            for write in writes {
+                match write {
+                    Write::Put(key, value) => {
+                        if !txn.update("items", *key, row(*key, *value))? {
+                            txn.insert("items", *key, row(*key, *value))?;
+                        }
+                    }
+                    Write::Delete(key) => {
+                        txn.delete("items", *key)?;
+                    }
+                }
+            }
+            if *commit { txn.commit() } else { txn.rollback() }
+        }
+        Operation::Bulk(bulk) => db.bulk_insert("items", bulk.iter().map(|(key, value)| (*key, row(*key, *value))).collect()),
+        Operation::Vacuum => db.vacuum("items").map(|_| ()),
+        Operation::Rewrite => db.rewrite_table("items"),
+        Operation::Save => db.save(),
+    }
+}
+
+/// The rows `setup` inserts.
+fn initial_rows() -> Rows {
+    (0..KEYS).step_by(3).map(|key| (key, key)).collect()
+}
+
+/// A database holding `initial_rows` with its index saved, before the workload starts.
+fn setup(dir: &Path) -> Database {
+    let db = Database::open(dir, Options::default()).unwrap();
+    db.create_table("items", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("label".to_string(), DataType::STRING(32), false),
+        Column::new("value".to_string(), DataType::INTEGER, false),
+    ]).unwrap();
+    for (key, value) in initial_rows() {
+        db.insert("items", key, row(key, value)).unwrap();
+    }
+    db.save().unwrap();
+    db
+}
+
+/// Runs the workload until an operation fails. Returns the rows after the last operation,
+/// or on a failure the rows before and after the failed one, either of which the database
+/// may hold.
+fn run(db: &Database, operations: &[Operation]) -> std::result::Result<Rows, (Rows, Rows)> {
+    let mut rows = initial_rows();
+    for operation in operations {
+        let after = expected(&rows, operation);
+        if apply(db, operation).is_err() {
+            return Err((rows, after));
+        }
+        rows = after;
+    }
+    Ok(rows)
+}
+
+/// The rows a scan finds, each checked against its index entry.
+fn contents(db: &Database) -> Rows {
+    let mut rows = Rows::new();
+    for values in db.scan("items").unwrap() {
+        let (key, value) = match (&values[0], &values[1], &values[2]) {
+            (Value::INTEGER(key), Value::STRING(label, _), Value::INTEGER(value)) => {
+                assert_eq!(label, &format!("value-{}", value), "torn row {}", key);
+                (*key, *value)
+            }
+            other => panic!("unexpected row {:?}", other),
+        };
+        assert!(rows.insert(key, value).is_none(), "key {} appears twice", key);
+    }
+    for key in 0..KEYS {
+        let indexed = db.get("items", key).unwrap().map(|values| match values[2] {
+            Value::INTEGER(value) => value,
+            ref other => panic!("unexpected value {:?}", other),
+        });
+        assert_eq!(indexed, rows.get(&key).copied(), "index entry of key {} does not match the rows", key);
+    }
+    rows
+}
+
+fn reopen(dir: &Path) -> Database {
+    Database::open(dir, Options::default()).unwrap_or_else(|e| panic!("reopen failed: {}", e))
+}
+
+fn crash_at_every_write(seed: u64) {
+    let operations = workload(seed);
+    let dir = TempDir::new(&format!("crash-count-{}", seed));
+    let db = setup(&dir);
+    FaultInjector::count_writes();
+    let outcome = run(&db, &operations);
+    let writes = FaultInjector::writes();
+    FaultInjector::disarm();
+    let rows = outcome.unwrap_or_else(|_| panic!("workload of seed {} fails without a crash", seed));
+    assert_eq!(contents(&db), rows);
+    drop(db);
+    assert_eq!(contents(&reopen(&dir)), rows);
+
+    let mut rng = Rng::new(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1);
+    for crash_point in 0..writes {
+        let dir = TempDir::new(&format!("crash-{}-{}", seed, crash_point));
+        let db = setup(&dir);
+        let torn_bytes = rng.below(TORN_LIMIT) as usize;
+        FaultInjector::crash_after(crash_point, torn_bytes);
+        let (before, after) = run(&db, &operations)
+            .expect_err("the workload ran to the end through a crash");
+        drop(db);
+        FaultInjector::disarm();
+
+        let context = format!("seed {}, crash at write {} torn after {} bytes", seed, crash_point, torn_bytes);
+        let db = reopen(&dir);
+        let recovered = contents(&db);
+        assert!(recovered == before || recovered == after,
+            "{}: recovered {:?}, expected {:?} or {:?}", context, recovered, before, after);
+
+        // The recovered database takes writes, and survives a clean restart
+        db.vacuum("items").unwrap();
+        assert_eq!(contents(&db), recovered, "{}: vacuum changed the rows", context);
+        db.insert("items", KEYS, row(KEYS, 0)).unwrap();
+        db.delete("items", KEYS).unwrap();
+        db.save().unwrap();
+        drop(db);
+        assert_eq!(contents(&reopen(&dir)), recovered, "{}: rows changed after a restart", context);
+    }
+}
+
+#[test]
+fn recovers_from_a_crash_at_every_write() {
+    crash_at_every_write(0x5eed);
+}
+
+#[test]
+fn recovers_from_a_crash_at_every_write_of_another_workload() {
+    crash_at_every_write(0xc0ffee);
+}
@@ -0,0 +1,82 @@
+//! The `Database` handle: independent databases in one process, each confined to its own
+//! directory, and existing tables used after opening without loading their indexes.
+
+use oxidedb::{Column, DataType, Database, Error, Options, Value};
+
+mod common;
+use common::TempDir;
+
+fn columns() -> Vec<Column> {
+    vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("name".to_string(), DataType::STRING(16), false),
+    ]
+}
+
+#[test]
+fn table_names_cannot_leave_the_directory() {
+    let parent = TempDir::new("database-names");
+    let dir = parent.join("db");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("kept", columns()).unwrap();
+
+    let long_name = "t".repeat(129);
+    let bad_names = ["../escaped", "a/b", "a\\b", "..", ".", "x\0y", "1abc", "", "with space", "名前", "dot.dat", long_name.as_str()];
+    for name in bad_names {
+        assert!(matches!(db.create_table(name, columns()), Err(Error::ConstraintViolation(_))), "create {:?}", name);
+        assert!(matches!(db.rename_table("kept", name), Err(Error::ConstraintViolation(_))), "rename to {:?}", name);
+    }
+    let mut files: Vec<String> = std::fs::read_dir(&*parent).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["db"]);
+    assert_eq!(db.table_names(), vec!["kept"]);
+
+    for name in ["_private", "Orders2", "snake_case_name"] {
+        db.create_table(name, columns()).unwrap();
+    }
+    db.rename_table("kept", "Kept_Renamed").unwrap();
+    db.insert("Kept_Renamed", 1, vec![Value::INTEGER(1), Value::STRING("one".to_string(), 16)]).unwrap();
+    assert!(db.get("Kept_Renamed", 1).unwrap().is_some());
+}
+
+#[test]
+fn tables_load_their_index_on_first_use() {
+    let dir = TempDir::new("database-lazy-indexes");
+    let name = |id: i32| Value::STRING(format!("name-{}", id), 16);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("people", columns()).unwrap();
+    for id in 0..100 {
+        db.insert("people", id, vec![Value::INTEGER(id), name(id)]).unwrap();
+    }
+    db.save().unwrap();
+    // Written after the save, so the saved index is stale and must be rebuilt
+    for id in 100..110 {
+        db.insert("people", id, vec![Value::INTEGER(id), name(id)]).unwrap();
+    }
+    drop(db);
+
+    let options = Options { load_indexes: false, ..Options::default() };
+    let db = Database::open(&dir, options).unwrap();
+    assert!(db.loaded_tables().is_empty());
+    assert_eq!(db.get("people", 5).unwrap().unwrap()[1].to_string(), "name-5");
+    assert_eq!(db.get("people", 105).unwrap().unwrap()[1].to_string(), "name-105");
+    assert!(matches!(
+        db.insert("people", 5, vec![Value::INTEGER(5), name(5)]),
+        Err(Error::DuplicateKey { key: 5, .. })
+    ));
+    // New rows go after the existing ones rather than over them
+    for id in 110..140 {
+        db.insert("people", id, vec![Value::INTEGER(id), name(id)]).unwrap();
+    }
+    assert_eq!(db.scan("people").unwrap().len(), 140);
+    db.save().unwrap();
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_eq!(db.scan("people").unwrap().len(), 140);
+    for id in 0..140 {
+        assert_eq!(db.get("people", id).unwrap().unwrap()[1].to_string(), format!("name-{}", id), "row {}", id);
+    }
+}
@@ -0,0 +1,82 @@
+//! Typed errors: each kind of failure comes back as its own `Error` variant through the
+//! public API, with a message naming what failed, rather than as a panic. Write conflicts,
+//! deadlocks and lock timeouts are covered in `transactions.rs`, import errors in
+//! `csv_copy.rs` and `json_lines.rs`.
+
+use std::error::Error as _;
+
+use oxidedb::{Column, DataType, Database, Error, Options, Value};
+
+mod common;
+use common::TempDir;
+
+fn open(name: &str) -> (Database, TempDir) {
+    let dir = TempDir::new(name);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("people", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("name".to_string(), DataType::STRING(16), false).with_not_null(true),
+    ]).unwrap();
+    (db, dir)
+}
+
+fn person(id: i32, name: &str) -> Vec<Value> {
+    vec![Value::INTEGER(id), Value::STRING(name.to_string(), 16)]
+}
+
+#[test]
+fn each_failure_has_its_own_variant() {
+    let (db, _dir) = open("errors-variants");
+    db.insert("people", 1, person(1, "Ada")).unwrap();
+
+    match db.insert("people", 1, person(1, "Bo")) {
+        Err(Error::DuplicateKey { table, key }) => assert_eq!((table.as_str(), key), ("people", 1)),
+        other => panic!("expected DuplicateKey, got {:?}", other),
+    }
+    let error = db.insert("missing", 1, person(1, "Ada")).unwrap_err();
+    assert!(matches!(error, Error::NotFound(_)) && error.to_string().contains("missing"), "{:?}", error);
+    assert!(matches!(db.get("missing", 1), Err(Error::NotFound(_))));
+    assert!(matches!(db.column_index("people", "age"), Err(Error::NotFound(_))));
+    assert!(matches!(db.drop_table("missing"), Err(Error::NotFound(_))));
+    assert!(matches!(db.create_table("people", vec![Column::new("id".to_string(), DataType::INTEGER, true)]), Err(Error::AlreadyExists(_))));
+
+    let mistyped = vec![Value::INTEGER(2), Value::INTEGER(2)];
+    assert!(matches!(db.insert("people", 2, mistyped), Err(Error::TypeMismatch(_))));
+    assert!(matches!(db.insert("people", 2, vec![Value::INTEGER(2)]), Err(Error::SchemaMismatch(_))));
+    assert!(matches!(db.insert("people", 2, vec![Value::INTEGER(2), Value::NULL]), Err(Error::ConstraintViolation(_))));
+    assert!(matches!(db.insert("sys_tables", 2, person(2, "x")), Err(Error::InvalidOperation(_))));
+    assert!(matches!(db.create_table("nothing", Vec::new()), Err(Error::SchemaMismatch(_))));
+
+    // None of them changed anything, and lookups of absent rows are not errors
+    assert_eq!(db.scan("people").unwrap().len(), 1);
+    assert!(db.get("people", 2).unwrap().is_none());
+    assert!(!db.delete("people", 2).unwrap());
+}
+
+#[test]
+fn damaged_files_and_io_failures_are_told_apart() {
+    let (db, dir) = open("errors-files");
+    drop(db);
+
+    // A transaction log record of a kind the database never writes
+    let log = dir.join("transactions.log");
+    let mut bytes = std::fs::read(&log).unwrap();
+    bytes.push(9);
+    bytes.extend(1u64.to_le_bytes());
+    std::fs::write(&log, bytes).unwrap();
+    match Database::open(&dir, Options::default()) {
+        Err(Error::Corruption(message)) => assert!(message.contains("transaction log"), "{}", message),
+        other => panic!("expected Corruption, got {:?}", other.err()),
+    }
+
+    // A database directory where a file is
+    let file = dir.join("not_a_directory");
+    std::fs::write(&file, b"x").unwrap();
+    let error = Database::open(&file, Options::default()).err().expect("opened a file as a database");
+    assert!(matches!(error, Error::Io(_)), "{:?}", error);
+    assert!(error.source().is_some());
+    assert!(error.to_string().contains("not_a_directory"), "{}", error);
+
+    let options = Options { create_if_missing: false, ..Options::default() };
+    assert!(matches!(Database::open(dir.join("absent"), options), Err(Error::NotFound(_))));
+}
@@ -0,0 +1,170 @@
+//! JSON Lines import and export: the JSON form of each type, import by column name, type
+//! mismatches reported by line, JSON rendering of rows and query results, and COPY.
+
+use std::io::Read;
+use std::path::Path;
+
+use oxidedb::{Column, DataType, Database, Date, Decimal, Error, Options, Timestamp, Value};
+
+mod common;
+use common::TempDir;
+
+fn all_types() -> Vec<Column> {
+    vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("f".to_string(), DataType::FLOAT, false),
+        Column::new("d".to_string(), DataType::DOUBLE, false),
+        Column::new("b".to_string(), DataType::BIGINT, false),
+        Column::new("s".to_string(), DataType::STRING(40), false),
+        Column::new("c".to_string(), DataType::CHAR(4), false),
+        Column::new("flag".to_string(), DataType::BOOLEAN, false),
+        Column::new("day".to_string(), DataType::DATE, false),
+        Column::new("at".to_string(), DataType::TIMESTAMP, false),
+        Column::new("price".to_string(), DataType::DECIMAL(20, 2), false),
+        Column::new("data".to_string(), DataType::BLOB, false),
+    ]
+}
+
+fn open(name: &str) -> (Database, TempDir) {
+    let dir = TempDir::new(name);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("all_types", all_types()).unwrap();
+    db.insert("all_types", 1, vec![
+        Value::INTEGER(1), Value::FLOAT(1.5), Value::DOUBLE(f64::NEG_INFINITY), Value::BIGINT(i64::MAX),
+        Value::STRING("tab\t\"quoted\" \\ é\u{1}".to_string(), 40), Value::CHAR("ab".to_string(), 4),
+        Value::BOOLEAN(true), Value::DATE(Date::from_ymd(2024, 2, 29).unwrap()),
+        Value::TIMESTAMP(Timestamp::parse("2024-02-29 13:45:00.25").unwrap()),
+        Value::DECIMAL(Decimal::parse("123456789012345678.90", 2).unwrap()), Value::BLOB(vec![0, 1, 0xff]),
+    ]).unwrap();
+    (db, dir)
+}
+
+fn write(path: &Path, text: &str) {
+    std::fs::write(path, text).unwrap();
+}
+
+fn rows(db: &Database, table_name: &str) -> Vec<Vec<String>> {
+    db.scan(table_name).unwrap()
+        .iter()
+        .map(|row| row.iter().map(|value| value.to_string()).collect())
+        .collect()
+}
+
+const FIRST_ROW: &str = concat!(
+    r#"{"id":1,"f":1.5,"d":"-inf","b":9223372036854775807,"s":"tab\t\"quoted\" \\ é\u0001","c":"ab","#,
+    r#""flag":true,"day":"2024-02-29","at":"2024-02-29 13:45:00.250000","price":123456789012345678.90,"data":"\\x0001ff"}"#,
+);
+
+#[test]
+fn rows_render_as_json() {
+    let (db, _dir) = open("json-render");
+    assert_eq!(db.select_json("all_types", 1).unwrap().unwrap(), FIRST_ROW);
+    assert_eq!(db.select_json("all_types", 2).unwrap(), None);
+
+    db.insert("all_types", 2, vec![
+        Value::INTEGER(2), Value::FLOAT(f32::NAN), Value::NULL, Value::NULL, Value::NULL, Value::NULL,
+        Value::BOOLEAN(false), Value::NULL, Value::NULL, Value::NULL, Value::NULL,
+    ]).unwrap();
+    let row = db.table("all_types").unwrap().get(2).unwrap().unwrap();
+    assert_eq!(row.to_json(), r#"{"id":2,"f":"NaN","d":null,"b":null,"s":null,"c":null,"flag":false,"day":null,"at":null,"price":null,"data":null}"#);
+    let row = db.query("all_types").select(&["flag", "id"]).first().unwrap().unwrap();
+    assert_eq!(row.to_json(), r#"{"flag":true,"id":1}"#);
+}
+
+#[test]
+fn every_type_round_trips() {
+    let (db, dir) = open("json-types");
+    let large: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+    db.insert("all_types", 2, vec![
+        Value::INTEGER(2), Value::FLOAT(f32::NAN), Value::DOUBLE(0.1), Value::NULL, Value::STRING(String::new(), 40),
+        Value::NULL, Value::BOOLEAN(false), Value::NULL, Value::NULL, Value::NULL, Value::BLOB(large.clone()),
+    ]).unwrap();
+    db.create_table("copy", all_types()).unwrap();
+
+    let file = dir.join("all_types.jsonl");
+    assert_eq!(db.copy_to_json_lines("all_types", &file).unwrap(), 2);
+    assert_eq!(std::fs::read_to_string(&file).unwrap().lines().next().unwrap(), FIRST_ROW);
+    assert_eq!(db.copy_from_json_lines("copy", &file).unwrap(), 2);
+    assert_eq!(rows(&db, "copy"), rows(&db, "all_types"));
+    let mut bytes = Vec::new();
+    db.read_blob("copy", 2, 10).unwrap().unwrap().read_to_end(&mut bytes).unwrap();
+    assert_eq!(bytes, large);
+
+    // A query result, with the spilled BLOB read in
+    let mut out = Vec::new();
+    assert_eq!(db.query("copy").eq("id", Value::INTEGER(2)).select(&["data"]).write_json_lines(&mut out).unwrap(), 1);
+    let expected: String = large.iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(String::from_utf8(out).unwrap(), format!("{{\"data\":\"\\\\x{}\"}}\n", expected));
+}
+
+#[test]
+fn import_by_column_name() {
+    let dir = TempDir::new("json-import");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("people", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("name".to_string(), DataType::STRING(64), false).with_not_null(true),
+        Column::new("city".to_string(), DataType::STRING(32), false).with_default(Value::STRING("nowhere".to_string(), 32)),
+        Column::new("price".to_string(), DataType::DECIMAL(6, 2), false),
+    ]).unwrap();
+    let file = dir.join("people.jsonl");
+    write(&file, concat!(
+        "{\"name\": \"Ann \\ud83d\\ude00\", \"id\": 1}\n",
+        "\n",
+        "  {\"id\":2,\"name\":\"Bob\",\"city\":null,\"price\":\"12.5\"}  \r\n",
+        "{\"id\":3,\"name\":\"Cy\",\"price\":1e1}\n",
+    ));
+    assert_eq!(db.copy_from_json_lines("people", &file).unwrap(), 3);
+    assert_eq!(rows(&db, "people"), vec![
+        vec!["1", "Ann 😀", "nowhere", "NULL"],
+        vec!["2", "Bob", "NULL", "12.50"],
+        vec!["3", "Cy", "nowhere", "10.00"],
+    ]);
+
+    write(&file, concat!(
+        "{\"id\":10,\"name\":\"ok\"}\n",
+        "{\"id\":\"11\",\"name\":\"a\"}\n",
+        "{\"id\":12,\"name\":7}\n",
+        "{\"id\":13.5,\"name\":\"c\"}\n",
+        "{\"id\":14,\"nickname\":\"d\"}\n",
+        "{\"id\":15}\n",
+        "[15]\n",
+        "{\"id\":16,\"name\":\"e\"\n",
+        "{\"id\":17,\"name\":\"f\",\"name\":\"g\"}\n",
+    ));
+    match db.copy_from_json_lines("people", &file) {
+        Err(Error::Import { errors, .. }) => {
+            let lines: Vec<usize> = errors.iter().map(|(line, _)| *line).collect();
+            assert_eq!(lines, (2..=9).collect::<Vec<usize>>(), "{:?}", errors);
+            assert_eq!(errors[0].1, "column 'id': expected a number for INTEGER, found a string");
+            assert_eq!(errors[1].1, "column 'name': expected a string for STRING, found a number");
+            assert!(errors[2].1.contains("Invalid INTEGER"), "{:?}", errors[2]);
+            assert_eq!(errors[3].1, "no column 'nickname'");
+            assert!(errors[4].1.contains("NOT NULL"), "{:?}", errors[4]);
+            assert_eq!(errors[5].1, "expected an object, found an array");
+            assert!(errors[6].1.contains("expected ',' or '}'"), "{:?}", errors[6]);
+            assert!(errors[7].1.contains("more than once"), "{:?}", errors[7]);
+        }
+        other => panic!("expected an import error, got {:?}", other),
+    }
+    assert_eq!(db.scan("people").unwrap().len(), 3);
+
+    // DECIMAL precision is checked when the rows are inserted, after parsing
+    write(&file, "{\"id\":18,\"name\":\"h\",\"price\":12345.67}\n");
+    assert!(matches!(db.copy_from_json_lines("people", &file), Err(Error::ConstraintViolation(_))));
+    assert_eq!(db.scan("people").unwrap().len(), 3);
+
+    // Through COPY
+    let out = dir.join("out.jsonl");
+    let out_path = out.display().to_string();
+    assert_eq!(db.execute_copy(&format!("COPY people TO '{}' WITH (FORMAT json)", out_path)).unwrap(), 3);
+    db.truncate_table("people").unwrap();
+    assert_eq!(db.execute_copy(&format!("COPY people FROM '{}' (FORMAT JSON);", out_path)).unwrap(), 3);
+    assert_eq!(rows(&db, "people")[0], vec!["1", "Ann 😀", "nowhere", "NULL"]);
+    for statement in [
+        format!("COPY people (id) TO '{}' WITH (FORMAT json)", out_path),
+        format!("COPY people TO '{}' WITH (FORMAT json, HEADER)", out_path),
+    ] {
+        assert!(matches!(db.execute_copy(&statement), Err(Error::InvalidOperation(_))), "{}", statement);
+    }
+}
@@ -0,0 +1,126 @@
+//! NULL values: SQL three-valued logic in predicates and queries, NOT NULL columns, and the
+//! per-row null bitmap surviving a restart, including rows with more than eight columns.
+
+use oxidedb::{Column, DataType, Database, Error, Options, Predicate, Value};
+
+mod common;
+use common::TempDir;
+
+/// Eleven nullable columns after the key, so the null bitmap takes two bytes
+const WIDE: i32 = 11;
+
+fn wide_columns() -> Vec<Column> {
+    let mut columns = vec![Column::new("id".to_string(), DataType::INTEGER, true)];
+    columns.extend((0..WIDE).map(|i| Column::new(format!("c{}", i), DataType::INTEGER, false)));
+    columns
+}
+
+/// Column `c<i>` of row `id` is NULL when bit `i` of `id` is set.
+fn wide_row(id: i32) -> Vec<Value> {
+    let mut values = vec![Value::INTEGER(id)];
+    values.extend((0..WIDE).map(|i| if id & (1 << i) != 0 { Value::NULL } else { Value::INTEGER(id * 100 + i) }));
+    values
+}
+
+#[test]
+fn comparisons_with_null_are_unknown() {
+    let null = [Value::NULL];
+    let one = [Value::INTEGER(1)];
+    for predicate in [
+        Predicate::Eq(0, Value::INTEGER(1)),
+        Predicate::Ne(0, Value::INTEGER(1)),
+        Predicate::Lt(0, Value::INTEGER(1)),
+        Predicate::Ge(0, Value::INTEGER(1)),
+    ] {
+        assert_eq!(predicate.evaluate(&null), None, "{:?}", predicate);
+        assert_eq!((!predicate.clone()).evaluate(&null), None, "NOT {:?}", predicate);
+        assert!(!predicate.matches(&null) && !(!predicate).matches(&null));
+    }
+    // A NULL on the right is UNKNOWN too, even against NULL
+    assert_eq!(Predicate::Eq(0, Value::NULL).evaluate(&one), None);
+    assert_eq!(Predicate::Eq(0, Value::NULL).evaluate(&null), None);
+    assert_eq!(Predicate::IsNull(0).evaluate(&null), Some(true));
+    assert_eq!(Predicate::IsNotNull(0).evaluate(&null), Some(false));
+    assert_eq!(Predicate::IsNull(0).evaluate(&one), Some(false));
+
+    // FALSE wins an AND and TRUE wins an OR; otherwise UNKNOWN stays UNKNOWN
+    let unknown = || Predicate::Eq(0, Value::NULL);
+    let truth = || Predicate::IsNotNull(0);
+    let falsehood = || Predicate::IsNull(0);
+    assert_eq!(unknown().and(falsehood()).evaluate(&one), Some(false));
+    assert_eq!(unknown().and(truth()).evaluate(&one), None);
+    assert_eq!(unknown().or(truth()).evaluate(&one), Some(true));
+    assert_eq!(unknown().or(falsehood()).evaluate(&one), None);
+}
+
+#[test]
+fn queries_skip_rows_where_the_filter_is_unknown() {
+    let dir = TempDir::new("null-queries");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("scores", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("score".to_string(), DataType::INTEGER, false),
+    ]).unwrap();
+    for (id, score) in [(1, Value::INTEGER(10)), (2, Value::NULL), (3, Value::INTEGER(30))] {
+        db.insert("scores", id, vec![Value::INTEGER(id), score]).unwrap();
+    }
+    let ids = |query: oxidedb::Query| -> Vec<String> {
+        query.run().unwrap().iter().map(|row| row["id"].to_string()).collect()
+    };
+    assert_eq!(ids(db.query("scores").gt("score", Value::INTEGER(5))), vec!["1", "3"]);
+    assert_eq!(ids(db.query("scores").filter(!Predicate::Gt(1, Value::INTEGER(20)))), vec!["1"]);
+    assert_eq!(ids(db.query("scores").ne("score", Value::INTEGER(10))), vec!["3"]);
+    assert_eq!(ids(db.query("scores").is_null("score")), vec!["2"]);
+    assert_eq!(ids(db.query("scores").is_not_null("score")), vec!["1", "3"]);
+    assert_eq!(ids(db.query("scores").eq("score", Value::NULL)), Vec::<String>::new());
+}
+
+#[test]
+fn not_null_columns_reject_null() {
+    let dir = TempDir::new("null-not-null");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("people", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("name".to_string(), DataType::STRING(16), false).with_not_null(true),
+        Column::new("nickname".to_string(), DataType::STRING(16), false),
+    ]).unwrap();
+    let result = db.insert("people", 1, vec![Value::INTEGER(1), Value::NULL, Value::NULL]);
+    assert!(matches!(result, Err(Error::ConstraintViolation(_))), "{:?}", result);
+    assert!(db.scan("people").unwrap().is_empty());
+    db.insert("people", 1, vec![Value::INTEGER(1), Value::STRING("Ada".to_string(), 16), Value::NULL]).unwrap();
+
+    // A column left out of a named insert is NULL, which a NOT NULL column refuses
+    let people = db.table("people").unwrap();
+    people.insert_named(vec![("id", Value::INTEGER(2)), ("name", Value::STRING("Bo".to_string(), 16))]).unwrap();
+    assert!(people.get(2).unwrap().unwrap()["nickname"].is_null());
+    assert!(people.insert_named(vec![("id", Value::INTEGER(3))]).is_err());
+
+    // Primary keys are always NOT NULL
+    assert!(db.columns("people").unwrap()[0].not_null);
+}
+
+#[test]
+fn null_bitmap_survives_a_restart() {
+    let dir = TempDir::new("null-bitmap");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("wide", wide_columns()).unwrap();
+    // Every pattern of NULLs in the first bitmap byte, and some reaching into the second
+    let ids: Vec<i32> = (0..256).chain([0x100, 0x2ff, 0x7ff, 0x555, 0x2aa]).collect();
+    for id in &ids {
+        db.insert("wide", *id, wide_row(*id)).unwrap();
+    }
+    db.save().unwrap();
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    for id in &ids {
+        let values = db.get("wide", *id).unwrap().unwrap();
+        assert_eq!(values.len(), wide_row(*id).len());
+        for (i, (read, written)) in values.iter().zip(wide_row(*id)).enumerate() {
+            assert_eq!(read.to_string(), written.to_string(), "row {} column {}", id, i);
+            assert_eq!(read.is_null(), written.is_null(), "row {} column {}", id, i);
+        }
+    }
+    let all_null = db.query("wide").is_null("c10").run().unwrap().len();
+    assert_eq!(all_null, ids.iter().filter(|id| *id & (1 << 10) != 0).count());
+}
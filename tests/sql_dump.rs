@@ -0,0 +1,170 @@
+//! SQL dump and restore: the script a small database dumps to, a whole database carried
+//! across through a dump, and scripts restore reads or rejects.
+
+use std::io::Read;
+
+use oxidedb::{Column, DataType, Database, Date, Decimal, Error, Options, Timestamp, Value};
+
+mod common;
+use common::TempDir;
+
+fn dump(db: &Database) -> String {
+    let mut script = Vec::new();
+    db.dump(&mut script).unwrap();
+    String::from_utf8(script).unwrap()
+}
+
+#[test]
+fn dump_writes_a_readable_script() {
+    let dir = TempDir::new("dump-script");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("users", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("name".to_string(), DataType::STRING(100), false).with_not_null(true),
+        Column::new("city".to_string(), DataType::STRING(32), false).with_default(Value::STRING("O'Hare".to_string(), 32)),
+        Column::new("joined".to_string(), DataType::DATE, false),
+    ]).unwrap();
+    db.create_table("log", vec![
+        Column::new("at".to_string(), DataType::TIMESTAMP, false),
+        Column::new("score".to_string(), DataType::DOUBLE, false),
+        Column::new("data".to_string(), DataType::BLOB, false),
+    ]).unwrap();
+    db.insert("users", 2, vec![Value::INTEGER(2), Value::STRING("Bob".to_string(), 100), Value::NULL, Value::NULL]).unwrap();
+    db.insert("users", 1, vec![
+        Value::INTEGER(1), Value::STRING("Ann \"A\"".to_string(), 100), Value::STRING("Oslo".to_string(), 32),
+        Value::DATE(Date::from_ymd(2024, 1, 31).unwrap()),
+    ]).unwrap();
+    db.insert("log", 7, vec![
+        Value::TIMESTAMP(Timestamp::parse("2024-01-31 08:00:00").unwrap()), Value::DOUBLE(f64::NAN), Value::BLOB(vec![0xca, 0xfe]),
+    ]).unwrap();
+
+    assert_eq!(dump(&db), r#"-- OxideDB SQL dump
+BEGIN TRANSACTION;
+
+CREATE TABLE "users" (
+  "id" INTEGER PRIMARY KEY,
+  "name" VARCHAR(100) NOT NULL,
+  "city" VARCHAR(32) DEFAULT 'O''Hare',
+  "joined" DATE
+);
+INSERT INTO "users" ("id", "name", "city", "joined") VALUES (1, 'Ann "A"', 'Oslo', DATE '2024-01-31');
+INSERT INTO "users" ("id", "name", "city", "joined") VALUES (2, 'Bob', NULL, NULL);
+
+CREATE TABLE "log" (
+  "at" TIMESTAMP,
+  "score" DOUBLE PRECISION,
+  "data" BLOB
+);
+-- key: 7
+INSERT INTO "log" ("at", "score", "data") VALUES (TIMESTAMP '2024-01-31 08:00:00', 'NaN', X'cafe');
+
+COMMIT;
+"#);
+}
+
+#[test]
+fn restore_carries_a_database_across() {
+    let dir = TempDir::new("dump-source");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("all_types", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("f".to_string(), DataType::FLOAT, false),
+        Column::new("d".to_string(), DataType::DOUBLE, false),
+        Column::new("b".to_string(), DataType::BIGINT, false),
+        Column::new("s".to_string(), DataType::STRING(40), false),
+        Column::new("c".to_string(), DataType::CHAR(4), false).with_default(Value::CHAR("x".to_string(), 4)),
+        Column::new("flag".to_string(), DataType::BOOLEAN, false).with_default(Value::BOOLEAN(false)),
+        Column::new("price".to_string(), DataType::DECIMAL(12, 3), false).with_default(Value::DECIMAL(Decimal::parse("-1.5", 3).unwrap())),
+        Column::new("data".to_string(), DataType::BLOB, false),
+    ]).unwrap();
+    let large: Vec<u8> = (0..20_000).map(|i| (i % 241) as u8).collect();
+    for id in 0..300 {
+        db.insert("all_types", id, vec![
+            Value::INTEGER(id), Value::FLOAT(id as f32 / 3.0), Value::DOUBLE(-1e300 / (id as f64 + 1.0)), Value::BIGINT(i64::MIN + id as i64),
+            Value::STRING(format!("line\n'{}'", id), 40), Value::CHAR("ab".to_string(), 4), Value::BOOLEAN(id % 2 == 0),
+            Value::DECIMAL(Decimal::parse(&format!("{}.125", id), 3).unwrap()),
+            if id == 5 { Value::BLOB(large.clone()) } else { Value::NULL },
+        ]).unwrap();
+    }
+    for id in (0..300).step_by(7) {
+        assert!(db.delete("all_types", id).unwrap());
+    }
+    // Keys that are not in the row, and a column added later
+    db.create_table("notes", vec![Column::new("text".to_string(), DataType::STRING(20), false)]).unwrap();
+    db.insert("notes", -4, vec![Value::STRING("minus four".to_string(), 20)]).unwrap();
+    db.insert("notes", 9, vec![Value::NULL]).unwrap();
+    db.add_column("notes", Column::new("seen".to_string(), DataType::BOOLEAN, false).with_default(Value::BOOLEAN(true))).unwrap();
+    // An uncommitted write is not in the dump
+    let mut txn = db.begin().unwrap();
+    txn.insert("notes", 10, vec![Value::STRING("pending".to_string(), 20), Value::NULL]).unwrap();
+
+    let script = dir.join("dump.sql");
+    assert_eq!(db.dump(std::fs::File::create(&script).unwrap()).unwrap(), 300 - 43 + 2);
+    txn.rollback().unwrap();
+
+    let copy_dir = TempDir::new("dump-copy");
+    let copy = Database::open(&copy_dir, Options::default()).unwrap();
+    assert_eq!(copy.restore_dump(&script).unwrap(), 300 - 43 + 2);
+    assert_eq!(dump(&copy), std::fs::read_to_string(&script).unwrap());
+    assert_eq!(copy.columns("all_types").unwrap().len(), 9);
+    assert!(copy.get("all_types", 7).unwrap().is_none());
+    assert!(matches!(copy.get("notes", -4).unwrap().unwrap()[1], Value::BOOLEAN(true)));
+    let mut bytes = Vec::new();
+    copy.read_blob("all_types", 5, 8).unwrap().unwrap().read_to_end(&mut bytes).unwrap();
+    assert_eq!(bytes, large);
+
+    // Restoring needs an empty database
+    assert!(matches!(copy.restore_dump(&script), Err(Error::InvalidOperation(_))));
+
+    // And the restored database outlives a reopen
+    drop(copy);
+    let copy = Database::open(&copy_dir, Options::default()).unwrap();
+    assert_eq!(dump(&copy), std::fs::read_to_string(&script).unwrap());
+}
+
+#[test]
+fn restore_reads_hand_written_scripts() {
+    let dir = TempDir::new("dump-hand");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    let script = dir.join("script.sql");
+    std::fs::write(&script, r#"
+        -- Written by hand, in the style of other databases
+        begin;
+        create table items (
+            id int primary key,
+            name varchar(20) not null,
+            price numeric(8, 2) default 0,
+            stock int null
+        );
+        insert into items values (1, 'one', 1.5, -3), (2, 'two', '2.25', null);
+        insert into items (name, id) values ('three', 3);
+        commit;
+    "#).unwrap();
+    assert_eq!(db.restore_dump(&script).unwrap(), 3);
+    let rows: Vec<String> = db.scan("items").unwrap().iter()
+        .map(|row| row.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" "))
+        .collect();
+    assert_eq!(rows, vec!["1 one 1.50 -3", "2 two 2.25 NULL", "3 three 0.00 NULL"]);
+
+    let errors = [
+        ("CREATE TABLE t (id INTEGER PRIMARY KEY);\nINSERT INTO t VALUES ('x');\n", 2, "Invalid INTEGER"),
+        ("CREATE TABLE t (id INTEGER PRIMARY KEY,\n  name TEXT);\n", 2, "unknown type TEXT"),
+        ("CREATE TABLE t (id INTEGER PRIMARY KEY);\nINSERT INTO t VALUES (1\n", 2, "expected ')', found the end of the script"),
+        ("CREATE TABLE t (s VARCHAR(4));\nINSERT INTO t VALUES ('a');\n", 2, "no INTEGER primary key"),
+        ("CREATE TABLE t (id INTEGER PRIMARY KEY);\n\nINSERT INTO t (id, x) VALUES (1, 2);\n", 3, "no column 'x'"),
+        ("DROP TABLE t;\n", 1, "unsupported statement DROP"),
+        ("INSERT INTO t VALUES ('unclosed);\n", 1, "string is not closed"),
+    ];
+    for (i, (text, line, message)) in errors.iter().enumerate() {
+        let dir = TempDir::new(&format!("dump-bad-{}", i));
+        let db = Database::open(&dir, Options::default()).unwrap();
+        std::fs::write(dir.join("bad.sql"), text).unwrap();
+        match db.restore_dump(dir.join("bad.sql")) {
+            Err(Error::Import { errors, .. }) => {
+                assert_eq!(errors[0].0, *line, "{:?}", errors);
+                assert!(errors[0].1.contains(message), "{:?}", errors);
+            }
+            other => panic!("expected an error for {:?}, got {:?}", text, other),
+        }
+    }
+}
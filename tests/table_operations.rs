@@ -0,0 +1,143 @@
+//! DROP TABLE, TRUNCATE TABLE and RENAME TABLE crashed at every write they make. Each goes
+//! through `catalog.journal`, so after a reopen the operation has happened entirely or not
+//! at all, and the tables it did not touch are as they were.
+
+use std::io::Read;
+use std::path::Path;
+
+use oxidedb::{Column, DataType, Database, FaultInjector, Options, Result, Value};
+
+mod common;
+use common::TempDir;
+
+const ROWS: i32 = 20;
+/// Large enough to go to the table's overflow file
+const BLOB_LEN: usize = 10_000;
+/// Torn writes keep up to this many bytes: past a page and its double-write header
+const TORN_LIMIT: usize = 4096 + 64;
+
+fn item(id: i32) -> Vec<Value> {
+    let photo = if id == 0 { Value::BLOB(vec![7; BLOB_LEN]) } else { Value::NULL };
+    vec![Value::INTEGER(id), Value::STRING(format!("item-{}", id), 32), photo]
+}
+
+fn create_items(db: &Database, table_name: &str) {
+    db.create_table(table_name, vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("label".to_string(), DataType::STRING(32), false),
+        Column::new("photo".to_string(), DataType::BLOB, false),
+    ]).unwrap();
+}
+
+/// `items` with rows, an overflow file and a saved index, and `kept` beside it.
+fn setup(dir: &Path) -> Database {
+    let db = Database::open(dir, Options::default()).unwrap();
+    create_items(&db, "items");
+    db.bulk_insert("items", (0..ROWS).map(|id| (id, item(id))).collect()).unwrap();
+    create_items(&db, "kept");
+    for id in 0..3 {
+        db.insert("kept", id, item(id)).unwrap();
+    }
+    db.save().unwrap();
+    db
+}
+
+fn keys(db: &Database, table_name: &str) -> Vec<i32> {
+    db.scan(table_name).unwrap().iter()
+        .map(|row| match row[0] {
+            Value::INTEGER(id) => id,
+            ref other => panic!("unexpected key {:?}", other),
+        })
+        .collect()
+}
+
+fn assert_all_items(db: &Database, table_name: &str, context: &str) {
+    assert_eq!(keys(db, table_name), (0..ROWS).collect::<Vec<_>>(), "{}", context);
+    let mut photo = Vec::new();
+    db.read_blob(table_name, 0, 2).unwrap().expect("row 0 has a BLOB").read_to_end(&mut photo).unwrap();
+    assert_eq!(photo, vec![7; BLOB_LEN], "{}", context);
+}
+
+/// Runs `operation` once to count its writes, then on a fresh database for every one of
+/// them with a crash there. After each crash the database is reopened and handed to `check`.
+fn crash_at_every_write<O, C>(name: &str, operation: O, check: C)
+where
+    O: Fn(&Database) -> Result<()>,
+    C: Fn(&Database, &Path, &str),
+{
+    let dir = TempDir::new(&format!("{}-count", name));
+    let db = setup(&dir);
+    FaultInjector::count_writes();
+    operation(&db).unwrap();
+    let writes = FaultInjector::writes();
+    FaultInjector::disarm();
+    assert!(writes > 0);
+
+    for crash_point in 0..writes {
+        let dir = TempDir::new(&format!("{}-{}", name, crash_point));
+        let db = setup(&dir);
+        let torn_bytes = (crash_point as usize * 997) % TORN_LIMIT;
+        FaultInjector::crash_after(crash_point, torn_bytes);
+        assert!(operation(&db).is_err(), "{}: crash at write {}", name, crash_point);
+        drop(db);
+        FaultInjector::disarm();
+
+        let context = format!("{}: crash at write {} torn after {} bytes", name, crash_point, torn_bytes);
+        let db = Database::open(&dir, Options::default()).unwrap_or_else(|e| panic!("{}: reopen failed: {}", context, e));
+        assert!(!dir.join("catalog.journal").exists(), "{}: journal left behind", context);
+        assert_eq!(keys(&db, "kept"), vec![0, 1, 2], "{}", context);
+        check(&db, &dir, &context);
+    }
+}
+
+#[test]
+fn drop_table_survives_a_crash_at_every_write() {
+    crash_at_every_write("drop", |db| db.drop_table("items"), |db, dir, context| {
+        if db.table_id("items").is_some() {
+            assert_all_items(db, "items", context);
+            return;
+        }
+        for file in ["items.dat", "items.dwb", "items.ovf", "items_btree.idx", "items_btree.stale"] {
+            assert!(!dir.join(file).exists(), "{}: {} left behind", context, file);
+        }
+        assert!(!db.table_names().contains(&"items".to_string()), "{}", context);
+        create_items(db, "items");
+        assert!(keys(db, "items").is_empty(), "{}", context);
+        db.insert("items", 1, item(1)).unwrap();
+    });
+}
+
+#[test]
+fn truncate_table_survives_a_crash_at_every_write() {
+    crash_at_every_write("truncate", |db| db.truncate_table("items"), |db, _dir, context| {
+        assert_eq!(db.columns("items").unwrap().len(), 3, "{}", context);
+        if keys(db, "items").is_empty() {
+            assert!(db.get("items", 0).unwrap().is_none(), "{}", context);
+        } else {
+            assert_all_items(db, "items", context);
+        }
+        db.insert("items", ROWS, item(ROWS)).unwrap();
+        assert!(keys(db, "items").contains(&ROWS), "{}", context);
+    });
+}
+
+#[test]
+fn rename_table_survives_a_crash_at_every_write() {
+    crash_at_every_write("rename", |db| db.rename_table("items", "renamed"), |db, dir, context| {
+        let (name, gone) = match (db.table_id("items"), db.table_id("renamed")) {
+            (Some(_), None) => ("items", "renamed"),
+            (None, Some(_)) => ("renamed", "items"),
+            other => panic!("{}: table ids {:?}", context, other),
+        };
+        assert_all_items(db, name, context);
+        for suffix in [".dat", ".ovf"] {
+            assert!(dir.join(format!("{}{}", name, suffix)).exists(), "{}: {}{} missing", context, name, suffix);
+            assert!(!dir.join(format!("{}{}", gone, suffix)).exists(), "{}: {}{} left behind", context, gone, suffix);
+        }
+        // The table takes writes under its name, and the other name is free
+        db.insert(name, ROWS, item(ROWS)).unwrap();
+        assert_eq!(db.get(name, ROWS).unwrap().unwrap()[1].to_string(), format!("item-{}", ROWS));
+        create_items(db, gone);
+        assert!(keys(db, gone).is_empty(), "{}", context);
+    });
+}
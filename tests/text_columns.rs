@@ -0,0 +1,81 @@
+//! STRING(n) and CHAR(n) columns: declared lengths checked at CREATE and ALTER, values longer
+//! than their column rejected or truncated, and CHAR values padded on disk.
+
+use oxidedb::{Column, DataType, Database, Error, Options, StringOverflowPolicy, Value};
+
+mod common;
+use common::TempDir;
+
+fn text_table(db: &Database) {
+    db.create_table("names", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("name".to_string(), DataType::STRING(8), false),
+        Column::new("code".to_string(), DataType::CHAR(4), false),
+    ]).unwrap();
+}
+
+#[test]
+fn lengths_must_be_positive_and_fit_a_page() {
+    let dir = TempDir::new("text-lengths");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    text_table(&db);
+    for column_type in [DataType::CHAR(-1), DataType::STRING(-5), DataType::STRING(0), DataType::CHAR(0), DataType::CHAR(4096), DataType::STRING(i64::MAX)] {
+        let column = Column::new("text".to_string(), column_type.clone(), false);
+        let created = db.create_table("bad", vec![Column::new("id".to_string(), DataType::INTEGER, true), column.clone()]);
+        assert!(matches!(created, Err(Error::SchemaMismatch(_))), "{:?}: {:?}", column_type, created);
+        let added = db.add_column("names", column);
+        assert!(matches!(added, Err(Error::SchemaMismatch(_))), "{:?}: {:?}", column_type, added);
+    }
+    assert!(db.table_id("bad").is_none());
+    assert_eq!(db.columns("names").unwrap().len(), 3);
+
+    // The longest length allowed holds a value of that length
+    db.create_table("long", vec![Column::new("text".to_string(), DataType::STRING(3987), false)]).unwrap();
+    let text = "x".repeat(3987);
+    db.insert("long", 1, vec![Value::STRING(text.clone(), 3987)]).unwrap();
+    assert_eq!(db.get("long", 1).unwrap().unwrap()[0].to_string(), text);
+    assert!(matches!(
+        db.create_table("longer", vec![Column::new("text".to_string(), DataType::STRING(3988), false)]),
+        Err(Error::SchemaMismatch(_))
+    ));
+}
+
+#[test]
+fn long_values_are_rejected_or_truncated() {
+    let dir = TempDir::new("text-overflow");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    text_table(&db);
+    let row = |name: &str, code: &str| vec![Value::INTEGER(1), Value::STRING(name.to_string(), 8), Value::CHAR(code.to_string(), 4)];
+    assert!(matches!(db.insert("names", 1, row("too long!", "ab")), Err(Error::ConstraintViolation(_))));
+    assert!(matches!(db.insert("names", 1, row("ok", "abcde")), Err(Error::ConstraintViolation(_))));
+    assert!(db.scan("names").unwrap().is_empty());
+    // Exactly the declared number of bytes, multi-byte characters included
+    db.insert("names", 1, row("ééééé", "ab")).unwrap_err();
+    db.insert("names", 1, row("éééé", "€a")).unwrap();
+    drop(db);
+
+    let options = Options { string_overflow: StringOverflowPolicy::Truncate, ..Options::default() };
+    let db = Database::open(&dir, options).unwrap();
+    db.insert("names", 2, vec![Value::INTEGER(2), Value::STRING("truncated here".to_string(), 8), Value::CHAR("€€".to_string(), 4)]).unwrap();
+    let values = db.get("names", 2).unwrap().unwrap();
+    assert_eq!(values[1].to_string(), "truncate");
+    // Cut at a character boundary, never inside one
+    assert_eq!(values[2].to_string(), "€");
+}
+
+#[test]
+fn char_values_are_padded_on_disk_and_read_back_unpadded() {
+    let dir = TempDir::new("text-char");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    text_table(&db);
+    for (id, code) in [(1, ""), (2, "a"), (3, "abcd")] {
+        db.insert("names", id, vec![Value::INTEGER(id), Value::NULL, Value::CHAR(code.to_string(), 4)]).unwrap();
+    }
+    db.save().unwrap();
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    let codes: Vec<String> = db.scan("names").unwrap().iter().map(|row| row[2].to_string()).collect();
+    assert_eq!(codes, vec!["", "a", "abcd"]);
+    assert!(matches!(&db.get("names", 2).unwrap().unwrap()[2], Value::CHAR(code, 4) if code == "a"));
+}
@@ -0,0 +1,331 @@
+//! Transactions over multi-version rows: snapshots that ignore later commits, rollback and
+//! savepoints, write conflicts between concurrent writers, row locks with their timeouts and deadlock
+//! victims, vacuum of dead versions, and transactions cut short by a crash.
+
+use std::sync::Barrier;
+use std::thread;
+use std::time::Duration;
+
+use oxidedb::{Column, DataType, Database, Error, FaultInjector, Options, Value};
+
+mod common;
+use common::TempDir;
+
+fn open(name: &str) -> (Database, TempDir) {
+    let dir = TempDir::new(name);
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("accounts", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("balance".to_string(), DataType::INTEGER, false),
+    ]).unwrap();
+    (db, dir)
+}
+
+fn account(id: i32, balance: i32) -> Vec<Value> {
+    vec![Value::INTEGER(id), Value::INTEGER(balance)]
+}
+
+fn balance(row: Option<Vec<Value>>) -> Option<i32> {
+    match row?.get(1) {
+        Some(Value::INTEGER(balance)) => Some(*balance),
+        other => panic!("unexpected balance {:?}", other),
+    }
+}
+
+#[test]
+fn snapshot_ignores_later_commits() {
+    let (db, _dir) = open("txn-snapshot");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+
+    let reader = db.begin().unwrap();
+    let mut writer = db.begin().unwrap();
+    writer.update("accounts", 1, account(1, 50)).unwrap();
+    writer.insert("accounts", 2, account(2, 10)).unwrap();
+
+    // Uncommitted writes are only visible to their own transaction
+    assert_eq!(balance(writer.get("accounts", 1).unwrap()), Some(50));
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(100));
+    assert!(db.get("accounts", 2).unwrap().is_none());
+
+    writer.commit().unwrap();
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(50));
+    assert_eq!(db.scan("accounts").unwrap().len(), 2);
+
+    // The reader began before the commit and keeps seeing the old state
+    assert_eq!(balance(reader.get("accounts", 1).unwrap()), Some(100));
+    let rows = reader.scan("accounts").unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(balance(rows.into_iter().next()), Some(100));
+    reader.commit().unwrap();
+}
+
+#[test]
+fn rollback_restores_rows() {
+    let (db, _dir) = open("txn-rollback");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+
+    let mut txn = db.begin().unwrap();
+    assert!(txn.update("accounts", 1, account(1, 0)).unwrap());
+    txn.insert("accounts", 2, account(2, 100)).unwrap();
+    assert!(txn.delete("accounts", 2).unwrap());
+    txn.insert("accounts", 2, account(2, 200)).unwrap();
+    txn.rollback().unwrap();
+
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(100));
+    assert!(db.get("accounts", 2).unwrap().is_none());
+
+    // Dropping an unfinished transaction rolls it back too
+    {
+        let mut txn = db.begin().unwrap();
+        txn.delete("accounts", 1).unwrap();
+    }
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(100));
+    db.insert("accounts", 2, account(2, 5)).unwrap();
+    assert_eq!(db.scan("accounts").unwrap().len(), 2);
+}
+
+#[test]
+fn rollback_to_savepoint_undoes_only_later_writes() {
+    let (db, _dir) = open("txn-savepoint");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+
+    let mut txn = db.begin().unwrap();
+    txn.update("accounts", 1, account(1, 90)).unwrap();
+    txn.savepoint("batch").unwrap();
+    for id in 2..5 {
+        txn.insert("accounts", id, account(id, 0)).unwrap();
+    }
+    txn.update("accounts", 1, account(1, 80)).unwrap();
+    // A failed sub-batch: the duplicate leaves the transaction usable
+    assert!(matches!(txn.insert("accounts", 3, account(3, 0)), Err(Error::DuplicateKey { .. })));
+    txn.rollback_to("batch").unwrap();
+
+    assert_eq!(balance(txn.get("accounts", 1).unwrap()), Some(90));
+    assert_eq!(txn.scan("accounts").unwrap().len(), 1);
+
+    // Retry the sub-batch; the savepoint is still there after a rollback to it
+    txn.savepoint("inner").unwrap();
+    txn.insert("accounts", 2, account(2, 20)).unwrap();
+    txn.rollback_to("batch").unwrap();
+    assert!(matches!(txn.release("inner"), Err(Error::NotFound(_))));
+    txn.insert("accounts", 2, account(2, 10)).unwrap();
+    txn.release("batch").unwrap();
+    assert!(matches!(txn.rollback_to("batch"), Err(Error::NotFound(_))));
+    txn.commit().unwrap();
+
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(90));
+    assert_eq!(balance(db.get("accounts", 2).unwrap()), Some(10));
+    assert!(db.get("accounts", 3).unwrap().is_none());
+    assert_eq!(db.scan("accounts").unwrap().len(), 2);
+}
+
+#[test]
+fn concurrent_writers_conflict() {
+    let (db, _dir) = open("txn-conflict");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+
+    // A row changed by a transaction that committed after our snapshot
+    let mut first = db.begin().unwrap();
+    let mut second = db.begin().unwrap();
+    first.update("accounts", 1, account(1, 90)).unwrap();
+    first.commit().unwrap();
+    assert!(matches!(second.update("accounts", 1, account(1, 80)), Err(Error::WriteConflict { .. })));
+    second.rollback().unwrap();
+
+    // A duplicate key is not a conflict
+    assert!(matches!(db.insert("accounts", 1, account(1, 0)), Err(Error::DuplicateKey { .. })));
+    assert!(db.delete("accounts", 1).unwrap());
+    db.insert("accounts", 1, account(1, 70)).unwrap();
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(70));
+}
+
+#[test]
+fn row_locks_wait_and_time_out() {
+    let (db, _dir) = open("txn-locks");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+    db.insert("accounts", 2, account(2, 100)).unwrap();
+
+    let mut first = db.begin().unwrap();
+    let mut second = db.begin().unwrap();
+    second.set_lock_timeout(Some(Duration::from_millis(50)));
+    first.update("accounts", 1, account(1, 90)).unwrap();
+
+    // Another row of the same table is free; the locked one times out
+    assert!(second.update("accounts", 2, account(2, 110)).unwrap());
+    assert!(matches!(second.delete("accounts", 1), Err(Error::LockTimeout { key: 1, .. })));
+    assert!(matches!(second.get_for_share("accounts", 1), Err(Error::LockTimeout { .. })));
+    second.commit().unwrap();
+
+    // A writer waits for the lock holder to finish rather than failing
+    thread::scope(|scope| {
+        let waiter = scope.spawn(|| db.delete("accounts", 1));
+        thread::sleep(Duration::from_millis(50));
+        first.commit().unwrap();
+        assert!(waiter.join().unwrap().unwrap());
+    });
+    assert!(db.get("accounts", 1).unwrap().is_none());
+    assert_eq!(balance(db.get("accounts", 2).unwrap()), Some(110));
+}
+
+#[test]
+fn deadlock_rolls_back_the_youngest() {
+    let (db, _dir) = open("txn-deadlock");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+    db.insert("accounts", 2, account(2, 100)).unwrap();
+
+    let older = db.begin().unwrap();
+    let younger = db.begin().unwrap();
+    let barrier = Barrier::new(2);
+    thread::scope(|scope| {
+        let barrier = &barrier;
+        let older = scope.spawn(move || {
+            let mut txn = older;
+            txn.update("accounts", 1, account(1, 50)).unwrap();
+            barrier.wait();
+            txn.update("accounts", 2, account(2, 150)).unwrap();
+            txn.commit().unwrap();
+        });
+        let younger = scope.spawn(move || {
+            let mut txn = younger;
+            txn.update("accounts", 2, account(2, 0)).unwrap();
+            barrier.wait();
+            let result = txn.update("accounts", 1, account(1, 200));
+            assert!(matches!(result, Err(Error::Deadlock { .. })), "{:?}", result.err());
+            // The victim is already rolled back
+            assert!(matches!(txn.insert("accounts", 3, account(3, 0)), Err(Error::InvalidOperation(_))));
+        });
+        older.join().unwrap();
+        younger.join().unwrap();
+    });
+
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(50));
+    assert_eq!(balance(db.get("accounts", 2).unwrap()), Some(150));
+    assert!(db.get("accounts", 3).unwrap().is_none());
+}
+
+#[test]
+fn failed_rollback_still_ends_the_transaction() {
+    let (db, dir) = open("txn-failed-rollback");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+
+    let mut txn = db.begin().unwrap();
+    txn.update("accounts", 1, account(1, 0)).unwrap();
+    txn.insert("accounts", 2, account(2, 100)).unwrap();
+    FaultInjector::crash_after(0, 0);
+    let result = txn.rollback();
+    FaultInjector::disarm();
+    assert!(matches!(result, Err(Error::Io(_))), "{:?}", result);
+
+    // Its locks are released and its writes count as rolled back, though they were not undone
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(100));
+    assert!(db.get("accounts", 2).unwrap().is_none());
+    let mut next = db.begin().unwrap();
+    next.set_lock_timeout(Some(Duration::from_millis(50)));
+    assert!(next.update("accounts", 1, account(1, 90)).unwrap());
+    next.insert("accounts", 2, account(2, 10)).unwrap();
+    next.commit().unwrap();
+    db.truncate_table("accounts").unwrap();
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+
+    let mut txn = db.begin().unwrap();
+    txn.insert("accounts", 3, account(3, 100)).unwrap();
+    FaultInjector::crash_after(0, 0);
+    assert!(txn.rollback().is_err());
+    FaultInjector::disarm();
+    db.save().unwrap();
+    drop(db);
+
+    // The failed rollback is not logged as finished, so it stays rolled back after a reopen
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(100));
+    assert!(db.get("accounts", 3).unwrap().is_none());
+}
+
+#[test]
+fn vacuum_keeps_versions_open_snapshots_need() {
+    let (db, _dir) = open("txn-vacuum");
+    db.insert("accounts", 1, account(1, 0)).unwrap();
+    let reader = db.begin().unwrap();
+    for step in 1..=5 {
+        let mut txn = db.begin().unwrap();
+        txn.update("accounts", 1, account(1, step)).unwrap();
+        txn.commit().unwrap();
+    }
+
+    assert_eq!(db.vacuum("accounts").unwrap(), 0);
+    assert_eq!(balance(reader.get("accounts", 1).unwrap()), Some(0));
+    reader.commit().unwrap();
+
+    assert_eq!(db.vacuum("accounts").unwrap(), 5);
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(5));
+    assert!(db.delete("accounts", 1).unwrap());
+    assert_eq!(db.vacuum("accounts").unwrap(), 1);
+    assert!(db.get("accounts", 1).unwrap().is_none());
+    assert!(db.scan("accounts").unwrap().is_empty());
+    db.insert("accounts", 1, account(1, 7)).unwrap();
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(7));
+}
+
+#[test]
+fn unfinished_transaction_is_ignored_after_reopen() {
+    let (db, dir) = open("txn-crash");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+
+    let mut txn = db.begin().unwrap();
+    txn.update("accounts", 1, account(1, 0)).unwrap();
+    txn.insert("accounts", 2, account(2, 100)).unwrap();
+    // Neither committed nor rolled back, as if the process died
+    std::mem::forget(txn);
+    db.save().unwrap();
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(100));
+    assert!(db.get("accounts", 2).unwrap().is_none());
+    db.insert("accounts", 2, account(2, 1)).unwrap();
+    assert_eq!(db.scan("accounts").unwrap().len(), 2);
+    assert_eq!(db.vacuum("accounts").unwrap(), 2);
+    assert_eq!(db.scan("accounts").unwrap().len(), 2);
+}
+
+#[test]
+fn failed_rollback_to_savepoint_leaves_only_rollback() {
+    let (db, dir) = open("txn-failed-savepoint");
+    db.insert("accounts", 1, account(1, 100)).unwrap();
+
+    let mut txn = db.begin().unwrap();
+    txn.update("accounts", 1, account(1, 50)).unwrap();
+    txn.savepoint("before").unwrap();
+    txn.insert("accounts", 2, account(2, 100)).unwrap();
+    txn.insert("accounts", 3, account(3, 100)).unwrap();
+    FaultInjector::crash_after(0, 0);
+    let result = txn.rollback_to("before");
+    FaultInjector::disarm();
+    assert!(matches!(result, Err(Error::Io(_))), "{:?}", result);
+
+    // Nothing but a rollback is accepted any more
+    assert!(matches!(txn.insert("accounts", 4, account(4, 1)), Err(Error::InvalidOperation(_))));
+    assert!(matches!(txn.rollback_to("before"), Err(Error::InvalidOperation(_))));
+    assert!(matches!(txn.savepoint("after"), Err(Error::InvalidOperation(_))));
+    txn.rollback().unwrap();
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(100));
+    assert!(db.get("accounts", 2).unwrap().is_none());
+    assert!(db.get("accounts", 3).unwrap().is_none());
+
+    // Commit is refused too, and the writes are rolled back when the transaction is dropped
+    let mut txn = db.begin().unwrap();
+    txn.savepoint("before").unwrap();
+    txn.insert("accounts", 2, account(2, 100)).unwrap();
+    FaultInjector::crash_after(0, 0);
+    assert!(txn.rollback_to("before").is_err());
+    FaultInjector::disarm();
+    assert!(matches!(txn.commit(), Err(Error::InvalidOperation(_))));
+    assert!(db.get("accounts", 2).unwrap().is_none());
+    db.save().unwrap();
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    assert_eq!(balance(db.get("accounts", 1).unwrap()), Some(100));
+    assert!(db.get("accounts", 2).unwrap().is_none());
+    assert!(db.get("accounts", 3).unwrap().is_none());
+}
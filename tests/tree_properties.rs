@@ -0,0 +1,375 @@
+//! Model checking of the index tree against `std::collections::BTreeMap`. Random sequences
+//! of inserts, removes, searches and range scans go to the tree and to the map; every answer
+//! must agree, and the tree's structure must stay valid after every step (sorted keys,
+//! separators bounding their subtrees, all leaves at one depth, leaf chain in key order).
+//! A failing sequence is shrunk to a minimal one before it is reported.
+//!
+//! `OXIDEDB_PROPTEST_SEED` replays the cases of one seed; `OXIDEDB_PROPTEST_CASES` sets how
+//! many there are.
+
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+
+use oxidedb::{IndexKey, IndexTree, RowLocation};
+
+mod common;
+use common::Rng;
+
+const DEFAULT_SEED: u64 = 0x0d15ea5e;
+const DEFAULT_CASES: u64 = 300;
+const MAX_OPERATIONS: u64 = 300;
+
+#[derive(Clone, Copy, Debug)]
+enum Operation {
+    Insert(i32, i64),
+    Remove(i32),
+    Search(i32),
+    /// Keys from the first to the second, both included
+    Range(i32, i32),
+}
+
+/// How the keys of a case are picked
+#[derive(Clone, Copy, Debug)]
+enum Keys {
+    /// Uniform over a space small enough to hit existing keys often
+    Random(i32),
+    Ascending,
+    Descending,
+}
+
+fn generate(rng: &mut Rng) -> Vec<Operation> {
+    let keys = match rng.below(4) {
+        0 => Keys::Ascending,
+        1 => Keys::Descending,
+        2 => Keys::Random(16),
+        _ => Keys::Random(1000),
+    };
+    let len = 1 + rng.below(MAX_OPERATIONS);
+    let mut counter = 0;
+    let mut next_key = |rng: &mut Rng| match keys {
+        Keys::Random(space) => rng.below(space as u64) as i32 - space / 4,
+        Keys::Ascending => {
+            counter += 1;
+            counter
+        }
+        Keys::Descending => {
+            counter -= 1;
+            counter
+        }
+    };
+    (0..len)
+        .map(|_| {
+            let key = next_key(rng);
+            match rng.below(10) {
+                0..=5 => Operation::Insert(key, rng.below(1 << 20) as i64),
+                6 | 7 => Operation::Remove(key),
+                8 => Operation::Search(key),
+                _ => Operation::Range(key.min(key / 2), key.max(key / 2) + rng.below(50) as i32),
+            }
+        })
+        .collect()
+}
+
+/// One tree implementation behind a common interface. Every location stores the page id
+/// only; the slot mirrors it so a location is easy to check.
+trait Subject {
+    const NAME: &'static str;
+
+    fn new() -> Self;
+    /// Adds the key or points it at `page`.
+    fn insert(&mut self, key: i32, page: i64);
+    fn remove(&mut self, key: i32) -> bool;
+    fn search(&self, key: i32) -> Option<i64>;
+    fn range(&self, low: i32, high: i32) -> Vec<(i32, i64)>;
+    fn entries(&self) -> Vec<(i32, i64)>;
+    fn check(&self) -> Result<(), String>;
+}
+
+fn slot(page: i64) -> i32 {
+    (page % 97) as i32
+}
+
+fn location_page(page_id: i64, offset: i32) -> i64 {
+    assert_eq!(offset, slot(page_id), "location of page {} has slot {}", page_id, offset);
+    page_id
+}
+
+impl Subject for IndexTree<i32> {
+    const NAME: &'static str = "IndexTree";
+
+    fn new() -> Self {
+        IndexTree::new()
+    }
+
+    fn insert(&mut self, key: i32, page: i64) {
+        IndexTree::insert(self, Some(Box::new(IndexKey::new(key, Some(Box::new(RowLocation::new(page, slot(page))))))));
+    }
+
+    fn remove(&mut self, key: i32) -> bool {
+        IndexTree::remove(self, &key)
+    }
+
+    fn search(&self, key: i32) -> Option<i64> {
+        IndexTree::search(self, &key).map(|data| location_page(data.page_id, data.offset))
+    }
+
+    fn range(&self, low: i32, high: i32) -> Vec<(i32, i64)> {
+        IndexTree::range(self, &low, &high).into_iter().map(index_entry).collect()
+    }
+
+    fn entries(&self) -> Vec<(i32, i64)> {
+        IndexTree::entries(self).into_iter().map(index_entry).collect()
+    }
+
+    fn check(&self) -> Result<(), String> {
+        self.check_invariants().map_err(|e| e.to_string())
+    }
+}
+
+fn index_entry(entry: IndexKey<i32>) -> (i32, i64) {
+    let data = entry.data.expect("entry without a location");
+    (entry.key, location_page(data.page_id, data.offset))
+}
+
+/// Runs the operations on a new tree and the model. Returns the first disagreement or
+/// broken invariant; a panic in the tree counts as one.
+fn run<S: Subject>(operations: &[Operation]) -> Result<(), String> {
+    run_on(S::new(), BTreeMap::new(), operations)
+}
+
+/// Runs the operations on a tree and a model holding the same entries.
+fn run_on<S: Subject>(mut tree: S, mut model: BTreeMap<i32, i64>, operations: &[Operation]) -> Result<(), String> {
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| -> Result<(), String> {
+        if let Err(broken) = tree.check() {
+            return Err(format!("before the first step: {}", broken));
+        }
+        for (step, operation) in operations.iter().enumerate() {
+            let fail = |what: String| Err(format!("step {} ({:?}): {}", step, operation, what));
+            match *operation {
+                Operation::Insert(key, page) => {
+                    tree.insert(key, page);
+                    model.insert(key, page);
+                }
+                Operation::Remove(key) => {
+                    let removed = tree.remove(key);
+                    if removed != model.remove(&key).is_some() {
+                        return fail(format!("remove returned {}", removed));
+                    }
+                }
+                Operation::Search(key) => {
+                    let found = tree.search(key);
+                    if found != model.get(&key).copied() {
+                        return fail(format!("search found {:?}, expected {:?}", found, model.get(&key)));
+                    }
+                }
+                Operation::Range(low, high) => {
+                    let found = tree.range(low, high);
+                    let expected: Vec<(i32, i64)> = model.range(low..=high).map(|(k, v)| (*k, *v)).collect();
+                    if found != expected {
+                        return fail(format!("range found {:?}, expected {:?}", found, expected));
+                    }
+                }
+            }
+            if let Err(broken) = tree.check() {
+                return fail(broken);
+            }
+        }
+        let entries = tree.entries();
+        let expected: Vec<(i32, i64)> = model.into_iter().collect();
+        if entries != expected {
+            return Err(format!("tree holds {:?}, expected {:?}", entries, expected));
+        }
+        Ok(())
+    }));
+    match outcome {
+        Ok(result) => result,
+        Err(panic) => Err(format!("panicked: {}", panic_message(&panic))),
+    }
+}
+
+fn panic_message(panic: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Makes a failing case smaller while it keeps failing: first by dropping runs of
+/// operations, halving the run length down to single operations, then by moving keys and
+/// pages towards zero.
+fn shrink<S: Subject>(mut operations: Vec<Operation>) -> Vec<Operation> {
+    let mut chunk = operations.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        while start < operations.len() {
+            let mut candidate = operations.clone();
+            candidate.drain(start..(start + chunk).min(operations.len()));
+            if run::<S>(&candidate).is_err() {
+                operations = candidate;
+            } else {
+                start += chunk;
+            }
+        }
+        chunk /= 2;
+    }
+
+    loop {
+        let mut smaller = false;
+        for i in 0..operations.len() {
+            for simpler in simplifications(operations[i]) {
+                let mut candidate = operations.clone();
+                candidate[i] = simpler;
+                if run::<S>(&candidate).is_err() {
+                    operations = candidate;
+                    smaller = true;
+                    break;
+                }
+            }
+        }
+        if !smaller {
+            return operations;
+        }
+    }
+}
+
+fn simpler_values(value: i64) -> Vec<i64> {
+    match value {
+        0 => Vec::new(),
+        _ => vec![0, value / 2, value - value.signum()],
+    }
+}
+
+fn simplifications(operation: Operation) -> Vec<Operation> {
+    match operation {
+        Operation::Insert(key, page) => simpler_values(key as i64).into_iter()
+            .map(|key| Operation::Insert(key as i32, page))
+            .chain(simpler_values(page).into_iter().map(|page| Operation::Insert(key, page)))
+            .collect(),
+        Operation::Remove(key) => simpler_values(key as i64).into_iter().map(|key| Operation::Remove(key as i32)).collect(),
+        Operation::Search(key) => simpler_values(key as i64).into_iter().map(|key| Operation::Search(key as i32)).collect(),
+        Operation::Range(low, high) => simpler_values(low as i64).into_iter()
+            .filter(|low| *low <= high as i64)
+            .map(|low| Operation::Range(low as i32, high))
+            .chain(simpler_values(high as i64).into_iter()
+                .filter(|high| *high >= low as i64)
+                .map(|high| Operation::Range(low, high as i32)))
+            .collect(),
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name).ok().map(|value| {
+        let value = value.trim_start_matches("0x");
+        u64::from_str_radix(value, 16).unwrap_or_else(|_| panic!("{} must be a hexadecimal number", name))
+    })
+}
+
+/// Checks the tree against the model on many random cases; the first failure is shrunk and
+/// reported with the seed that replays it.
+fn check_against_model<S: Subject>() {
+    let seed = env_u64("OXIDEDB_PROPTEST_SEED").unwrap_or(DEFAULT_SEED);
+    let cases = env_u64("OXIDEDB_PROPTEST_CASES").unwrap_or(DEFAULT_CASES);
+    let mut rng = Rng::new(seed ^ S::NAME.len() as u64);
+    for case in 0..cases {
+        let operations = generate(&mut rng);
+        if let Err(error) = run::<S>(&operations) {
+            let minimal = shrink::<S>(operations);
+            let error = run::<S>(&minimal).err().unwrap_or(error);
+            panic!(
+                "{} disagrees with BTreeMap in case {} of seed {:#x}: {}\nminimal operations: {:?}",
+                S::NAME, case, seed, error, minimal
+            );
+        }
+    }
+}
+
+#[test]
+fn index_tree_matches_btreemap() {
+    check_against_model::<IndexTree<i32>>();
+}
+
+/// Trees built bottom-up hold their entries, keep every invariant, and take further changes
+/// like trees built by inserts. Sizes around multiples of the node capacity come first.
+#[test]
+fn bulk_loaded_tree_matches_btreemap() {
+    let seed = env_u64("OXIDEDB_PROPTEST_SEED").unwrap_or(DEFAULT_SEED);
+    let mut rng = Rng::new(seed);
+    let sizes = [0, 1, 2, 31, 32, 33, 34, 64, 65, 66, 1056, 1088, 1089, 1090, 2178, 5000];
+    let random_sizes: Vec<u64> = (0..20).map(|_| rng.below(3000)).collect();
+    for size in sizes.into_iter().chain(random_sizes) {
+        let mut model = BTreeMap::new();
+        let mut key = rng.below(100) as i32 - 50;
+        for _ in 0..size {
+            key += 1 + rng.below(5) as i32;
+            model.insert(key, rng.below(1 << 20) as i64);
+        }
+        let tree = IndexTree::bulk_load(model.iter()
+            .map(|(key, page)| IndexKey::new(*key, Some(Box::new(RowLocation::new(*page, slot(*page)))))))
+            .unwrap();
+        let operations = generate(&mut rng);
+        if let Err(error) = run_on(tree, model, &operations) {
+            panic!("tree bulk loaded with {} entries, seed {:#x}: {}\noperations: {:?}", size, seed, error, operations);
+        }
+    }
+}
+
+/// Keys out of order are refused.
+#[test]
+fn bulk_load_refuses_unsorted_keys() {
+    let entry = |key: i32| IndexKey::new(key, Some(Box::new(RowLocation::new(0, 0))));
+    assert!(IndexTree::bulk_load(vec![entry(1), entry(3), entry(2)]).is_err());
+    assert!(IndexTree::bulk_load(vec![entry(1), entry(1)]).is_err());
+}
+
+/// A failing case must shrink to something a person can read.
+#[test]
+fn shrinking_finds_a_minimal_case() {
+    struct Broken(BTreeMap<i32, i64>);
+
+    impl Subject for Broken {
+        const NAME: &'static str = "Broken";
+
+        fn new() -> Self {
+            Broken(BTreeMap::new())
+        }
+
+        /// Loses every key above 100
+        fn insert(&mut self, key: i32, page: i64) {
+            if key <= 100 {
+                self.0.insert(key, page);
+            }
+        }
+
+        fn remove(&mut self, key: i32) -> bool {
+            self.0.remove(&key).is_some()
+        }
+
+        fn search(&self, key: i32) -> Option<i64> {
+            self.0.get(&key).copied()
+        }
+
+        fn range(&self, low: i32, high: i32) -> Vec<(i32, i64)> {
+            self.0.range(low..=high).map(|(k, v)| (*k, *v)).collect()
+        }
+
+        fn entries(&self) -> Vec<(i32, i64)> {
+            self.0.iter().map(|(k, v)| (*k, *v)).collect()
+        }
+
+        fn check(&self) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    let mut rng = Rng::new(7);
+    let failing = (0..)
+        .map(|_| generate(&mut rng))
+        .find(|operations| run::<Broken>(operations).is_err())
+        .unwrap();
+    let minimal = shrink::<Broken>(failing);
+    assert_eq!(minimal.len(), 1, "{:?}", minimal);
+    assert!(matches!(minimal[0], Operation::Insert(101, 0)), "{:?}", minimal);
+}
@@ -0,0 +1,159 @@
+//! BOOLEAN, DATE, TIMESTAMP and DECIMAL values: parsing, formatting, the range of years a
+//! date or timestamp can hold, DECIMAL rounding to its column's scale and precision, and
+//! values of each type read back after a restart.
+
+use oxidedb::{Column, DataType, Database, Date, Decimal, Error, Options, Predicate, Timestamp, Value};
+
+mod common;
+use common::TempDir;
+
+#[test]
+fn dates_and_timestamps_cover_years_1_to_9999() {
+    assert_eq!(Date::parse("0001-01-01").unwrap().to_string(), "0001-01-01");
+    assert_eq!(Date::parse("9999-12-31").unwrap().to_string(), "9999-12-31");
+    assert_eq!(Date::from_ymd(1, 1, 1).unwrap().to_ymd(), (1, 1, 1));
+    let first = Timestamp::parse("0001-01-01 00:00:00").unwrap();
+    let last = Timestamp::parse("9999-12-31 23:59:59.999999").unwrap();
+    assert_eq!(first.to_string(), "0001-01-01 00:00:00");
+    assert_eq!(last.to_string(), "9999-12-31 23:59:59.999999");
+    assert!(first < last);
+
+    for text in ["0000-12-31", "10000-01-01"] {
+        let error = Date::parse(text).unwrap_err();
+        assert!(error.contains("out of range"), "{}: {}", text, error);
+    }
+    assert!(Date::from_ymd(-5, 1, 1).is_err());
+    assert!(Date::from_ymd(i64::MAX, 1, 1).is_err());
+}
+
+#[test]
+fn timestamps_far_out_of_range_are_errors() {
+    // Microseconds since 1970 for these overflow an i64; they must fail, not panic
+    for text in ["999999-01-01 00:00:00", "292278-01-01 00:00:00", "10000-01-01T00:00:00Z", "99999999999999999999-01-01"] {
+        assert!(Timestamp::parse(text).is_err(), "{}", text);
+        assert!(DataType::TIMESTAMP.parse_value(text).is_err(), "{}", text);
+    }
+    assert!(DataType::DATE.parse_value("999999-01-01").is_err());
+}
+
+#[test]
+fn decimals_compare_with_floating_point_values() {
+    let dir = TempDir::new("types-decimal-compare");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("prices", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("price".to_string(), DataType::DECIMAL(8, 2), false),
+    ]).unwrap();
+    for (id, price) in [(1, "0.75"), (2, "1.50"), (3, "2.25")] {
+        db.insert("prices", id, vec![Value::INTEGER(id), Value::DECIMAL(Decimal::parse(price, 2).unwrap())]).unwrap();
+    }
+    let ids = |query: oxidedb::Query| -> Vec<String> {
+        query.run().unwrap().iter().map(|row| row["id"].to_string()).collect()
+    };
+    assert_eq!(ids(db.query("prices").gt("price", Value::DOUBLE(1.5))), vec!["3"]);
+    assert_eq!(ids(db.query("prices").ge("price", Value::FLOAT(1.5))), vec!["2", "3"]);
+    assert_eq!(ids(db.query("prices").lt("price", Value::DOUBLE(1.0))), vec!["1"]);
+    assert_eq!(ids(db.query("prices").eq("price", Value::INTEGER(2))), Vec::<String>::new());
+    assert_eq!(ids(db.query("prices").le("price", Value::BIGINT(2))), vec!["1", "2"]);
+
+    // The same with the floating point value on the left, and NaN compares as UNKNOWN
+    let row = [Value::DOUBLE(1.5), Value::DECIMAL(Decimal::parse("1.5", 2).unwrap())];
+    assert!(Predicate::Eq(1, Value::DOUBLE(1.5)).matches(&row));
+    assert!(Predicate::Lt(0, Value::DECIMAL(Decimal::parse("1.51", 2).unwrap())).matches(&row));
+    assert_eq!(Predicate::Eq(1, Value::DOUBLE(f64::NAN)).evaluate(&row), None);
+}
+
+#[test]
+fn decimals_round_half_away_from_zero_to_their_scale() {
+    for (text, scale, expected) in [
+        ("1.005", 2, "1.01"),
+        ("-1.005", 2, "-1.01"),
+        ("2.004", 2, "2.00"),
+        ("0.5", 0, "1"),
+        ("-0.5", 0, "-1"),
+        ("7", 3, "7.000"),
+        (".25", 1, "0.3"),
+    ] {
+        assert_eq!(Decimal::parse(text, scale).unwrap().to_string(), expected, "{} at scale {}", text, scale);
+    }
+    for text in ["", "-", "1.2.3", "1e5", "abc"] {
+        assert!(Decimal::parse(text, 2).is_err(), "{}", text);
+    }
+
+    let dir = TempDir::new("types-decimal-scale");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("amounts", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("amount".to_string(), DataType::DECIMAL(6, 2), false),
+    ]).unwrap();
+    let amount = |text: &str, scale: u8| vec![Value::INTEGER(1), Value::DECIMAL(Decimal::parse(text, scale).unwrap())];
+    // A value with more fractional digits than the column is rounded to the column's scale
+    db.insert("amounts", 1, amount("12.345", 3)).unwrap();
+    assert_eq!(db.get("amounts", 1).unwrap().unwrap()[1].to_string(), "12.35");
+    // More digits than the precision, before or after rounding, is refused
+    for (text, scale) in [("12345.67", 2), ("9999.995", 3), ("1000000", 0)] {
+        let result = db.insert("amounts", 2, amount(text, scale));
+        assert!(matches!(result, Err(Error::ConstraintViolation(_))), "{}: {:?}", text, result);
+    }
+    db.insert("amounts", 2, amount("-9999.99", 2)).unwrap();
+    assert!(matches!(
+        db.create_table("wide", vec![Column::new("d".to_string(), DataType::DECIMAL(4, 5), false)]),
+        Err(Error::SchemaMismatch(_))
+    ));
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    let amounts: Vec<String> = db.scan("amounts").unwrap().iter().map(|row| row[1].to_string()).collect();
+    assert_eq!(amounts, vec!["12.35", "-9999.99"]);
+}
+
+#[test]
+fn every_type_reads_back_after_a_restart() {
+    let dir = TempDir::new("types-restart");
+    let db = Database::open(&dir, Options::default()).unwrap();
+    db.create_table("events", vec![
+        Column::new("id".to_string(), DataType::INTEGER, true),
+        Column::new("done".to_string(), DataType::BOOLEAN, false),
+        Column::new("day".to_string(), DataType::DATE, false),
+        Column::new("at".to_string(), DataType::TIMESTAMP, false),
+        Column::new("cost".to_string(), DataType::DECIMAL(10, 4), false),
+    ]).unwrap();
+    let rows = [
+        (1, "true", "1969-12-31", "1969-12-31 23:59:59.000001", "-0.0001"),
+        (2, "f", "2000-02-29", "2000-02-29T12:30:00", "123456.7890"),
+        (3, "1", "0001-01-01", "0001-01-01 00:00:00", "0"),
+        (4, "false", "9999-12-31", "9999-12-31 23:59:59.999999", "999999.9999"),
+    ];
+    let columns = db.columns("events").unwrap();
+    for (id, done, day, at, cost) in rows {
+        let mut values = vec![Value::INTEGER(id)];
+        for (column, text) in columns[1..].iter().zip([done, day, at, cost]) {
+            values.push(column.column_type.parse_value(text).unwrap());
+        }
+        db.insert("events", id, values).unwrap();
+    }
+    db.save().unwrap();
+    drop(db);
+
+    let db = Database::open(&dir, Options::default()).unwrap();
+    let text: Vec<Vec<String>> = db.scan("events").unwrap().iter()
+        .map(|row| row.iter().map(|value| value.to_string()).collect())
+        .collect();
+    assert_eq!(text, vec![
+        vec!["1", "true", "1969-12-31", "1969-12-31 23:59:59.000001", "-0.0001"],
+        vec!["2", "false", "2000-02-29", "2000-02-29 12:30:00", "123456.7890"],
+        vec!["3", "true", "0001-01-01", "0001-01-01 00:00:00", "0.0000"],
+        vec!["4", "false", "9999-12-31", "9999-12-31 23:59:59.999999", "999999.9999"],
+    ]);
+
+    // A DATE is midnight when compared with a TIMESTAMP
+    let ids = |query: oxidedb::Query| -> Vec<String> {
+        query.run().unwrap().iter().map(|row| row["id"].to_string()).collect()
+    };
+    let noon = Value::TIMESTAMP(Timestamp::parse("2000-02-29 12:00:00").unwrap());
+    assert_eq!(ids(db.query("events").lt("day", noon.clone())), vec!["1", "2", "3"]);
+    assert_eq!(ids(db.query("events").gt("at", noon)), vec!["2", "4"]);
+    assert_eq!(ids(db.query("events").eq("done", Value::BOOLEAN(true))), vec!["1", "3"]);
+    assert!(DataType::DATE.parse_value("2001-02-29").is_err());
+    assert!(DataType::BOOLEAN.parse_value("yes").is_err());
+}